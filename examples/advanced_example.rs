@@ -83,12 +83,9 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     info!("Logging level set to: {}", log_level);
 
     // Create clients - either with stored auth or by pairing
-    let clients = if pairing_code.is_some() {
-        info!(
-            "Pairing with new screen using code: {}",
-            pairing_code.as_ref().unwrap()
-        );
-        match create_client_with_pairing(pairing_code.as_ref().unwrap()).await {
+    let clients = if let Some(code) = &pairing_code {
+        info!("Pairing with new screen using code: {}", code);
+        match create_client_with_pairing(code).await {
             Ok(client) => vec![client],
             Err(e) => {
                 error!("Failed to pair with screen: {}", e);
@@ -188,11 +185,11 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                 debug!("Received event: {:?}", event);
                 match event {
                     LoungeEvent::NowPlaying(np) => {
-                        if let Some(video_data) = &np.video_data {
+                        if let Some(title) = np.video_data.as_ref().and_then(|d| d.title()) {
                             info!(
                                 "[{}] Now playing: {} ({}) - State: {} ({})",
                                 screen_id_clone,
-                                video_data.title,
+                                title,
                                 np.video_id,
                                 np.state,
                                 np.status()
@@ -227,11 +224,11 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                             0
                         };
 
-                        if let Some(video_data) = &session.video_data {
+                        if let Some(title) = session.video_data.as_ref().and_then(|d| d.title()) {
                             info!(
                                 "[{}] Playback Session - {} ({}) - {}s / {}s [{}%] - State: {} ({})",
                                 screen_id_clone,
-                                video_data.title,
+                                title,
                                 session.video_id,
                                 session.current_time,
                                 session.duration,
@@ -269,6 +266,12 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                             screen_id_clone
                         );
                     }
+                    LoungeEvent::SessionResumed { video_id, position } => {
+                        info!(
+                            "[{}] Session resumed - was playing {} at {:.1}s",
+                            screen_id_clone, video_id, position
+                        );
+                    }
                     LoungeEvent::AdPlaying(state) => {
                         info!(
                             "[{}] Ad playing - State: {}, Skippable {}, SkipEnabled: {}, Duration: {}",
@@ -289,6 +292,14 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                             screen_id_clone, state.video_id
                         );
                     }
+                    LoungeEvent::SubtitlesTrackList { video_id, tracks } => {
+                        info!(
+                            "[{}] {} subtitle tracks for video: {}",
+                            screen_id_clone,
+                            tracks.len(),
+                            video_id
+                        );
+                    }
                     LoungeEvent::AudioTrackChanged(state) => {
                         info!(
                             "[{}] Audio track changed to: {} for video: {}",
@@ -328,6 +339,14 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                             state.is_muted()
                         );
                     }
+                    LoungeEvent::SeekableRangeChanged(range) => {
+                        info!(
+                            "[{}] Seekable range: {:.1}s - {:.1}s",
+                            screen_id_clone,
+                            range.start(),
+                            range.end()
+                        );
+                    }
                     LoungeEvent::PlaylistModified(state) => {
                         info!(
                             "[{}] Playlist modified - List ID: {}, Video ID: {}",
@@ -355,7 +374,7 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                         if let Some(qid) = queue_id {
                             debug!("[{}] Queue ID: {}", screen_id_clone, qid);
                         }
-                        for device in devices {
+                        for device in devices.iter() {
                             info!(
                                 "[{}]   Device: {} ({})",
                                 screen_id_clone, device.name, device.device_type
@@ -368,9 +387,20 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                             }
                         }
                     }
+                    LoungeEvent::PlaybackError(error) => {
+                        warn!(
+                            "[{}] Playback error: code={} video={}",
+                            screen_id_clone,
+                            error.error_code,
+                            error.video_id.as_deref().unwrap_or("-")
+                        );
+                    }
                     LoungeEvent::Unknown(event_info) => {
                         warn!("[{}] Unknown event: {}", screen_id_clone, event_info);
                     }
+                    LoungeEvent::Raw(raw) => {
+                        debug!("[{}] Raw event: {}", screen_id_clone, raw);
+                    }
                 }
             }
         });
@@ -439,10 +469,10 @@ fn update_token_in_auth_store(
                 "[{}] Failed to acquire lock for auth file: {}",
                 screen_id, e
             );
-            return Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Lock acquisition failed: {}", e),
-            )));
+            return Err(Box::new(io::Error::other(format!(
+                "Lock acquisition failed: {}",
+                e
+            ))));
         }
     };
 
@@ -737,10 +767,7 @@ fn save_auth(auth: &AuthStore) -> io::Result<()> {
         Ok(guard) => guard,
         Err(e) => {
             error!("Failed to acquire lock for auth file: {}", e);
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Lock acquisition failed: {}", e),
-            ));
+            return Err(io::Error::other(format!("Lock acquisition failed: {}", e)));
         }
     };
 
@@ -811,10 +838,7 @@ fn load_auth() -> io::Result<AuthStore> {
         Ok(guard) => guard,
         Err(e) => {
             error!("Failed to acquire lock for auth file: {}", e);
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Lock acquisition failed: {}", e),
-            ));
+            return Err(io::Error::other(format!("Lock acquisition failed: {}", e)));
         }
     };
 
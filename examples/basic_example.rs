@@ -1,51 +1,15 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, Read, Write};
-use std::path::Path;
+use std::io;
+use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 use tracing::{debug, error, info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
-
-use youtube_lounge_rs::{LoungeClient, LoungeEvent, PlaybackCommand, Screen};
-
-// Structure to store screen authentication data
-#[derive(Serialize, Deserialize, Default)]
-struct AuthStore {
-    screens: HashMap<String, StoredScreen>,
-}
-
-// Screen information to store
-#[derive(Serialize, Deserialize, Clone)]
-struct StoredScreen {
-    name: Option<String>,
-    screen_id: String,
-    lounge_token: String,
-    device_id: String,
-    device_name: Option<String>,
-    #[serde(default = "default_true")]
-    enabled: bool,
-}
-
-fn default_true() -> bool {
-    true
-}
-
-impl From<&Screen> for StoredScreen {
-    fn from(screen: &Screen) -> Self {
-        StoredScreen {
-            name: screen.name.clone(),
-            screen_id: screen.screen_id.clone(),
-            lounge_token: screen.lounge_token.clone(),
-            device_id: String::new(), // Will be set after client creation
-            device_name: None,
-            enabled: true,
-        }
-    }
-}
+use youtube_lounge_rs::{
+    JsonFileTokenStore, LoungeClient, LoungeEvent, PlaybackCommand, StoredScreen, TokenStore,
+};
 
 const AUTH_FILENAME: &str = "youtube_auth.json";
+const DEVICE_NAME: &str = "Rust YouTube Controller";
 
 /// A simple example showing how to use the YouTube Lounge API with persistence
 #[tokio::main]
@@ -75,12 +39,9 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     info!("Logging level set to: {}", log_level);
 
     // Create a client - either with stored auth or by pairing
-    let client = if pairing_code.is_some() {
-        info!(
-            "Pairing with new screen using code: {}",
-            pairing_code.as_ref().unwrap()
-        );
-        match create_client_with_pairing(pairing_code.as_ref().unwrap()).await {
+    let client = if let Some(code) = &pairing_code {
+        info!("Pairing with new screen using code: {}", code);
+        match create_client_with_pairing(code).await {
             Ok(client) => client,
             Err(e) => {
                 error!("Failed to pair with screen: {}", e);
@@ -162,11 +123,11 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             debug!("Received event: {:?}", event);
             match event {
                 LoungeEvent::NowPlaying(np) => {
-                    if let Some(video_data) = &np.video_data {
+                    if let Some(title) = np.video_data.as_ref().and_then(|d| d.title()) {
                         info!(
                             "[{}] Now playing: {} ({}) - State: {} ({})",
                             screen_id_clone,
-                            video_data.title,
+                            title,
                             np.video_id,
                             np.state,
                             np.status()
@@ -201,11 +162,11 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                         0
                     };
 
-                    if let Some(video_data) = &session.video_data {
+                    if let Some(title) = session.video_data.as_ref().and_then(|d| d.title()) {
                         info!(
                             "[{}] Playback Session - {} ({}) - {}s / {}s [{}%] - State: {} ({})",
                             screen_id_clone,
-                            video_data.title,
+                            title,
                             session.video_id,
                             session.current_time,
                             session.duration,
@@ -243,6 +204,12 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                         screen_id_clone
                     );
                 }
+                LoungeEvent::SessionResumed { video_id, position } => {
+                    info!(
+                        "[{}] Session resumed - was playing {} at {:.1}s",
+                        screen_id_clone, video_id, position
+                    );
+                }
                 LoungeEvent::AdPlaying(state) => {
                     info!(
                         "[{}] Ad playing - State: {}, Skippable {}, SkipEnabled: {}, Duration: {}",
@@ -267,6 +234,14 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                         screen_id_clone, state.video_id
                     );
                 }
+                LoungeEvent::SubtitlesTrackList { video_id, tracks } => {
+                    info!(
+                        "[{}] {} subtitle tracks for video: {}",
+                        screen_id_clone,
+                        tracks.len(),
+                        video_id
+                    );
+                }
                 LoungeEvent::AudioTrackChanged(state) => {
                     info!(
                         "[{}] Audio track changed to: {} for video: {}",
@@ -306,6 +281,14 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                         state.is_muted()
                     );
                 }
+                LoungeEvent::SeekableRangeChanged(range) => {
+                    info!(
+                        "[{}] Seekable range: {:.1}s - {:.1}s",
+                        screen_id_clone,
+                        range.start(),
+                        range.end()
+                    );
+                }
                 LoungeEvent::PlaylistModified(state) => {
                     info!(
                         "[{}] Playlist modified - List ID: {}, Video ID: {}",
@@ -333,7 +316,7 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                     if let Some(qid) = queue_id {
                         debug!("[{}] Queue ID: {}", screen_id_clone, qid);
                     }
-                    for device in devices {
+                    for device in devices.iter() {
                         info!(
                             "[{}]   Device: {} ({})",
                             screen_id_clone, device.name, device.device_type
@@ -346,9 +329,20 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
                         }
                     }
                 }
+                LoungeEvent::PlaybackError(error) => {
+                    warn!(
+                        "[{}] Playback error: code={} video={}",
+                        screen_id_clone,
+                        error.error_code,
+                        error.video_id.as_deref().unwrap_or("-")
+                    );
+                }
                 LoungeEvent::Unknown(event_info) => {
                     warn!("[{}] Unknown event: {}", screen_id_clone, event_info);
                 }
+                LoungeEvent::Raw(raw) => {
+                    debug!("[{}] Raw event: {}", screen_id_clone, raw);
+                }
             }
         }
     });
@@ -434,26 +428,18 @@ async fn create_client_with_pairing(
     let client = LoungeClient::new(
         &screen.screen_id,
         &screen.lounge_token,
-        "Rust YouTube Controller",
+        DEVICE_NAME,
         None,
         None, // Explicitly pass None to use the default client
     );
 
-    // Store auth data for next time
-    let mut auth_store = load_auth().unwrap_or_default();
-    let mut stored_screen = StoredScreen::from(&screen);
-
-    // Set the device ID from client
-    stored_screen.device_id = client.device_id().to_string();
-    stored_screen.device_name = Some("Rust YouTube Controller".to_string());
-
-    // Add the screen to the map using screen_id as key
-    auth_store
-        .screens
-        .insert(screen.screen_id.clone(), stored_screen);
-
-    // Save to disk
-    save_auth(&auth_store)?;
+    // Store auth data for next time; the client keeps it up to date when
+    // it refreshes the token
+    let store = Arc::new(JsonFileTokenStore::new(AUTH_FILENAME));
+    let mut stored_screen = StoredScreen::new(&screen, client.device_id());
+    stored_screen.device_name = Some(DEVICE_NAME.to_string());
+    store.save(&stored_screen)?;
+    client.set_token_store(store).await;
     debug!("Auth data saved to {} for next time", AUTH_FILENAME);
 
     Ok(client)
@@ -461,159 +447,31 @@ async fn create_client_with_pairing(
 
 // Create client from stored auth
 async fn create_client_from_stored_auth() -> Result<LoungeClient, Box<dyn Error + Send + Sync>> {
-    // Load auth data from file
-    let auth_store = match load_auth() {
-        Ok(store) => store,
-        Err(e) => {
-            error!("Failed to load authentication data: {}", e);
-            return Err(Box::new(e));
-        }
-    };
-
-    // Get stored screen info - use the first screen we find
-    if auth_store.screens.is_empty() {
-        let err = io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Auth file exists but contains no screens",
-        );
-        error!("No screens found in auth file");
-        return Err(Box::new(err));
-    }
-
-    let screen_entry = auth_store.screens.iter().next().unwrap();
-    let stored_screen = screen_entry.1;
-
-    // Check if screen data looks valid
-    if stored_screen.screen_id.trim().is_empty() {
-        let err = io::Error::new(io::ErrorKind::InvalidData, "Stored screen_id is empty");
-        error!("Invalid screen data in auth file: empty screen_id");
-        return Err(Box::new(err));
-    }
-
-    if stored_screen.lounge_token.trim().is_empty() {
-        let err = io::Error::new(io::ErrorKind::InvalidData, "Stored lounge_token is empty");
-        error!("Invalid screen data in auth file: empty lounge_token");
-        return Err(Box::new(err));
-    }
+    let store = Arc::new(JsonFileTokenStore::new(AUTH_FILENAME));
 
-    if stored_screen.device_id.trim().is_empty() {
-        let err = io::Error::new(io::ErrorKind::InvalidData, "Stored device_id is empty");
-        error!("Invalid screen data in auth file: empty device_id");
-        return Err(Box::new(err));
-    }
+    // Use the first screen we find
+    let Some(stored_screen) = store.screens()?.into_iter().next() else {
+        warn!("No screens found in auth file '{}'", AUTH_FILENAME);
+        return Err(Box::new(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No stored screens, pair with a screen first",
+        )));
+    };
 
-    // Create client with stored device ID
-    let client = LoungeClient::new(
+    // Create client with the stored device ID, saving refreshed tokens back
+    let client = LoungeClient::from_token_store(
+        store,
         &stored_screen.screen_id,
-        &stored_screen.lounge_token,
-        stored_screen
-            .device_name
-            .as_deref()
-            .unwrap_or("Rust YouTube Controller"),
-        Some(stored_screen.device_id.as_str()),
-        None, // Explicitly pass None to use the default client
-    );
+        stored_screen.device_name.as_deref().unwrap_or(DEVICE_NAME),
+    )?
+    .expect("screen was just listed");
 
     info!(
         "Using stored screen: {} (ID: {})",
         stored_screen.name.as_deref().unwrap_or("Unknown"),
         stored_screen.screen_id
     );
-    debug!("Using lounge token: {}", stored_screen.lounge_token);
-    debug!("Using device ID: {}", stored_screen.device_id);
+    debug!("Using device ID: {}", client.device_id());
 
     Ok(client)
 }
-
-// Save auth data to file
-fn save_auth(auth: &AuthStore) -> io::Result<()> {
-    // Generate JSON
-    let json = match serde_json::to_string_pretty(auth) {
-        Ok(j) => j,
-        Err(e) => {
-            error!("Failed to serialize auth data: {}", e);
-            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
-        }
-    };
-
-    debug!("Serialized auth data to JSON, size: {} bytes", json.len());
-
-    // Create file
-    let mut file = match File::create(AUTH_FILENAME) {
-        Ok(f) => f,
-        Err(e) => {
-            error!("Failed to create auth file '{}': {}", AUTH_FILENAME, e);
-            return Err(e);
-        }
-    };
-
-    // Write data
-    match file.write_all(json.as_bytes()) {
-        Ok(_) => {
-            info!("Successfully saved auth data to '{}'", AUTH_FILENAME);
-            Ok(())
-        }
-        Err(e) => {
-            error!(
-                "Failed to write data to auth file '{}': {}",
-                AUTH_FILENAME, e
-            );
-            Err(e)
-        }
-    }
-}
-
-// Load auth data from file
-fn load_auth() -> io::Result<AuthStore> {
-    let path = Path::new(AUTH_FILENAME);
-    if !path.exists() {
-        warn!("Auth file '{}' does not exist", AUTH_FILENAME);
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("Auth file '{}' does not exist", AUTH_FILENAME),
-        ));
-    }
-
-    // Check if file is readable
-    if let Ok(metadata) = path.metadata() {
-        debug!("Auth file size: {} bytes", metadata.len());
-    } else {
-        error!("Cannot read metadata for auth file '{}'", AUTH_FILENAME);
-    }
-
-    // Open and read file
-    let mut file = match File::open(AUTH_FILENAME) {
-        Ok(f) => f,
-        Err(e) => {
-            error!("Failed to open auth file '{}': {}", AUTH_FILENAME, e);
-            return Err(e);
-        }
-    };
-
-    let mut contents = String::new();
-    match file.read_to_string(&mut contents) {
-        Ok(bytes) => debug!("Read {} bytes from auth file", bytes),
-        Err(e) => {
-            error!("Failed to read auth file '{}': {}", AUTH_FILENAME, e);
-            return Err(e);
-        }
-    }
-
-    // Parse JSON
-    match serde_json::from_str(&contents) {
-        Ok(auth) => {
-            debug!("Successfully parsed auth data from '{}'", AUTH_FILENAME);
-            Ok(auth)
-        }
-        Err(e) => {
-            error!("Failed to parse auth file '{}': {}", AUTH_FILENAME, e);
-            // Preview the file content for debugging
-            if contents.len() > 100 {
-                error!("File content preview: {}...", &contents[..100]);
-            } else {
-                error!("File content: {}", contents);
-            }
-            Err(io::Error::new(io::ErrorKind::InvalidData, e))
-        }
-    }
-}
@@ -0,0 +1,174 @@
+// Background automation driven by the event stream (e.g. skipping past broken
+// videos, priming state after a bind) and by timers (keep-alives).
+// Runs as its own task so command round-trips never stall the long-poll reader.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use crate::player::ResumePoint;
+use crate::rt;
+use crate::state::ConnectionState;
+use crate::{CommandContext, LoungeEvent, PlaybackCommand, PlaybackStatus};
+
+/// Tracks automatic skips so a run of unavailable videos can't cause a skip storm.
+#[derive(Debug, Default)]
+struct AutoSkipState {
+    consecutive_skips: u32,
+    last_skipped_video: Option<String>,
+}
+
+/// `enabled` is the runtime switch from [`crate::LoungeClient::set_automation_enabled`];
+/// while off, events are still consumed but no playback action is taken.
+pub(crate) async fn run(
+    mut rx: broadcast::Receiver<LoungeEvent>,
+    commands: CommandContext,
+    enabled: Arc<AtomicBool>,
+) {
+    let mut auto_skip = AutoSkipState::default();
+
+    // The first session was established before this task started
+    if commands.config.request_initial_state {
+        request_initial_state(&commands).await;
+    }
+
+    let mut keepalive = commands
+        .config
+        .keepalive_interval
+        .map(|period| rt::interval_at(rt::Instant::now() + period, period));
+
+    loop {
+        let received = tokio::select! {
+            received = rx.recv() => received,
+            _ = next_tick(&mut keepalive) => {
+                send_keepalive(&commands).await;
+                continue;
+            }
+        };
+        let event = match received {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!(
+                    "Automation lagged behind event stream, skipped {} events",
+                    n
+                );
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        match event {
+            LoungeEvent::PlaybackError(error) if commands.config.auto_skip_on_error => {
+                // The TV may report the same failure more than once; only skip it once
+                if error.video_id.is_some() && error.video_id == auto_skip.last_skipped_video {
+                    debug!(video_id = ?error.video_id, "Already skipped this video, ignoring");
+                    continue;
+                }
+                if !enabled.load(Ordering::SeqCst) {
+                    debug!(video_id = ?error.video_id, "Automation paused, not auto-skipping");
+                    continue;
+                }
+                if auto_skip.consecutive_skips >= commands.config.max_consecutive_auto_skips {
+                    warn!(
+                        "Playback error (code {}) but {} consecutive auto-skips reached, not skipping",
+                        error.error_code, auto_skip.consecutive_skips
+                    );
+                    continue;
+                }
+
+                info!(
+                    video_id = ?error.video_id,
+                    "Playback error (code {}), auto-skipping to next video", error.error_code
+                );
+                auto_skip.consecutive_skips += 1;
+                auto_skip.last_skipped_video = error.video_id;
+                if let Err(e) = commands.send(PlaybackCommand::Next).await {
+                    warn!(error = %e, "Auto-skip failed to send Next");
+                }
+            }
+            LoungeEvent::SessionEstablished if commands.config.request_initial_state => {
+                request_initial_state(&commands).await;
+            }
+            LoungeEvent::StateChange(state) if state.status() == PlaybackStatus::Playing => {
+                auto_skip = AutoSkipState::default();
+            }
+            LoungeEvent::NowPlaying(np) if np.status() == PlaybackStatus::Playing => {
+                auto_skip = AutoSkipState::default();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Commands are rejected until the manager has confirmed a new session.
+/// Returns `false` if the manager is gone.
+async fn wait_until_connected(commands: &CommandContext) -> bool {
+    let mut state_rx = commands.state_rx.clone();
+    let connected = state_rx
+        .wait_for(|state| *state == ConnectionState::Connected)
+        .await
+        .is_ok();
+    connected
+}
+
+/// Send the getters that make the TV report its current state.
+async fn request_initial_state(commands: &CommandContext) {
+    if !wait_until_connected(commands).await {
+        return;
+    }
+
+    debug!("Requesting initial state from screen");
+    for command in [
+        PlaybackCommand::GetNowPlaying,
+        PlaybackCommand::GetVolume,
+        PlaybackCommand::GetAutoplayMode,
+    ] {
+        if let Err(e) = commands.send(command).await {
+            warn!(error = %e, "Failed to request initial state");
+            break;
+        }
+    }
+}
+
+/// Start the video that was playing when the session was lost again, at the
+/// position it had reached.
+pub(crate) async fn resume_playback(commands: CommandContext, point: ResumePoint) {
+    if !wait_until_connected(&commands).await {
+        return;
+    }
+    info!(video_id = %point.video_id, position = point.position, "Resuming playback");
+    let command = PlaybackCommand::SetPlaylist {
+        video_id: point.video_id,
+        list_id: point.list_id,
+        current_index: Some(-1),
+        current_time: Some(point.position),
+        audio_only: Some(false),
+        params: None,
+        player_params: None,
+    };
+    if let Err(e) = commands.send(command).await {
+        warn!(error = %e, "Failed to resume playback");
+    }
+}
+
+async fn next_tick(interval: &mut Option<rt::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Send a cheap getter so the TV doesn't drop an otherwise idle session.
+async fn send_keepalive(commands: &CommandContext) {
+    if *commands.state_rx.borrow() != ConnectionState::Connected {
+        return;
+    }
+    debug!("Sending keep-alive");
+    if let Err(e) = commands.send(PlaybackCommand::GetNowPlaying).await {
+        warn!(error = %e, "Keep-alive command failed");
+    }
+}
@@ -0,0 +1,71 @@
+// A synchronous facade over `LoungeClient`, for callers without an async runtime.
+
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+use crate::{LoungeClient, LoungeError, LoungeEvent, PlaybackCommand};
+
+/// A blocking wrapper around [`LoungeClient`], in the spirit of
+/// `reqwest::blocking`. It owns a Tokio runtime that drives the connection in
+/// the background, so events keep arriving between calls.
+///
+/// Must not be created, used or dropped from within an async context.
+pub struct BlockingLoungeClient {
+    runtime: Runtime,
+    client: LoungeClient,
+    events: broadcast::Receiver<LoungeEvent>,
+}
+
+impl BlockingLoungeClient {
+    /// Wrap a configured client. Events are buffered from this point on.
+    pub fn new(client: LoungeClient) -> Result<Self, LoungeError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let events = client.event_receiver();
+        Ok(Self {
+            runtime,
+            client,
+            events,
+        })
+    }
+
+    /// The wrapped async client, for accessors not mirrored here.
+    pub fn inner(&self) -> &LoungeClient {
+        &self.client
+    }
+
+    /// See [`LoungeClient::connect`].
+    pub fn connect(&self) -> Result<(), LoungeError> {
+        self.runtime.block_on(self.client.connect())
+    }
+
+    /// See [`LoungeClient::disconnect`].
+    pub fn disconnect(&self) -> Result<(), LoungeError> {
+        self.runtime.block_on(self.client.disconnect())
+    }
+
+    /// See [`LoungeClient::send_command`].
+    pub fn send_command(&self, command: PlaybackCommand) -> Result<(), LoungeError> {
+        self.runtime.block_on(self.client.send_command(command))
+    }
+
+    /// Block until the next event arrives. Returns `None` once the client is
+    /// gone and no more events can arrive.
+    pub fn recv_event(&mut self) -> Option<LoungeEvent> {
+        loop {
+            match self.runtime.block_on(self.events.recv()) {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        skipped,
+                        "Blocking event receiver lagged, events were dropped"
+                    )
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// Builder for clients that need more than `LoungeClient::new` offers.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+
+use crate::{LoungeClient, LoungeConfig, LoungeError, Metrics, TokenStore};
+
+/// Builds a [`LoungeClient`] with custom HTTP and connection settings. Created
+/// with [`LoungeClient::builder`]; anything not set keeps its default.
+pub struct LoungeClientBuilder {
+    screen_id: String,
+    lounge_token: String,
+    device_name: String,
+    device_id: Option<String>,
+    http_client: Option<Arc<Client>>,
+    user_agent: Option<String>,
+    token_store: Option<Arc<dyn TokenStore>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    config: LoungeConfig,
+}
+
+impl LoungeClientBuilder {
+    pub(crate) fn new(screen_id: &str, lounge_token: &str, device_name: &str) -> Self {
+        Self {
+            screen_id: screen_id.to_string(),
+            lounge_token: lounge_token.to_string(),
+            device_name: device_name.to_string(),
+            device_id: None,
+            http_client: None,
+            user_agent: None,
+            token_store: None,
+            metrics: None,
+            config: LoungeConfig::default(),
+        }
+    }
+
+    /// Reuse a device ID, so the screen recognises this remote across restarts.
+    /// A new UUID is generated otherwise.
+    pub fn device_id(mut self, device_id: &str) -> Self {
+        self.device_id = Some(device_id.to_string());
+        self
+    }
+
+    /// Send requests through this client instead of a default one, e.g. to
+    /// share a connection pool. Takes precedence over [`Self::user_agent`].
+    pub fn http_client(mut self, client: Arc<Client>) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// The `User-Agent` of the default HTTP client.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// See [`LoungeClient::set_token_store`].
+    pub fn token_store(mut self, store: Arc<dyn TokenStore>) -> Self {
+        self.token_store = Some(store);
+        self
+    }
+
+    /// See [`LoungeClient::set_metrics`].
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Start from this config instead of the default; the setters below
+    /// override single fields of it.
+    pub fn config(mut self, config: LoungeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// See [`LoungeConfig::inactivity_timeout`].
+    pub fn inactivity_timeout(mut self, timeout: Duration) -> Self {
+        self.config.inactivity_timeout = timeout;
+        self
+    }
+
+    /// See [`LoungeConfig::long_poll_timeout`].
+    pub fn long_poll_timeout(mut self, timeout: Duration) -> Self {
+        self.config.long_poll_timeout = timeout;
+        self
+    }
+
+    /// See [`LoungeConfig::min_backoff`].
+    pub fn min_backoff(mut self, backoff: Duration) -> Self {
+        self.config.min_backoff = backoff;
+        self
+    }
+
+    /// See [`LoungeConfig::max_backoff`].
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.config.max_backoff = backoff;
+        self
+    }
+
+    /// See [`LoungeConfig::proxy`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// See [`LoungeConfig::min_command_interval`].
+    pub fn min_command_interval(mut self, interval: Duration) -> Self {
+        self.config.min_command_interval = Some(interval);
+        self
+    }
+
+    /// See [`LoungeConfig::command_coalesce_window`].
+    pub fn command_coalesce_window(mut self, window: Duration) -> Self {
+        self.config.command_coalesce_window = Some(window);
+        self
+    }
+
+    /// See [`LoungeConfig::reaper`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reaper(mut self, runtime: tokio::runtime::Handle) -> Self {
+        self.config.reaper = Some(runtime);
+        self
+    }
+
+    /// See [`LoungeConfig::event_channel_capacity`].
+    pub fn event_channel_capacity(mut self, capacity: usize) -> Self {
+        self.config.event_channel_capacity = capacity;
+        self
+    }
+
+    /// Create the client. Fails if the default HTTP client can't be built.
+    pub fn build(self) -> Result<LoungeClient, LoungeError> {
+        let http_client = match self.http_client {
+            Some(client) => client,
+            None => {
+                let mut builder = LoungeClient::http_client_builder(&self.config);
+                if let Some(user_agent) = &self.user_agent {
+                    builder = builder.user_agent(user_agent);
+                }
+                Arc::new(builder.build()?)
+            }
+        };
+        let client = LoungeClient::new(
+            &self.screen_id,
+            &self.lounge_token,
+            &self.device_name,
+            self.device_id.as_deref(),
+            Some(http_client),
+        )
+        // The HTTP client was built for this config, or supplied by the caller
+        .apply_config(self.config);
+        let client = match self.token_store {
+            Some(store) => client.with_token_store(store),
+            None => client,
+        };
+        Ok(match self.metrics {
+            Some(metrics) => client.with_metrics(metrics),
+            None => client,
+        })
+    }
+}
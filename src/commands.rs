@@ -1,3 +1,5 @@
+use crate::{LoungeEvent, PlaybackStatus};
+
 // Playback Command Enum
 #[derive(Debug, Clone)]
 pub enum PlaybackCommand {
@@ -19,20 +21,174 @@ pub enum PlaybackCommand {
         video_id: String,
         video_sources: Option<String>,
     },
+    /// Move a queued video to a new zero-based position in the queue.
+    MoveVideo {
+        video_id: String,
+        to_index: i32,
+    },
+    /// Remove a video from the queue.
+    RemoveVideo {
+        video_id: String,
+    },
+    /// Empty the queue.
+    ClearPlaylist,
     SeekTo {
         new_time: f64,
     },
     SetAutoplayMode {
         autoplay_mode: String,
     },
+    /// Repeat the playlist and/or play it in random order. Answered with
+    /// `onPlaylistModeChanged`.
+    SetPlaylistMode {
+        loop_enabled: bool,
+        shuffle_enabled: bool,
+    },
     SetVolume {
         volume: i32,
     },
     Mute,
     Unmute,
+    /// Ask the TV to report what it is playing (answered with `nowPlaying`).
+    GetNowPlaying,
+    /// Ask the TV to report its volume (answered with `onVolumeChanged`).
+    GetVolume,
+    /// Ask the TV to report its autoplay mode (answered with `onAutoplayModeChanged`).
+    GetAutoplayMode,
+    /// Ask the TV for the current video's subtitle tracks (answered with
+    /// `onSubtitlesTrackList`).
+    GetSubtitlesTrackList,
+    /// Show subtitles in `language_code` for `video_id`; an empty code turns
+    /// them off. Answered with `onSubtitlesTrackChanged`.
+    SetSubtitlesTrack {
+        video_id: String,
+        language_code: String,
+    },
+    /// Switch `video_id` to another audio track, by the ID reported in
+    /// `onAudioTrackChanged`.
+    SetAudioTrack {
+        video_id: String,
+        audio_track_id: String,
+    },
+    /// Play `video_id` at a quality level, e.g. 1080, out of those reported in
+    /// `onVideoQualityChanged`.
+    SetVideoQuality {
+        video_id: String,
+        quality_level: i32,
+    },
+    /// Press a key on the TV's remote control pad.
+    Dpad {
+        key: DpadKey,
+    },
+    /// Report the state of voice input on the remote, e.g. `start` or `stop`.
+    VoiceCommand {
+        status: String,
+    },
+    /// Stop playback and unload the video.
+    StopVideo,
+    /// Jump to the video at `index` (zero-based) of the current playlist.
+    SetPlaylistIndex {
+        index: i32,
+    },
+}
+
+/// How a command sent with [`crate::LoungeClient::send_command_ack`] turned out.
+#[derive(Debug, Clone)]
+pub enum CommandOutcome {
+    /// The TV reported the command's effect with this event.
+    Acknowledged(Box<LoungeEvent>),
+    /// The command was delivered, but the TV reported no matching change in time.
+    TimedOut,
+    /// The command was delivered, but the TV doesn't report its effect, so it
+    /// can't be confirmed (e.g. d-pad presses).
+    Unconfirmable,
 }
 
 impl PlaybackCommand {
+    /// Whether the TV reports this command's effect with an event.
+    pub fn is_confirmable(&self) -> bool {
+        !matches!(self, Self::Dpad { .. } | Self::VoiceCommand { .. })
+    }
+
+    /// Whether only the last of several such commands sent in quick
+    /// succession matters, so the others can be dropped. See
+    /// [`crate::LoungeConfig::command_coalesce_window`].
+    pub fn is_coalescable(&self) -> bool {
+        matches!(self, Self::SetVolume { .. } | Self::SeekTo { .. })
+    }
+
+    /// Whether `event` reports this command's effect, e.g. a paused state for
+    /// `Pause` or the requested level for `SetVolume`. `None` for commands
+    /// that aren't [confirmable](Self::is_confirmable).
+    pub fn is_acknowledged_by(&self, event: &LoungeEvent) -> Option<bool> {
+        if !self.is_confirmable() {
+            return None;
+        }
+        let status = match event {
+            LoungeEvent::StateChange(state) => Some(state.status()),
+            LoungeEvent::NowPlaying(np) => Some(np.status()),
+            _ => None,
+        };
+        let acknowledged = match self {
+            Self::Play => status == Some(PlaybackStatus::Playing),
+            Self::Pause => status == Some(PlaybackStatus::Paused),
+            Self::StopVideo => status == Some(PlaybackStatus::Stopped),
+            Self::SkipAd => status.is_some_and(|status| status != PlaybackStatus::Advertisement),
+            Self::SeekTo { .. } => matches!(event, LoungeEvent::StateChange(_)),
+            Self::SetPlaylist { video_id, .. } => matches!(
+                event,
+                LoungeEvent::NowPlaying(np) if video_id.is_empty() || np.video_id == *video_id
+            ),
+            Self::Next | Self::Previous | Self::SetPlaylistIndex { .. } | Self::GetNowPlaying => {
+                matches!(event, LoungeEvent::NowPlaying(_))
+            }
+            Self::AddVideo { .. }
+            | Self::MoveVideo { .. }
+            | Self::RemoveVideo { .. }
+            | Self::ClearPlaylist => matches!(event, LoungeEvent::PlaylistModified(_)),
+            Self::SetVolume { volume } => matches!(
+                event,
+                LoungeEvent::VolumeChanged(change) if change.volume_level() == *volume
+            ),
+            Self::Mute => {
+                matches!(event, LoungeEvent::VolumeChanged(change) if change.is_muted())
+            }
+            Self::Unmute => {
+                matches!(event, LoungeEvent::VolumeChanged(change) if !change.is_muted())
+            }
+            Self::GetVolume => matches!(event, LoungeEvent::VolumeChanged(_)),
+            Self::SetAutoplayMode { autoplay_mode } => matches!(
+                event,
+                LoungeEvent::AutoplayModeChanged(change) if change.autoplay_mode == *autoplay_mode
+            ),
+            Self::GetAutoplayMode => matches!(event, LoungeEvent::AutoplayModeChanged(_)),
+            Self::SetPlaylistMode {
+                loop_enabled,
+                shuffle_enabled,
+            } => matches!(
+                event,
+                LoungeEvent::PlaylistModeChanged(change)
+                    if change.is_loop_enabled() == *loop_enabled
+                        && change.is_shuffle_enabled() == *shuffle_enabled
+            ),
+            Self::GetSubtitlesTrackList => matches!(event, LoungeEvent::SubtitlesTrackList { .. }),
+            Self::SetSubtitlesTrack { video_id, .. } => matches!(
+                event,
+                LoungeEvent::SubtitlesTrackChanged(change) if change.video_id == *video_id
+            ),
+            Self::SetAudioTrack { video_id, .. } => matches!(
+                event,
+                LoungeEvent::AudioTrackChanged(change) if change.video_id == *video_id
+            ),
+            Self::SetVideoQuality { video_id, .. } => matches!(
+                event,
+                LoungeEvent::VideoQualityChanged(change) if change.video_id == *video_id
+            ),
+            Self::Dpad { .. } | Self::VoiceCommand { .. } => false,
+        };
+        Some(acknowledged)
+    }
+
     pub fn set_playlist(video_id: String) -> Self {
         PlaybackCommand::SetPlaylist {
             video_id,
@@ -69,6 +225,38 @@ impl PlaybackCommand {
         }
     }
 
+    /// Start a playlist at a specific video when its index isn't known; the
+    /// TV locates `video_id` within `list_id` itself.
+    pub fn set_playlist_from_video(list_id: String, video_id: String) -> Self {
+        PlaybackCommand::SetPlaylist {
+            video_id,
+            list_id: Some(list_id),
+            current_index: None,
+            current_time: Some(0.0),
+            audio_only: Some(false),
+            params: None,
+            player_params: None,
+        }
+    }
+
+    /// Start an endless radio mix seeded with `video_id`.
+    ///
+    /// Mixes are the auto-generated `RD<video_id>` playlists. `params` is the
+    /// base64 watch-endpoint payload the YouTube apps send for `start_radio=1`
+    /// (protobuf field 7 set to 1, i.e. `OAE=`); without it the TV plays only
+    /// the seed video instead of continuing the mix.
+    pub fn mix(video_id: String) -> Self {
+        PlaybackCommand::SetPlaylist {
+            list_id: Some(format!("RD{}", video_id)),
+            video_id,
+            current_index: Some(0),
+            current_time: Some(0.0),
+            audio_only: Some(false),
+            params: Some("OAE=".to_string()),
+            player_params: None,
+        }
+    }
+
     pub fn add_video(video_id: String) -> Self {
         PlaybackCommand::AddVideo {
             video_id,
@@ -76,6 +264,110 @@ impl PlaybackCommand {
         }
     }
 
+    /// Parameters sent with the command, without their `reqN_` prefix.
+    pub(crate) fn form_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        match self {
+            Self::SetPlaylist {
+                video_id,
+                list_id,
+                current_index,
+                current_time,
+                audio_only,
+                params,
+                player_params,
+            } => {
+                fields.push(("videoId", video_id.clone()));
+                if let Some(idx) = current_index {
+                    fields.push(("currentIndex", idx.to_string()));
+                }
+                if let Some(list) = list_id {
+                    fields.push(("listId", list.clone()));
+                }
+                if let Some(time) = current_time {
+                    fields.push(("currentTime", time.to_string()));
+                }
+                if let Some(audio) = audio_only {
+                    fields.push(("audioOnly", audio.to_string()));
+                }
+                if let Some(p) = params {
+                    fields.push(("params", p.clone()));
+                }
+                if let Some(pp) = player_params {
+                    fields.push(("playerParams", pp.clone()));
+                }
+                fields.push((
+                    "prioritizeMobileSenderPlaybackStateOnConnection",
+                    "true".to_string(),
+                ));
+            }
+            Self::AddVideo {
+                video_id,
+                video_sources,
+            } => {
+                fields.push(("videoId", video_id.clone()));
+                if let Some(sources) = video_sources {
+                    fields.push(("videoSources", sources.clone()));
+                }
+            }
+            Self::MoveVideo { video_id, to_index } => {
+                fields.push(("videoId", video_id.clone()));
+                fields.push(("index", to_index.to_string()));
+            }
+            Self::RemoveVideo { video_id } => {
+                fields.push(("videoId", video_id.clone()));
+            }
+            Self::SeekTo { new_time } => {
+                fields.push(("newTime", new_time.to_string()));
+            }
+            Self::SetVolume { volume } => {
+                fields.push(("volume", volume.to_string()));
+            }
+            Self::SetAutoplayMode { autoplay_mode } => {
+                fields.push(("autoplayMode", autoplay_mode.clone()));
+            }
+            Self::SetPlaylistMode {
+                loop_enabled,
+                shuffle_enabled,
+            } => {
+                fields.push(("loopEnabled", loop_enabled.to_string()));
+                fields.push(("shuffleEnabled", shuffle_enabled.to_string()));
+            }
+            Self::SetSubtitlesTrack {
+                video_id,
+                language_code,
+            } => {
+                fields.push(("videoId", video_id.clone()));
+                fields.push(("languageCode", language_code.clone()));
+            }
+            Self::SetAudioTrack {
+                video_id,
+                audio_track_id,
+            } => {
+                fields.push(("videoId", video_id.clone()));
+                fields.push(("audioTrackId", audio_track_id.clone()));
+            }
+            Self::SetVideoQuality {
+                video_id,
+                quality_level,
+            } => {
+                fields.push(("videoId", video_id.clone()));
+                fields.push(("qualityLevel", quality_level.to_string()));
+            }
+            Self::Dpad { key } => {
+                fields.push(("key", key.as_str().to_string()));
+            }
+            Self::VoiceCommand { status } => {
+                fields.push(("status", status.clone()));
+            }
+            Self::SetPlaylistIndex { index } => {
+                fields.push(("index", index.to_string()));
+            }
+            _ => {}
+        }
+        fields
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             Self::Play => "play",
@@ -85,11 +377,78 @@ impl PlaybackCommand {
             Self::SkipAd => "skipAd",
             Self::SetPlaylist { .. } => "setPlaylist",
             Self::AddVideo { .. } => "addVideo",
+            Self::MoveVideo { .. } => "moveVideo",
+            Self::RemoveVideo { .. } => "removeVideo",
+            Self::ClearPlaylist => "clearPlaylist",
             Self::SeekTo { .. } => "seekTo",
             Self::SetAutoplayMode { .. } => "setAutoplayMode",
+            Self::SetPlaylistMode { .. } => "setPlaylistMode",
             Self::SetVolume { .. } => "setVolume",
             Self::Mute => "mute",
             Self::Unmute => "unMute",
+            Self::GetNowPlaying => "getNowPlaying",
+            Self::GetVolume => "getVolume",
+            Self::GetAutoplayMode => "getAutoplayMode",
+            Self::GetSubtitlesTrackList => "getSubtitlesTrackList",
+            Self::SetSubtitlesTrack { .. } => "setSubtitlesTrack",
+            Self::SetAudioTrack { .. } => "setAudioTrack",
+            Self::SetVideoQuality { .. } => "setVideoQuality",
+            Self::Dpad { .. } => "dpadCommand",
+            Self::VoiceCommand { .. } => "voiceCommand",
+            Self::StopVideo => "stopVideo",
+            Self::SetPlaylistIndex { .. } => "setPlaylistIndex",
+        }
+    }
+}
+
+/// A key of the TV remote's directional pad, for [`PlaybackCommand::Dpad`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DpadKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Back,
+}
+
+impl DpadKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Up => "UP",
+            Self::Down => "DOWN",
+            Self::Left => "LEFT",
+            Self::Right => "RIGHT",
+            Self::Enter => "ENTER",
+            Self::Back => "BACK",
+        }
+    }
+}
+
+/// Reason reported to the TV when terminating a session. The TV only logs it,
+/// so unknown values are accepted via `Custom`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DisconnectReason {
+    /// The user chose to disconnect (what the official apps send).
+    #[default]
+    DisconnectedByUser,
+    /// The remote is switching to control a different screen.
+    ConnectedToAnotherScreen,
+    /// The controlling application is shutting down.
+    AppClosing,
+    /// Any other `MDX_SESSION_DISCONNECT_REASON_*` value.
+    Custom(String),
+}
+
+impl DisconnectReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::DisconnectedByUser => "MDX_SESSION_DISCONNECT_REASON_DISCONNECTED_BY_USER",
+            Self::ConnectedToAnotherScreen => {
+                "MDX_SESSION_DISCONNECT_REASON_CONNECTED_TO_ANOTHER_SCREEN"
+            }
+            Self::AppClosing => "MDX_SESSION_DISCONNECT_REASON_APP_CLOSED",
+            Self::Custom(reason) => reason,
         }
     }
 }
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use crate::{ErrorContext, LoungeError, SETTINGS};
+
+/// Default base URL of the YouTube Lounge API.
+pub const DEFAULT_API_BASE: &str = "https://www.youtube.com/api/lounge";
+
+/// What an event stream from [`crate::LoungeClient::events`] does when its
+/// reader falls so far behind that events were dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LagPolicy {
+    /// Log how many events were missed and carry on with the oldest kept one.
+    #[default]
+    Skip,
+    /// End the stream, for readers that can't tolerate gaps.
+    End,
+}
+
+/// Per-client behaviour options. Unlike [`crate::SETTINGS`], which is read once
+/// from the environment, these can differ between clients in the same process.
+#[derive(Debug, Clone)]
+pub struct LoungeConfig {
+    /// Base URL of the Lounge API. Override to point the client at a proxy or mock server.
+    pub api_base: String,
+    /// Automatically send `Next` when the TV reports a playback error.
+    pub auto_skip_on_error: bool,
+    /// Maximum number of consecutive automatic skips before giving up. The counter
+    /// resets once a video starts playing.
+    pub max_consecutive_auto_skips: u32,
+    /// Ask the TV for its now-playing, volume and autoplay mode whenever a
+    /// session is established, instead of waiting for them to change.
+    pub request_initial_state: bool,
+    /// Also emit `VolumeChanged` and `SeekableRangeChanged` when the values carried
+    /// by an `onStateChange` change, for consumers preferring fine-grained events.
+    pub decompose_state_change: bool,
+    /// Keep events from this far back for [`crate::LoungeClient::recent_events`].
+    /// `None` (the default) keeps no history.
+    pub event_history_window: Option<Duration>,
+    /// Maximum number of events kept in the history.
+    pub event_history_capacity: usize,
+    /// Token refreshes requested within this long of the previous one reuse its
+    /// token, so a burst of 401s causes a single refresh.
+    pub min_token_refresh_interval: Duration,
+    /// While connected, send a `getNowPlaying` this often so TVs that drop idle
+    /// sessions keep this one alive. `None` (the default) sends no keep-alives.
+    pub keepalive_interval: Option<Duration>,
+    /// Read the body of every command response and pass it to the callback set
+    /// with [`crate::LoungeClient::set_command_response_callback`].
+    pub observe_command_responses: bool,
+    /// How long a long poll may go without receiving any data before it counts
+    /// as inactive. Defaults to `INACTIVITY_TIMEOUT_SECS` from [`crate::SETTINGS`] (32s).
+    pub inactivity_timeout: Duration,
+    /// End the poll and immediately poll again once it has been inactive for
+    /// `inactivity_timeout` (the default). When `false` the poll keeps waiting,
+    /// and only reconnects when the request itself times out or fails, which
+    /// saves requests on quiet sessions.
+    pub repoll_on_inactivity: bool,
+    /// Emit a `PlaybackSession` built from a single `nowPlaying` or
+    /// `onStateChange` when the matching other half isn't available, instead of
+    /// waiting for both. See [`crate::PlaybackSession::from_now_playing`].
+    pub emit_partial_sessions: bool,
+    /// Hold commands sent while the client is (re)connecting until it is
+    /// connected, instead of failing them with `SessionLost`. A command still
+    /// not sent after the request timeout fails as before.
+    pub queue_commands_until_connected: bool,
+    /// Remember what was playing when the session is lost and emit
+    /// `SessionResumed` with it once a new session is bound.
+    pub resume_on_reconnect: bool,
+    /// With `resume_on_reconnect`, also send a `SetPlaylist` to start the
+    /// remembered video again at its position, for screens that drop
+    /// playback along with the session.
+    pub resume_playback_on_reconnect: bool,
+    /// The `ui` bind parameter. `false` (the default) binds as a plain remote;
+    /// `true` presents it as a remote with its own UI, which some screens list
+    /// differently among connected devices.
+    pub bind_ui: bool,
+    /// The `theme` bind parameter. `cl` (the default) is what the official
+    /// remotes send; the server's handling of other values is undocumented.
+    pub bind_theme: String,
+    /// How long `disconnect()` waits for the screen to acknowledge the
+    /// terminate request. The terminate is best effort, so a short timeout
+    /// makes shutdown snappier at the risk of the screen keeping the session
+    /// around until it expires.
+    pub terminate_timeout: Duration,
+    /// Maximum number of playback sessions tracked per CPN; the least recently
+    /// updated is dropped first.
+    pub max_tracked_sessions: usize,
+    /// Forget tracked sessions not updated for this long. `None` (the default)
+    /// keeps them until `max_tracked_sessions` pushes them out.
+    pub session_ttl: Option<Duration>,
+    /// Initial capacity in bytes of the buffer long-poll responses are decoded
+    /// from. Defaults to `STREAMING_BUFFER_CAPACITY` from [`crate::SETTINGS`]
+    /// (16KB); raise it for screens sending large playlists in one burst.
+    pub stream_buffer_capacity: usize,
+    /// How long a single long poll may stay open before it is abandoned and
+    /// retried. Defaults to `LONG_POLL_TIMEOUT_SECS` from [`crate::SETTINGS`] (300s).
+    pub long_poll_timeout: Duration,
+    /// First delay before reconnecting after a failure; it doubles with each
+    /// further failure. Defaults to `MIN_BACKOFF_MS` from [`crate::SETTINGS`] (500ms).
+    pub min_backoff: Duration,
+    /// Upper bound of the reconnect delay. Defaults to `MAX_BACKOFF_SECS` from
+    /// [`crate::SETTINGS`] (60s).
+    pub max_backoff: Duration,
+    /// How many events a subscriber may fall behind before it lags. Defaults to
+    /// `EVENT_BUFFER_CAPACITY` from [`crate::SETTINGS`] (1000).
+    pub event_channel_capacity: usize,
+    /// How streams from [`crate::LoungeClient::events`] handle falling behind.
+    pub event_lag_policy: LagPolicy,
+    /// Queue size of each [`crate::LoungeClient::subscribe_filtered`]
+    /// subscription. Once it is full, further events for that subscription
+    /// are dropped until it catches up. Defaults to 64.
+    pub filtered_event_capacity: usize,
+    /// Send all Lounge API traffic, pairing included, through this proxy, e.g.
+    /// `reqwest::Proxy::all("http://proxy.corp:3128")`. SOCKS5 proxies need
+    /// reqwest's `socks` feature enabled. Not applied to a custom HTTP client
+    /// passed to [`crate::LoungeClient::new`], which brings its own settings.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub proxy: Option<reqwest::Proxy>,
+    /// Keep command requests at least this far apart, delaying the ones sent
+    /// too soon after the previous. `None` (the default) sends at once.
+    pub min_command_interval: Option<Duration>,
+    /// Coalesce `SetVolume` and `SeekTo` commands sent within this window of
+    /// the first one of their kind: only the last is sent, when the window
+    /// closes. Replaced commands return `Ok` without being sent. `None` (the
+    /// default) sends every command.
+    pub command_coalesce_window: Option<Duration>,
+    /// Wrap errors of bind, command and token requests in
+    /// `LoungeError::WithContext`, with the screen, endpoint, HTTP status and
+    /// start of the response body. Off by default, since code matching on
+    /// variants has to look through it with `LoungeError::without_context`.
+    pub error_context: bool,
+    /// Runtime on which a client dropped while connected sends its terminate
+    /// request, so the screen drops the session right away. `None` (the
+    /// default) only aborts the connection manager, leaving the session to
+    /// expire on the screen. Prefer `LoungeClient::shutdown` where you can
+    /// await it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub reaper: Option<tokio::runtime::Handle>,
+}
+
+impl Default for LoungeConfig {
+    fn default() -> Self {
+        Self {
+            api_base: DEFAULT_API_BASE.to_string(),
+            auto_skip_on_error: false,
+            max_consecutive_auto_skips: 3,
+            request_initial_state: false,
+            decompose_state_change: false,
+            event_history_window: None,
+            event_history_capacity: 256,
+            min_token_refresh_interval: Duration::from_secs(5),
+            keepalive_interval: None,
+            observe_command_responses: false,
+            inactivity_timeout: SETTINGS.inactivity_timeout,
+            repoll_on_inactivity: true,
+            emit_partial_sessions: false,
+            queue_commands_until_connected: false,
+            resume_on_reconnect: false,
+            resume_playback_on_reconnect: false,
+            bind_ui: false,
+            bind_theme: "cl".to_string(),
+            terminate_timeout: Duration::from_secs(5),
+            max_tracked_sessions: 16,
+            session_ttl: None,
+            stream_buffer_capacity: SETTINGS.streaming_buffer_capacity,
+            long_poll_timeout: SETTINGS.long_poll_timeout,
+            min_backoff: SETTINGS.min_backoff,
+            max_backoff: SETTINGS.max_backoff,
+            event_channel_capacity: SETTINGS.event_buffer_capacity,
+            event_lag_policy: LagPolicy::Skip,
+            filtered_event_capacity: 64,
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
+            min_command_interval: None,
+            command_coalesce_window: None,
+            error_context: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            reaper: None,
+        }
+    }
+}
+
+impl LoungeConfig {
+    /// Attach `context` to `error` if [`Self::error_context`] is enabled.
+    pub(crate) fn contextualize(&self, error: LoungeError, context: ErrorContext) -> LoungeError {
+        if self.error_context {
+            error.with_context(context)
+        } else {
+            error
+        }
+    }
+
+    /// Build the full URL of a Lounge API endpoint, e.g. `bc/bind`.
+    pub(crate) fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.api_base.trim_end_matches('/'), path)
+    }
+}
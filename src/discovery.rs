@@ -0,0 +1,164 @@
+// SSDP/DIAL discovery of screens on the local network, the way the official
+// remotes find TVs without a pairing code.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::future::join_all;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Client;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Instant};
+use tracing::{debug, info};
+
+use crate::{LoungeError, SETTINGS};
+
+/// The SSDP multicast group searches are sent to.
+pub const SSDP_MULTICAST_ADDR: &str = "239.255.255.250:1900";
+/// The SSDP search target DIAL servers answer.
+pub const DIAL_SEARCH_TARGET: &str = "urn:dial-multiscreen-org:service:dial:1";
+
+lazy_static! {
+    static ref FRIENDLY_NAME_RE: Regex =
+        Regex::new(r"<friendlyName>([^<]*)</friendlyName>").unwrap();
+    static ref SCREEN_ID_RE: Regex = Regex::new(r"<screenId>([^<]*)</screenId>").unwrap();
+}
+
+/// A screen running the YouTube app, found on the local network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredScreen {
+    /// Pass to [`crate::LoungeClient::pair_with_screen_id`] to get a lounge token.
+    pub screen_id: String,
+    /// The device's `friendlyName`, when its description has one.
+    pub name: Option<String>,
+    /// URL of the UPnP device description the device announced.
+    pub location: String,
+    /// DIAL URL of the device's YouTube app.
+    pub app_url: String,
+}
+
+/// Search the local network for screens, collecting answers for `wait`.
+///
+/// Devices only report a screen ID while the YouTube app is running, so idle
+/// TVs may be missing from the result.
+pub async fn discover_screens(wait: Duration) -> Result<Vec<DiscoveredScreen>, LoungeError> {
+    let target = SSDP_MULTICAST_ADDR
+        .parse()
+        .expect("SSDP multicast address is valid");
+    discover_screens_at(target, wait).await
+}
+
+/// Like [`discover_screens`], but sends the search to `target`. Pointing it at
+/// a single device works on networks that drop multicast traffic.
+pub async fn discover_screens_at(
+    target: SocketAddr,
+    wait: Duration,
+) -> Result<Vec<DiscoveredScreen>, LoungeError> {
+    let locations = search(target, wait).await?;
+    let client = Client::builder()
+        .timeout(SETTINGS.request_timeout)
+        .build()?;
+
+    let described = join_all(locations.iter().map(|location| describe(&client, location))).await;
+    let screens: Vec<_> = locations
+        .iter()
+        .zip(described)
+        .filter_map(|(location, result)| match result {
+            Ok(screen) => Some(screen),
+            Err(e) => {
+                // Most DIAL devices on a network are not YouTube screens
+                debug!(%location, error = %e, "Skipping DIAL device");
+                None
+            }
+        })
+        .collect();
+    info!("Discovered {} screen(s)", screens.len());
+    Ok(screens)
+}
+
+/// Send an M-SEARCH and collect the distinct `LOCATION`s of the answers.
+async fn search(target: SocketAddr, wait: Duration) -> Result<Vec<String>, LoungeError> {
+    let bind_addr = if target.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    // MX asks devices to spread their answers over that many seconds
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: {}\r\n\r\n",
+        SSDP_MULTICAST_ADDR,
+        wait.as_secs().clamp(1, 5),
+        DIAL_SEARCH_TARGET
+    );
+    socket.send_to(request.as_bytes(), target).await?;
+
+    let deadline = Instant::now() + wait;
+    let mut buf = [0u8; 2048];
+    let mut locations = Vec::new();
+    while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = received?;
+        let answer = String::from_utf8_lossy(&buf[..len]);
+        let Some(location) = header_value(&answer, "location") else {
+            continue;
+        };
+        if !locations.contains(&location) {
+            debug!(%from, %location, "DIAL device answered");
+            locations.push(location);
+        }
+    }
+    Ok(locations)
+}
+
+/// Fetch a device's description and its YouTube app's state, which carries the screen ID.
+async fn describe(client: &Client, location: &str) -> Result<DiscoveredScreen, LoungeError> {
+    let response = client.get(location).send().await?.error_for_status()?;
+    let app_base = response
+        .headers()
+        .get("Application-URL")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| LoungeError::InvalidResponse("No Application-URL header".to_string()))?
+        .to_string();
+    let description = response.text().await?;
+
+    let app_url = format!("{}/YouTube", app_base.trim_end_matches('/'));
+    let app_info = client
+        .get(&app_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let screen_id = capture(&SCREEN_ID_RE, &app_info)
+        .ok_or_else(|| LoungeError::InvalidResponse("No screenId in app info".to_string()))?;
+
+    Ok(DiscoveredScreen {
+        screen_id,
+        name: capture(&FRIENDLY_NAME_RE, &description),
+        location: location.to_string(),
+        app_url,
+    })
+}
+
+/// The value of a header in an HTTP-style SSDP message, matched case-insensitively.
+fn header_value(message: &str, name: &str) -> Option<String> {
+    message.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// The trimmed, unescaped text of the first match, if not empty.
+fn capture(re: &Regex, xml: &str) -> Option<String> {
+    let text = re.captures(xml)?.get(1)?.as_str().trim();
+    (!text.is_empty()).then(|| {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    })
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 // Basic error handling with thiserror
@@ -33,17 +34,262 @@ pub enum LoungeError {
     #[error("Server indicated session is invalid (HTTP {0})")]
     SessionInvalidatedByServer(u16),
 
+    #[error("Screen is no longer paired; pair again with a new pairing code")]
+    ScreenUnpaired, // Token refresh found no binding for the screen
+
+    #[error("Screen is offline or unknown (HTTP 404 on bind)")]
+    ScreenOffline,
+
     #[error("Token refresh failed: {0}")]
     TokenRefreshFailed(Box<LoungeError>), // Box to avoid recursive type size issue
 
     #[error("Task panicked or cancelled")]
-    TaskJoinError(#[from] tokio::task::JoinError),
+    TaskJoinError(#[from] crate::rt::JoinError),
 
     #[error("Already connected to screen: {0}")]
     DuplicateScreen(String),
+
+    #[error("Bind response did not contain SID/gsessionid: {body_preview:?}")]
+    SessionIdExtractionFailed { body_preview: String }, // Start of the offending body
+
+    #[error("Not a TV code (up to 12 digits): {0:?}")]
+    InvalidPairingCode(String),
+
+    #[error("Queue index {index} is out of range for a queue of {len} video(s)")]
+    QueueIndexOutOfRange { index: usize, len: usize },
+
+    #[error("Rate limited by the server; retry after {retry_after:?}")]
+    RateLimited {
+        /// The server's `Retry-After`, when it sent one in seconds.
+        retry_after: Option<std::time::Duration>,
+    },
+
+    #[error("The screen did not report its volume")]
+    VolumeUnknown,
+
+    #[error("Shutdown did not finish within {0:?}; the connection manager was aborted")]
+    ShutdownTimedOut(std::time::Duration),
+
+    /// Another error with details of the request that failed. Only produced
+    /// with [`crate::LoungeConfig::error_context`] enabled.
+    #[error("{source} ({context})")]
+    WithContext {
+        source: Box<LoungeError>,
+        context: Box<ErrorContext>,
+    },
+}
+
+/// Broad category of a [`LoungeError`], for retry policies that shouldn't
+/// depend on single variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorKind {
+    /// The request didn't complete: connection, timeout or I/O failure.
+    Network,
+    /// The lounge token was rejected, or the screen is no longer paired.
+    Auth,
+    /// The session is gone or couldn't be established.
+    Session,
+    /// The screen is offline or unknown.
+    Unavailable,
+    /// The server asked to slow down.
+    RateLimited,
+    /// The server sent something unexpected or unparseable.
+    Protocol,
+    /// The connection was closed on purpose.
+    Closed,
+    /// The call itself was invalid, e.g. a queue index out of range.
+    InvalidInput,
+    /// A background task panicked or was cancelled.
+    Internal,
+}
+
+/// Details of the request behind an error. Fields are `None` when unknown,
+/// e.g. `status` for a request that never got a response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub screen_id: Option<String>,
+    /// The Lounge API endpoint, e.g. `bc/bind`.
+    pub endpoint: Option<String>,
+    pub status: Option<u16>,
+    /// The start of the response body, if it wasn't empty.
+    pub body_excerpt: Option<String>,
+}
+
+impl ErrorContext {
+    pub(crate) fn new(screen_id: &str, endpoint: &str) -> Self {
+        Self {
+            screen_id: Some(screen_id.to_string()),
+            endpoint: Some(endpoint.to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub(crate) fn body(mut self, body: &str) -> Self {
+        self.body_excerpt = (!body.is_empty()).then(|| excerpt(body));
+        self
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(screen_id) = &self.screen_id {
+            parts.push(format!("screen {}", screen_id));
+        }
+        if let Some(endpoint) = &self.endpoint {
+            parts.push(endpoint.clone());
+        }
+        if let Some(status) = self.status {
+            parts.push(format!("HTTP {}", status));
+        }
+        if let Some(body) = &self.body_excerpt {
+            parts.push(format!("body {:?}", body));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// How much of a response body to keep in errors.
+const BODY_PREVIEW_CHARS: usize = 256;
+
+/// The start of `body`, marked as cut off if it is longer.
+fn excerpt(body: &str) -> String {
+    let mut preview: String = body.chars().take(BODY_PREVIEW_CHARS).collect();
+    if body.chars().count() > BODY_PREVIEW_CHARS {
+        preview.push('…');
+    }
+    preview
 }
 
 impl LoungeError {
+    pub(crate) fn session_id_extraction_failed(body: &[u8]) -> Self {
+        LoungeError::SessionIdExtractionFailed {
+            body_preview: excerpt(&String::from_utf8_lossy(body)),
+        }
+    }
+
+    /// Wrap this error with details of the failed request. Context already
+    /// attached is replaced.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        LoungeError::WithContext {
+            source: Box::new(self.into_without_context()),
+            context: Box::new(context),
+        }
+    }
+
+    /// The attached request details, if any.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            LoungeError::WithContext { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The error without attached context, for matching on its variant.
+    pub fn without_context(&self) -> &LoungeError {
+        match self {
+            LoungeError::WithContext { source, .. } => source.without_context(),
+            other => other,
+        }
+    }
+
+    fn into_without_context(self) -> LoungeError {
+        match self {
+            LoungeError::WithContext { source, .. } => source.into_without_context(),
+            other => other,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            LoungeError::RequestFailed(_) | LoungeError::IoError(_) => ErrorKind::Network,
+            LoungeError::TokenExpired | LoungeError::ScreenUnpaired => ErrorKind::Auth,
+            LoungeError::SessionLost | LoungeError::SessionInvalidatedByServer(_) => {
+                ErrorKind::Session
+            }
+            LoungeError::ScreenOffline => ErrorKind::Unavailable,
+            LoungeError::RateLimited { .. } => ErrorKind::RateLimited,
+            LoungeError::ParseFailed(_)
+            | LoungeError::UrlEncodingFailed(_)
+            | LoungeError::NumericParseFailed(_)
+            | LoungeError::InvalidResponse(_)
+            | LoungeError::SessionIdExtractionFailed { .. }
+            | LoungeError::VolumeUnknown => ErrorKind::Protocol,
+            LoungeError::ConnectionClosed | LoungeError::ShutdownTimedOut(_) => ErrorKind::Closed,
+            LoungeError::DuplicateScreen(_)
+            | LoungeError::InvalidPairingCode(_)
+            | LoungeError::QueueIndexOutOfRange { .. } => ErrorKind::InvalidInput,
+            LoungeError::TaskJoinError(_) => ErrorKind::Internal,
+            LoungeError::TokenRefreshFailed(cause)
+            | LoungeError::WithContext { source: cause, .. } => cause.kind(),
+        }
+    }
+
+    /// Whether the token was rejected or the screen is no longer paired. An
+    /// expired token is fixed by refreshing it; an unpaired screen is also
+    /// [fatal](Self::is_fatal).
+    pub fn is_auth(&self) -> bool {
+        self.kind() == ErrorKind::Auth
+    }
+
+    /// Whether the operation is over for good: neither retrying nor
+    /// refreshing the token helps. Covers [fatal](Self::is_fatal) errors,
+    /// invalid input and a connection closed on purpose.
+    pub fn is_terminal(&self) -> bool {
+        self.is_fatal() || matches!(self.kind(), ErrorKind::Closed | ErrorKind::InvalidInput)
+    }
+
+    /// Whether trying the same operation again later may succeed, e.g. after a
+    /// network hiccup or once the screen is back online.
+    ///
+    /// Some errors are neither retryable nor [fatal](Self::is_fatal): parse
+    /// failures concern a single payload, and `ConnectionClosed` means the
+    /// connection was closed on purpose.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LoungeError::RequestFailed(_)
+            | LoungeError::SessionLost
+            | LoungeError::TokenExpired
+            | LoungeError::InvalidResponse(_)
+            | LoungeError::IoError(_)
+            | LoungeError::SessionInvalidatedByServer(_)
+            | LoungeError::ScreenOffline
+            | LoungeError::SessionIdExtractionFailed { .. }
+            | LoungeError::RateLimited { .. }
+            | LoungeError::VolumeUnknown => true,
+            LoungeError::TokenRefreshFailed(cause)
+            | LoungeError::WithContext { source: cause, .. } => cause.is_retryable(),
+            LoungeError::ParseFailed(_)
+            | LoungeError::UrlEncodingFailed(_)
+            | LoungeError::NumericParseFailed(_)
+            | LoungeError::ConnectionClosed
+            | LoungeError::ShutdownTimedOut(_)
+            | LoungeError::ScreenUnpaired
+            | LoungeError::TaskJoinError(_)
+            | LoungeError::DuplicateScreen(_)
+            | LoungeError::InvalidPairingCode(_)
+            | LoungeError::QueueIndexOutOfRange { .. } => false,
+        }
+    }
+
+    /// Whether retrying cannot help and the user or caller has to act first,
+    /// e.g. by pairing the screen again.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            LoungeError::ScreenUnpaired
+            | LoungeError::DuplicateScreen(_)
+            | LoungeError::TaskJoinError(_) => true,
+            LoungeError::TokenRefreshFailed(cause)
+            | LoungeError::WithContext { source: cause, .. } => cause.is_fatal(),
+            _ => false,
+        }
+    }
+
     /// Helper to check if an error suggests the session is definitively dead
     /// (requires a full re-bind attempt).
     pub(crate) fn _indicates_session_dead(&self) -> bool {
@@ -55,3 +301,53 @@ impl LoungeError {
         )
     }
 }
+
+/// Status and diagnostic headers of an unsuccessful HTTP response, kept so
+/// auth failures and throttling (403/429) can be investigated after the fact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseDiagnostics {
+    pub status: u16,
+    /// `retry-after`, `www-authenticate` and `x-goog-*` headers, with lowercase names.
+    pub headers: Vec<(String, String)>,
+}
+
+impl ResponseDiagnostics {
+    pub(crate) fn from_response(response: &reqwest::Response) -> Self {
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| {
+                let name = name.as_str();
+                name.starts_with("x-goog-") || name == "retry-after" || name == "www-authenticate"
+            })
+            .map(|(name, value)| {
+                (
+                    name.as_str().to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                )
+            })
+            .collect();
+        Self {
+            status: response.status().as_u16(),
+            headers,
+        }
+    }
+
+    /// The `Retry-After` delay, if sent in seconds. The HTTP-date form is not
+    /// supported.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        self.header("retry-after")?
+            .trim()
+            .parse()
+            .ok()
+            .map(std::time::Duration::from_secs)
+    }
+
+    /// Look up a captured header by (case-insensitive) name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
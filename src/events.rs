@@ -1,16 +1,23 @@
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::error::LoungeError;
+use crate::history::EventHistory;
 use crate::models;
+use crate::player::CurrentState;
+use crate::rt::StdInstant as Instant;
+use crate::stats::ClockSkewEstimator;
+use crate::utils::youtube_parse;
 
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::fmt::Display;
 use std::sync::{
-    atomic::{AtomicU32, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc, Mutex, MutexGuard, PoisonError,
 };
+use std::time::Duration;
 
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::broadcast;
 
 #[derive(Debug, Clone)]
 pub enum LoungeEvent {
@@ -19,21 +26,40 @@ pub enum LoungeEvent {
     /// A synthetic event that combines NowPlaying and StateChange events
     /// for the same video (matched by CPN)
     PlaybackSession(PlaybackSession),
-    LoungeStatus(Vec<models::Device>, Option<String>),
+    /// Connected devices and the queue ID. The device list is shared, so
+    /// fanning the event out to many subscribers doesn't copy it.
+    LoungeStatus(Arc<Vec<models::Device>>, Option<String>),
     ScreenDisconnected,
     SessionEstablished,
+    /// Synthetic, emitted with `resume_on_reconnect` once a lost session has
+    /// been re-established: the video and position playing when it was lost.
+    SessionResumed {
+        video_id: String,
+        position: f64,
+    },
     AdPlaying(models::AdPlaying),
     AdStateChange(models::AdState),
     SubtitlesTrackChanged(models::SubtitlesTrackChanged),
+    /// Subtitle tracks available for a video, in reply to `GetSubtitlesTrackList`.
+    SubtitlesTrackList {
+        video_id: String,
+        tracks: Vec<models::SubtitleTrack>,
+    },
     AudioTrackChanged(models::AudioTrackChanged),
     AutoplayModeChanged(models::AutoplayModeChanged),
     HasPreviousNextChanged(models::HasPreviousNextChanged),
     VideoQualityChanged(models::VideoQualityChanged),
     VolumeChanged(models::VolumeChanged),
+    /// Synthetic, split out of `onStateChange` when `decompose_state_change` is on.
+    SeekableRangeChanged(models::SeekableRange),
     PlaylistModified(models::PlaylistModified),
     PlaylistModeChanged(models::PlaylistModeChanged),
     AutoplayUpNext(models::AutoplayUpNext),
-    Unknown(String),
+    PlaybackError(models::PlaybackError),
+    Unknown(RawEvent),
+    /// Every received event as raw JSON, emitted just before its parsed form
+    /// while debug mode is on. See [`crate::LoungeClient::enable_debug_mode`].
+    Raw(RawEvent),
 }
 
 impl Display for LoungeEvent {
@@ -42,6 +68,61 @@ impl Display for LoungeEvent {
     }
 }
 
+/// Event payloads that can be picked out of the event stream by type, as
+/// with [`crate::LoungeClient::events_of`].
+pub trait FromLoungeEvent: Sized {
+    /// The payload, if `event` carries this type.
+    fn from_event(event: LoungeEvent) -> Option<Self>;
+}
+
+macro_rules! from_lounge_event {
+    ($($variant:ident($payload:ty)),* $(,)?) => {
+        $(
+            impl FromLoungeEvent for $payload {
+                fn from_event(event: LoungeEvent) -> Option<Self> {
+                    match event {
+                        LoungeEvent::$variant(payload) => Some(payload),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_lounge_event!(
+    StateChange(models::PlaybackState),
+    NowPlaying(models::NowPlaying),
+    PlaybackSession(PlaybackSession),
+    AdPlaying(models::AdPlaying),
+    AdStateChange(models::AdState),
+    SubtitlesTrackChanged(models::SubtitlesTrackChanged),
+    AudioTrackChanged(models::AudioTrackChanged),
+    AutoplayModeChanged(models::AutoplayModeChanged),
+    HasPreviousNextChanged(models::HasPreviousNextChanged),
+    VideoQualityChanged(models::VideoQualityChanged),
+    VolumeChanged(models::VolumeChanged),
+    SeekableRangeChanged(models::SeekableRange),
+    PlaylistModified(models::PlaylistModified),
+    PlaylistModeChanged(models::PlaylistModeChanged),
+    AutoplayUpNext(models::AutoplayUpNext),
+    PlaybackError(models::PlaybackError),
+);
+
+/// An event as received: its type and JSON payload. The payload is shared,
+/// so subscribers don't each copy it.
+#[derive(Debug, Clone)]
+pub struct RawEvent {
+    pub event_type: String,
+    pub payload: Arc<serde_json::Value>,
+}
+
+impl Display for RawEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - payload: {}", self.event_type, self.payload)
+    }
+}
+
 /// Represents a complete playback session with data combined from
 /// NowPlaying and StateChange events.
 #[derive(Debug, Clone)]
@@ -104,12 +185,52 @@ impl PlaybackSession {
             current_time,
             duration,
             state: playback_state,
-            video_data: None,
+            video_data: now_playing.video_data.clone().or_else(|| {
+                (!now_playing.video_id.is_empty())
+                    .then(|| models::VideoData::for_video(&now_playing.video_id))
+            }),
             cpn: state.cpn.clone(),
             list_id: now_playing.list_id.clone(),
             loaded_time,
         })
     }
+
+    /// Build a partial session from a `nowPlaying` alone, for when no matching
+    /// state change is available. Times the TV left out are reported as 0.
+    pub fn from_now_playing(now_playing: &models::NowPlaying) -> Self {
+        Self {
+            video_id: now_playing.video_id.clone(),
+            current_time: youtube_parse::parse_float(&now_playing.current_time),
+            duration: youtube_parse::parse_float(&now_playing.duration),
+            state: now_playing.state.clone(),
+            video_data: now_playing.video_data.clone().or_else(|| {
+                (!now_playing.video_id.is_empty())
+                    .then(|| models::VideoData::for_video(&now_playing.video_id))
+            }),
+            cpn: now_playing.cpn.clone(),
+            list_id: now_playing.list_id.clone(),
+            loaded_time: youtube_parse::parse_float(&now_playing.loaded_time),
+        }
+    }
+
+    /// Build a partial session from a state change alone, for when the
+    /// matching `nowPlaying` hasn't been seen. The video ID is left empty.
+    pub fn from_state(state: &models::PlaybackState) -> Self {
+        Self {
+            video_id: String::new(),
+            current_time: youtube_parse::parse_float(&state.current_time),
+            duration: youtube_parse::parse_float(&state.duration),
+            state: if state.state.trim().is_empty() {
+                models::default_state()
+            } else {
+                state.state.clone()
+            },
+            video_data: None,
+            cpn: state.cpn.clone(),
+            list_id: None,
+            loaded_time: youtube_parse::parse_float(&state.loaded_time),
+        }
+    }
 }
 
 /// Represents the playback status codes from YouTube
@@ -159,12 +280,360 @@ impl std::fmt::Display for PlaybackStatus {
     }
 }
 
-pub(crate) async fn process_event_chunk(
-    chunk: &str,
-    sender: &broadcast::Sender<LoungeEvent>,
-    latest_now_playing_arc: &Arc<RwLock<Option<models::NowPlaying>>>,
-    aid_atomic: &Arc<AtomicU32>,
-) {
+/// A backwards AID jump landing at or below this value, from further away than
+/// this, is taken as the server restarting its counter rather than a replay.
+const AID_RESET_CEILING: u32 = 10;
+
+/// How an event's AID relates to the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AidOrder {
+    InOrder,
+    /// Skipped ahead; this many AIDs were never seen.
+    Gap(u32),
+    /// Already seen; the event should be dropped.
+    Replay,
+    /// The server restarted its counter (e.g. after a rebind).
+    Reset,
+}
+
+/// Tracks the ordering of event AIDs to spot gaps, replays and counter resets.
+#[derive(Debug, Default)]
+pub(crate) struct AidTracker {
+    last: Option<u32>,
+}
+
+impl AidTracker {
+    pub(crate) fn observe(&mut self, aid: u32) -> AidOrder {
+        let Some(last) = self.last else {
+            self.last = Some(aid);
+            return AidOrder::InOrder;
+        };
+        let order = if aid > last {
+            match aid - last - 1 {
+                0 => AidOrder::InOrder,
+                missed => AidOrder::Gap(missed),
+            }
+        } else if aid <= AID_RESET_CEILING && last - aid > AID_RESET_CEILING {
+            AidOrder::Reset
+        } else {
+            return AidOrder::Replay;
+        };
+        self.last = Some(aid);
+        order
+    }
+
+    /// Treat `aid` as already processed, e.g. when resuming a stored session.
+    pub(crate) fn resume_from(aid: u32) -> Self {
+        Self { last: Some(aid) }
+    }
+
+    /// Forget the baseline, e.g. when a new session is bound.
+    pub(crate) fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// The latest `PlaybackSession` of each recent playback, keyed by CPN, plus a
+/// channel carrying only sessions.
+pub(crate) struct SessionTracker {
+    /// Sessions with when they were last updated, least recent first.
+    sessions: Mutex<VecDeque<(PlaybackSession, Instant)>>,
+    /// Device ID to the queue (list ID) `loungeStatus` last reported it in.
+    device_lists: Mutex<HashMap<String, String>>,
+    sender: broadcast::Sender<PlaybackSession>,
+    max_sessions: usize,
+    ttl: Option<Duration>,
+}
+
+impl SessionTracker {
+    pub(crate) fn new(capacity: usize, max_sessions: usize, ttl: Option<Duration>) -> Self {
+        Self {
+            sessions: Mutex::new(VecDeque::new()),
+            device_lists: Mutex::new(HashMap::new()),
+            sender: broadcast::channel(capacity).0,
+            max_sessions,
+            ttl,
+        }
+    }
+
+    /// Lock the sessions, dropping those not updated within the TTL.
+    fn live(&self) -> MutexGuard<'_, VecDeque<(PlaybackSession, Instant)>> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ttl) = self.ttl {
+            sessions.retain(|(_, updated)| {
+                crate::utils::now().saturating_duration_since(*updated) < ttl
+            });
+        }
+        sessions
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<PlaybackSession> {
+        self.sender.subscribe()
+    }
+
+    /// Record a session, replacing the previous one for the same CPN. Sessions
+    /// without a CPN can't be told apart and are only broadcast.
+    fn update(&self, session: &PlaybackSession) {
+        if let Some(cpn) = &session.cpn {
+            let mut sessions = self.live();
+            sessions.retain(|(tracked, _)| tracked.cpn.as_ref() != Some(cpn));
+            sessions.push_back((session.clone(), crate::utils::now()));
+            while sessions.len() > self.max_sessions {
+                sessions.pop_front();
+            }
+        }
+        // No receivers is fine, the map is still updated
+        let _ = self.sender.send(session.clone());
+    }
+
+    pub(crate) fn by_cpn(&self, cpn: &str) -> Option<PlaybackSession> {
+        self.live()
+            .iter()
+            .map(|(session, _)| session)
+            .find(|session| session.cpn.as_deref() == Some(cpn))
+            .cloned()
+    }
+
+    /// Link the devices of a `loungeStatus` to its queue, replacing earlier links.
+    fn link_devices(&self, devices: &[models::Device], queue_id: Option<&str>) {
+        let mut links = self
+            .device_lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        links.clear();
+        if let Some(queue_id) = queue_id.filter(|id| !id.is_empty()) {
+            for device in devices {
+                links.insert(device.id.clone(), queue_id.to_string());
+            }
+        }
+    }
+
+    /// The most recent live session playing from the queue `device_id` is in.
+    pub(crate) fn for_device(&self, device_id: &str) -> Option<PlaybackSession> {
+        let list_id = self
+            .device_lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(device_id)
+            .cloned()?;
+        self.live()
+            .iter()
+            .rev()
+            .map(|(session, _)| session)
+            .find(|session| session.list_id.as_deref() == Some(list_id.as_str()))
+            .cloned()
+    }
+
+    /// Live sessions, least recently updated first.
+    pub(crate) fn all(&self) -> Vec<PlaybackSession> {
+        self.live()
+            .iter()
+            .map(|(session, _)| session.clone())
+            .collect()
+    }
+}
+
+/// `nowPlaying` and `onStateChange` often describe the same moment; identical
+/// sessions arriving within this window are only emitted once.
+const SESSION_DEDUP_WINDOW: Duration = Duration::from_secs(1);
+
+/// Identifies a playback session emission: (cpn, current_time bits, state).
+type SessionKey = (Option<String>, u64, String);
+
+/// Everything the event pipeline reads or updates while processing messages.
+pub(crate) struct EventPipeline {
+    pub(crate) sender: broadcast::Sender<LoungeEvent>,
+    pub(crate) session_cache: Mutex<SessionCache>,
+    pub(crate) aid_atomic: Arc<AtomicU32>,
+    pub(crate) player_state: Arc<std::sync::RwLock<CurrentState>>,
+    pub(crate) last_session: Mutex<Option<(SessionKey, Instant)>>,
+    pub(crate) aid_tracker: Mutex<AidTracker>,
+    /// Emit sub-changes of `onStateChange` as their own events.
+    pub(crate) decompose_state_change: bool,
+    /// Emit sessions built from a single event when the other is missing.
+    pub(crate) partial_sessions: bool,
+    pub(crate) state_change_parts: Mutex<StateChangeParts>,
+    pub(crate) history: Option<Arc<Mutex<EventHistory>>>,
+    pub(crate) clock_skew: Arc<Mutex<ClockSkewEstimator>>,
+    /// A gsessionid announced mid-stream by an `S` frame, waiting to be applied
+    /// to the session by the poll loop.
+    pub(crate) rotated_gsessionid: Mutex<Option<String>>,
+    /// When the last event of any kind, noops included, arrived.
+    pub(crate) last_event_at: Arc<Mutex<Option<Instant>>>,
+    pub(crate) sessions: Arc<SessionTracker>,
+    /// Emit each event's raw JSON as `LoungeEvent::Raw`.
+    pub(crate) debug_mode: Arc<AtomicBool>,
+}
+
+/// The latest `nowPlaying` and `onStateChange`, from which sessions are built.
+#[derive(Debug, Default)]
+pub(crate) struct SessionCache {
+    /// Last `nowPlaying` that carried a cpn.
+    pub(crate) now_playing: Option<models::NowPlaying>,
+    /// Last `onStateChange`.
+    pub(crate) state: Option<models::PlaybackState>,
+}
+
+impl SessionCache {
+    /// The last known state for the cached video, if the TV reported one.
+    fn state_for_now_playing(&self) -> Option<&models::PlaybackState> {
+        let cpn = self.now_playing.as_ref()?.cpn.as_ref()?;
+        self.state
+            .as_ref()
+            .filter(|state| state.cpn.as_ref() == Some(cpn))
+    }
+
+    /// The session to report when playback stops: the cached video with its
+    /// real duration, preferring the state's figure since `nowPlaying` often
+    /// omits it.
+    fn stopped_session(&self) -> Option<PlaybackSession> {
+        let prev = self.now_playing.as_ref()?;
+        let duration = self
+            .state_for_now_playing()
+            .map(|state| state.duration.clone())
+            .filter(|duration| !duration.is_empty())
+            .unwrap_or_else(|| prev.duration.clone());
+        let state = models::PlaybackState {
+            current_time: "0".to_string(),
+            state: "-1".to_string(),
+            duration,
+            cpn: prev.cpn.clone(),
+            loaded_time: "0".to_string(),
+        };
+        PlaybackSession::new(prev, &state).ok()
+    }
+}
+
+/// Parts of `onStateChange` last emitted as separate events.
+#[derive(Debug, Default)]
+pub(crate) struct StateChangeParts {
+    volume: Option<models::VolumeChanged>,
+    seekable_range: Option<models::SeekableRange>,
+}
+
+impl EventPipeline {
+    /// A pipeline with default behaviour that belongs to no client, feeding
+    /// only `sender`.
+    pub(crate) fn detached(sender: broadcast::Sender<LoungeEvent>) -> Self {
+        EventPipeline {
+            sender,
+            session_cache: Mutex::new(Default::default()),
+            aid_atomic: Arc::new(AtomicU32::new(0)),
+            player_state: Arc::new(std::sync::RwLock::new(CurrentState::default())),
+            last_session: Mutex::new(None),
+            aid_tracker: Mutex::new(AidTracker::default()),
+            decompose_state_change: false,
+            partial_sessions: false,
+            state_change_parts: Mutex::new(Default::default()),
+            history: None,
+            clock_skew: Arc::new(Mutex::new(ClockSkewEstimator::default())),
+            rotated_gsessionid: Mutex::new(None),
+            last_event_at: Arc::new(Mutex::new(None)),
+            sessions: Arc::new(SessionTracker::new(
+                crate::SETTINGS.event_buffer_capacity,
+                crate::LoungeConfig::default().max_tracked_sessions,
+                None,
+            )),
+            debug_mode: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Update the cached player state, then broadcast the event.
+    fn emit(&self, event: &LoungeEvent) {
+        self.player_state
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .apply(event);
+        if let Some(history) = &self.history {
+            history
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(event.clone());
+        }
+        send_event(&self.sender, event);
+    }
+
+    /// Emit the volume and seekable window carried by a state change, if they changed.
+    fn emit_state_change_parts(&self, payload: &serde_json::Value) {
+        let volume = serde_json::from_value::<models::VolumeChanged>(payload.clone()).ok();
+        let seekable_range = serde_json::from_value::<models::SeekableRange>(payload.clone()).ok();
+
+        let mut changed = Vec::new();
+        {
+            let mut last = self
+                .state_change_parts
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some(volume) = volume.filter(|v| last.volume.as_ref() != Some(v)) {
+                last.volume = Some(volume.clone());
+                changed.push(LoungeEvent::VolumeChanged(volume));
+            }
+            if let Some(range) = seekable_range.filter(|r| last.seekable_range.as_ref() != Some(r))
+            {
+                last.seekable_range = Some(range.clone());
+                changed.push(LoungeEvent::SeekableRangeChanged(range));
+            }
+        }
+        for event in &changed {
+            self.emit(event);
+        }
+    }
+
+    /// Emit a session unless an identical one was emitted moments ago.
+    fn emit_session(&self, session: PlaybackSession) {
+        let key = (
+            session.cpn.clone(),
+            session.current_time.to_bits(),
+            session.state.clone(),
+        );
+        let now = crate::utils::now();
+        {
+            let mut last = self
+                .last_session
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some((last_key, at)) = last.as_ref() {
+                if *last_key == key && now.duration_since(*at) < SESSION_DEDUP_WINDOW {
+                    trace!(cpn = ?session.cpn, "Skipping duplicate PlaybackSession");
+                    return;
+                }
+            }
+            *last = Some((key, now));
+        }
+        self.sessions.update(&session);
+        self.emit(&LoungeEvent::PlaybackSession(session));
+    }
+}
+
+/// Parse `onSubtitlesTrackList`, shaped like
+/// `{"videoId": "...", "tracks": "[{\"languageCode\": \"en\", \"languageName\": ..., \"vssId\": \".en\"}]"}`.
+/// As with `loungeStatus` devices, the list usually arrives JSON-encoded inside a
+/// string; a plain array is accepted too.
+fn parse_subtitles_track_list(payload: &serde_json::Value) -> Option<LoungeEvent> {
+    let video_id = payload
+        .get("videoId")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let tracks = match payload.get("tracks") {
+        Some(serde_json::Value::String(raw)) if raw.trim().is_empty() => Ok(Vec::new()),
+        Some(serde_json::Value::String(raw)) => serde_json::from_str(raw),
+        Some(tracks) => serde_json::from_value(tracks.clone()),
+        None => Ok(Vec::new()),
+    };
+    match tracks {
+        Ok(tracks) => Some(LoungeEvent::SubtitlesTrackList { video_id, tracks }),
+        Err(e) => {
+            error!(error = %e, payload = %payload, "Failed to parse subtitles track list");
+            None
+        }
+    }
+}
+
+pub(crate) fn process_event_chunk(chunk: &str, pipeline: &EventPipeline) {
+    let session_cache = &pipeline.session_cache;
+    let aid_atomic = &pipeline.aid_atomic;
+
     // Helper function for deserializing with error logging
     fn deserialize_with_logging<T>(
         event_type: &str,
@@ -200,15 +669,41 @@ pub(crate) async fn process_event_chunk(
         }
     };
 
-    for event in events {
+    for mut event in events {
         if event.len() < 2 {
             continue;
         }
+        *pipeline
+            .last_event_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(crate::utils::now());
         if let Some(event_id) = event.first().and_then(|id| id.as_i64()) {
-            aid_atomic.store(event_id as u32, Ordering::SeqCst);
+            let aid = event_id as u32;
+            match pipeline
+                .aid_tracker
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .observe(aid)
+            {
+                AidOrder::InOrder => {}
+                AidOrder::Gap(missed) => {
+                    warn!(
+                        aid,
+                        missed, "Gap in event stream, events may have been missed"
+                    )
+                }
+                AidOrder::Reset => {
+                    info!(aid, "Event stream AID reset, continuing from new baseline")
+                }
+                AidOrder::Replay => {
+                    debug!(aid, "Skipping replayed event");
+                    continue;
+                }
+            }
+            aid_atomic.store(aid, Ordering::SeqCst);
         }
 
-        if let Some(event_array) = event.get(1).and_then(|v| v.as_array()) {
+        if let Some(event_array) = event.get_mut(1).and_then(|v| v.as_array_mut()) {
             // Check for the specific JSON noop structure [[N, ["noop"]]]
             if event_array.len() == 1 {
                 // Should only contain ["noop"]
@@ -224,38 +719,58 @@ pub(crate) async fn process_event_chunk(
             if event_array.len() < 2 {
                 continue;
             }
-            if let Some(event_type) = event_array.first().and_then(|t| t.as_str()) {
-                let payload = &event_array[1];
+            // Split so an unmodelled payload can be moved into its event
+            let (event_type, rest) = event_array.split_at_mut(1);
+            if let Some(event_type) = event_type[0].as_str() {
+                let payload = &mut rest[0];
                 log_event(event_type, payload);
+                if pipeline.debug_mode.load(Ordering::Relaxed) {
+                    pipeline.emit(&LoungeEvent::Raw(RawEvent {
+                        event_type: event_type.to_string(),
+                        payload: Arc::new(payload.clone()),
+                    }));
+                }
 
                 match event_type {
                     "onStateChange" => {
                         if let Ok(state) =
                             deserialize_with_logging::<models::PlaybackState>(event_type, payload)
                         {
-                            send_event(sender, &LoungeEvent::StateChange(state.clone()));
+                            pipeline
+                                .clock_skew
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .observe(&state);
+                            pipeline.emit(&LoungeEvent::StateChange(state.clone()));
+                            if pipeline.decompose_state_change {
+                                pipeline.emit_state_change_parts(payload);
+                            }
                             let latest_np = {
-                                let guard = latest_now_playing_arc.read().await;
-                                guard.clone()
+                                let mut cache =
+                                    session_cache.lock().unwrap_or_else(PoisonError::into_inner);
+                                cache.state = Some(state.clone());
+                                cache.now_playing.clone()
                             };
-                            if let Some(np) = latest_np.as_ref() {
-                                if let (Some(state_cpn), Some(np_cpn)) = (&state.cpn, &np.cpn) {
-                                    if state_cpn == np_cpn {
-                                        if let Ok(session) = PlaybackSession::new(np, &state) {
-                                            send_event(
-                                                sender,
-                                                &LoungeEvent::PlaybackSession(session),
-                                            );
-                                        }
-                                    }
+                            let matching_np = latest_np.as_ref().filter(
+                                |np| matches!((&state.cpn, &np.cpn), (Some(a), Some(b)) if a == b),
+                            );
+                            if let Some(np) = matching_np {
+                                if let Ok(session) = PlaybackSession::new(np, &state) {
+                                    pipeline.emit_session(session);
                                 }
+                            } else if pipeline.partial_sessions {
+                                pipeline.emit_session(PlaybackSession::from_state(&state));
                             }
                         }
                     }
                     "nowPlaying" => {
-                        if let Ok(now_playing) =
+                        if let Ok(mut now_playing) =
                             deserialize_with_logging::<models::NowPlaying>(event_type, payload)
                         {
+                            if !now_playing.video_id.is_empty() {
+                                now_playing.video_data =
+                                    Some(models::VideoData::for_video(&now_playing.video_id));
+                            }
                             debug!(
                                 "NowPlaying: id={} state={} time={}/{} list={} cpn={}",
                                 now_playing.video_id,
@@ -267,35 +782,23 @@ pub(crate) async fn process_event_chunk(
                             );
 
                             // Always send the raw event
-                            send_event(sender, &LoungeEvent::NowPlaying(now_playing.clone()));
+                            pipeline.emit(&LoungeEvent::NowPlaying(now_playing.clone()));
                             if now_playing.cpn.is_some() {
-                                let mut guard = latest_now_playing_arc.write().await;
-                                *guard = Some(now_playing.clone());
+                                session_cache
+                                    .lock()
+                                    .unwrap_or_else(PoisonError::into_inner)
+                                    .now_playing = Some(now_playing.clone());
                             }
                             // Create and send a PlaybackSession if possible
                             match now_playing.state.as_str() {
                                 // Handle stop events (-1)
                                 "-1" if now_playing.video_id.is_empty() => {
-                                    let prev_np_opt = {
-                                        let guard = latest_now_playing_arc.read().await;
-                                        guard.clone()
-                                    };
-                                    if let Some(prev) = prev_np_opt.as_ref() {
-                                        // Use prev_np_opt
-                                        let state = models::PlaybackState {
-                                            current_time: "0".to_string(),
-                                            state: "-1".to_string(),
-                                            duration: prev.duration.clone(),
-                                            cpn: prev.cpn.clone(),
-                                            loaded_time: "0".to_string(),
-                                        };
-
-                                        if let Ok(session) = PlaybackSession::new(prev, &state) {
-                                            send_event(
-                                                sender,
-                                                &LoungeEvent::PlaybackSession(session),
-                                            );
-                                        }
+                                    let stopped = session_cache
+                                        .lock()
+                                        .unwrap_or_else(PoisonError::into_inner)
+                                        .stopped_session();
+                                    if let Some(session) = stopped {
+                                        pipeline.emit_session(session);
                                     }
                                 }
 
@@ -314,10 +817,18 @@ pub(crate) async fn process_event_chunk(
                                     if let Ok(session) =
                                         PlaybackSession::new(&now_playing, &state_from_np)
                                     {
-                                        send_event(sender, &LoungeEvent::PlaybackSession(session));
+                                        pipeline.emit_session(session);
                                     }
                                 }
 
+                                _ if pipeline.partial_sessions
+                                    && !now_playing.video_id.is_empty() =>
+                                {
+                                    pipeline.emit_session(PlaybackSession::from_now_playing(
+                                        &now_playing,
+                                    ));
+                                }
+
                                 _ => debug!("Insufficient data to create PlaybackSession"),
                             }
                         }
@@ -355,13 +866,14 @@ pub(crate) async fn process_event_chunk(
                                         })
                                         .collect();
 
-                                    send_event(
-                                        sender,
-                                        &LoungeEvent::LoungeStatus(
-                                            devices_with_info,
-                                            status.queue_id,
-                                        ),
+                                    pipeline.sessions.link_devices(
+                                        &devices_with_info,
+                                        status.queue_id.as_deref(),
                                     );
+                                    pipeline.emit(&LoungeEvent::LoungeStatus(
+                                        Arc::new(devices_with_info),
+                                        status.queue_id,
+                                    ));
                                 }
                                 Err(e) => {
                                     error!(error = %e, "Failed to parse devices from loungeStatus");
@@ -371,92 +883,114 @@ pub(crate) async fn process_event_chunk(
                         }
                     }
                     "loungeScreenDisconnected" => {
-                        send_event(sender, &LoungeEvent::ScreenDisconnected);
+                        pipeline.emit(&LoungeEvent::ScreenDisconnected);
                     }
                     "adPlaying" => {
                         if let Ok(state) =
                             deserialize_with_logging::<models::AdPlaying>(event_type, payload)
                         {
-                            send_event(sender, &LoungeEvent::AdPlaying(state));
+                            pipeline.emit(&LoungeEvent::AdPlaying(state));
                         }
                     }
                     "onAdStateChange" => {
                         if let Ok(state) =
                             deserialize_with_logging::<models::AdState>(event_type, payload)
                         {
-                            send_event(sender, &LoungeEvent::AdStateChange(state));
+                            pipeline.emit(&LoungeEvent::AdStateChange(state));
                         }
                     }
                     "onSubtitlesTrackChanged" => {
                         if let Ok(state) = deserialize_with_logging::<models::SubtitlesTrackChanged>(
                             event_type, payload,
                         ) {
-                            send_event(sender, &LoungeEvent::SubtitlesTrackChanged(state));
+                            pipeline.emit(&LoungeEvent::SubtitlesTrackChanged(state));
+                        }
+                    }
+                    "onSubtitlesTrackList" => {
+                        if let Some(event) = parse_subtitles_track_list(payload) {
+                            pipeline.emit(&event);
                         }
                     }
                     "onAudioTrackChanged" => {
                         if let Ok(state) = deserialize_with_logging::<models::AudioTrackChanged>(
                             event_type, payload,
                         ) {
-                            send_event(sender, &LoungeEvent::AudioTrackChanged(state));
+                            pipeline.emit(&LoungeEvent::AudioTrackChanged(state));
                         }
                     }
                     "onAutoplayModeChanged" => {
                         if let Ok(state) = deserialize_with_logging::<models::AutoplayModeChanged>(
                             event_type, payload,
                         ) {
-                            send_event(sender, &LoungeEvent::AutoplayModeChanged(state));
+                            pipeline.emit(&LoungeEvent::AutoplayModeChanged(state));
                         }
                     }
                     "onHasPreviousNextChanged" => {
                         if let Ok(state) = deserialize_with_logging::<models::HasPreviousNextChanged>(
                             event_type, payload,
                         ) {
-                            send_event(sender, &LoungeEvent::HasPreviousNextChanged(state));
+                            pipeline.emit(&LoungeEvent::HasPreviousNextChanged(state));
                         }
                     }
                     "onVideoQualityChanged" => {
                         if let Ok(state) = deserialize_with_logging::<models::VideoQualityChanged>(
                             event_type, payload,
                         ) {
-                            send_event(sender, &LoungeEvent::VideoQualityChanged(state));
+                            pipeline.emit(&LoungeEvent::VideoQualityChanged(state));
                         }
                     }
                     "onVolumeChanged" => {
                         if let Ok(state) =
                             deserialize_with_logging::<models::VolumeChanged>(event_type, payload)
                         {
-                            send_event(sender, &LoungeEvent::VolumeChanged(state));
+                            pipeline.emit(&LoungeEvent::VolumeChanged(state));
                         }
                     }
                     "playlistModified" => {
                         if let Ok(state) = deserialize_with_logging::<models::PlaylistModified>(
                             event_type, payload,
                         ) {
-                            send_event(sender, &LoungeEvent::PlaylistModified(state));
+                            pipeline.emit(&LoungeEvent::PlaylistModified(state));
                         }
                     }
                     "onPlaylistModeChanged" => {
                         if let Ok(state) = deserialize_with_logging::<models::PlaylistModeChanged>(
                             event_type, payload,
                         ) {
-                            send_event(sender, &LoungeEvent::PlaylistModeChanged(state));
+                            pipeline.emit(&LoungeEvent::PlaylistModeChanged(state));
                         }
                     }
                     "autoplayUpNext" => {
                         if let Ok(state) =
                             deserialize_with_logging::<models::AutoplayUpNext>(event_type, payload)
                         {
-                            send_event(sender, &LoungeEvent::AutoplayUpNext(state));
+                            pipeline.emit(&LoungeEvent::AutoplayUpNext(state));
+                        }
+                    }
+                    "onError" => {
+                        if let Ok(state) =
+                            deserialize_with_logging::<models::PlaybackError>(event_type, payload)
+                        {
+                            pipeline.emit(&LoungeEvent::PlaybackError(state));
+                        }
+                    }
+                    "S" => {
+                        // The server moved the session; later requests must use the new ID
+                        if let Some(gsessionid) = payload.as_str().filter(|id| !id.is_empty()) {
+                            *pipeline
+                                .rotated_gsessionid
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner) =
+                                Some(gsessionid.to_string());
                         }
                     }
                     _ => {
-                        let event_with_payload = format!("{} - payload: {}", event_type, payload);
-                        warn!(
-                            "Unknown event type '{}' with payload: {}",
-                            event_type, payload
-                        );
-                        send_event(sender, &LoungeEvent::Unknown(event_with_payload));
+                        warn!("Unknown event type '{}'", event_type);
+                        trace!(event_type = %event_type, payload = %payload, "Unknown event payload");
+                        pipeline.emit(&LoungeEvent::Unknown(RawEvent {
+                            event_type: event_type.to_string(),
+                            payload: Arc::new(payload.take()),
+                        }));
                     }
                 }
             }
@@ -468,8 +1002,10 @@ pub(crate) async fn process_event_chunk(
 pub(crate) fn send_event(sender: &broadcast::Sender<LoungeEvent>, event: &LoungeEvent) {
     match sender.send(event.clone()) {
         Ok(n_subs) => trace!("Event {:?} sent to {} subs", event, n_subs),
+        // The only way a broadcast send fails; expected for clients nobody
+        // listens to, such as a connect-only health check
         Err(broadcast::error::SendError(dropped)) => {
-            warn!("Dropped event {:?} because no subscribers", dropped);
+            trace!("Dropped event {:?} because no subscribers", dropped);
         }
     }
 }
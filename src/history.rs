@@ -0,0 +1,62 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::rt::StdInstant as Instant;
+use crate::LoungeEvent;
+
+/// Recent events kept for late subscribers, bounded by age and count.
+#[derive(Debug, Clone)]
+pub struct EventHistory {
+    window: Duration,
+    capacity: usize,
+    events: VecDeque<(Instant, LoungeEvent)>,
+}
+
+impl EventHistory {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            events: VecDeque::with_capacity(capacity.min(1024)),
+        }
+    }
+
+    pub fn push(&mut self, event: LoungeEvent) {
+        self.push_at(crate::utils::now(), event);
+    }
+
+    pub fn push_at(&mut self, at: Instant, event: LoungeEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back((at, event));
+        self.evict(at);
+    }
+
+    /// Events received within the window ending now, oldest first.
+    pub fn recent(&self) -> Vec<(Instant, LoungeEvent)> {
+        self.recent_at(crate::utils::now())
+    }
+
+    /// Events received within the window ending at `now`, oldest first.
+    pub fn recent_at(&self, now: Instant) -> Vec<(Instant, LoungeEvent)> {
+        self.events
+            .iter()
+            .filter(|(at, _)| now.saturating_duration_since(*at) <= self.window)
+            .cloned()
+            .collect()
+    }
+
+    fn evict(&mut self, now: Instant) {
+        while self
+            .events
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > self.window)
+        {
+            self.events.pop_front();
+        }
+    }
+}
@@ -1,41 +1,83 @@
+mod automation;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+mod builder;
+pub use builder::LoungeClientBuilder;
 mod codec;
 pub use codec::LoungeCodec;
 mod commands;
-pub use commands::PlaybackCommand;
+pub use commands::{CommandOutcome, DisconnectReason, DpadKey, PlaybackCommand};
+mod config;
+pub use config::{LagPolicy, LoungeConfig, DEFAULT_API_BASE};
+#[cfg(not(target_arch = "wasm32"))]
+pub mod discovery;
 mod error;
-pub use error::LoungeError;
+pub use error::{ErrorContext, ErrorKind, LoungeError, ResponseDiagnostics};
 mod events;
-use events::send_event;
-pub use events::{LoungeEvent, PlaybackSession, PlaybackStatus};
+use events::{send_event, AidTracker, EventPipeline, SessionTracker};
+pub use events::{FromLoungeEvent, LoungeEvent, PlaybackSession, PlaybackStatus, RawEvent};
+mod history;
+pub use history::EventHistory;
+mod manager;
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+pub mod mock;
+pub use manager::{LoungeManager, ScreenEvent};
+mod metrics;
+pub use metrics::Metrics;
 mod models;
+mod player;
 pub use models::{
-    AdState, AudioTrackChanged, AutoplayModeChanged, AutoplayUpNext, Device, DeviceInfo,
-    HasPreviousNextChanged, LoungeStatus, NowPlaying, PlaybackState, PlaylistModified, Screen,
-    ScreenResponse, ScreensResponse, SubtitlesTrackChanged, VideoData, VideoQualityChanged,
-    VolumeChanged,
+    AdState, AudioTrackChanged, AutoplayModeChanged, AutoplayUpNext, Chapter, Device, DeviceInfo,
+    HasPreviousNextChanged, LoungeStatus, NowPlaying, PlaybackError, PlaybackState, PlaylistAction,
+    PlaylistModeChanged, PlaylistModified, Screen, ScreenResponse, ScreensResponse, SeekableRange,
+    SubtitleTrack, SubtitlesTrackChanged, VideoData, VideoQualityChanged, VolumeChanged,
 };
+use player::ResumePoint;
+pub use player::{CurrentState, Queue, StateDiff, TrackSettings, Volume};
+pub mod replay;
+mod router;
+use router::EventRouter;
+pub use router::{EventKind, EventKindSet, FilteredEvents};
+mod rt;
+#[cfg(target_arch = "wasm32")]
+pub use rt::{JoinError, JoinHandle};
 mod settings;
 pub use settings::SETTINGS;
 mod state;
-use state::{ConnectionState, ConnectionStatus, InnerState, SessionState};
+pub use state::{ConnectionState, Failure, SessionDebug, SessionSnapshot};
+use state::{ConnectionStatus, InnerState, SessionState, TokenRefresh, TokenRefreshState};
+mod stats;
+pub use stats::{
+    ClockSkewEstimator, MessageSizeStats, PollCycleEvent, PollCycleStats, PollEndReason,
+    ThroughputMeter,
+};
+mod throttle;
+use throttle::CommandThrottle;
+mod token_store;
+pub use token_store::{JsonFileTokenStore, StoredScreen, TokenStore};
 mod utils;
 pub use utils::youtube_parse;
+pub mod v2;
 
 use bytes::BytesMut;
-use futures::{FutureExt, StreamExt}; // Needed for response.bytes_stream()
+use futures::{FutureExt, Stream, StreamExt}; // Needed for response.bytes_stream()
 use reqwest::Client;
+use rt::{sleep, timeout, Instant};
+use std::panic::AssertUnwindSafe;
 use std::sync::{
-    atomic::{AtomicBool, AtomicU32, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    Arc, Mutex, PoisonError,
 };
+use std::time::Duration;
 use tokio::sync::{broadcast, watch, Notify, RwLock}; // Added watch
-use tokio::time::{sleep, timeout, Duration};
 use tokio_util::codec::Decoder;
 use tracing::{debug, error, info, trace, warn};
 use uuid::Uuid; // Needed for jitter
 
 // Type alias for the optional callback function pointer for clarity
 pub type TokenCallback = Option<Box<dyn Fn(&str, &str) + Send + Sync + 'static>>;
+pub type PollCycleCallback = Option<Box<dyn Fn(&PollCycleEvent) + Send + Sync + 'static>>;
+pub type CommandResponseCallback = Option<Box<dyn Fn(&str, u16, &str) + Send + Sync + 'static>>;
 
 /// Main client enables controlling YouTube playback on TV devices through
 /// the YouTube Lounge API protocol. It handles pairing, authentication,
@@ -69,20 +111,51 @@ pub type TokenCallback = Option<Box<dyn Fn(&str, &str) + Send + Sync + 'static>>
 /// - `ERROR`: Shows critical failures and error conditions
 struct ConnectionManagerContext {
     client: Arc<Client>,
+    config: Arc<LoungeConfig>,
     screen_id: String,
     device_name: String,
     device_id: String,
     shared_state: Arc<RwLock<InnerState>>,
     session_state_rwlock: Arc<RwLock<SessionState>>,
     event_sender: broadcast::Sender<LoungeEvent>,
-    latest_now_playing: Arc<RwLock<Option<NowPlaying>>>,
+    pipeline: EventPipeline,
     aid_atomic: Arc<AtomicU32>,
     shutdown_notify: Arc<Notify>,
+    network_changed: Arc<Notify>,
     state_tx: Arc<watch::Sender<ConnectionState>>,
+    connected_since: Arc<Mutex<Option<rt::StdInstant>>>,
+    last_poll_end_reason: Arc<Mutex<Option<PollEndReason>>>,
+    commands: CommandContext,
+    poll_cycles: AtomicU64,
+    throughput: Arc<Mutex<Option<ThroughputMeter>>>,
+    message_sizes: Arc<Mutex<MessageSizeStats>>,
+    last_token_refresh: Arc<tokio::sync::Mutex<TokenRefreshState>>,
+}
+
+/// Everything needed to send a command without borrowing the `LoungeClient`,
+/// so background tasks spawned by the connection manager can issue commands too.
+#[derive(Clone)]
+struct CommandContext {
+    client: Arc<Client>,
+    config: Arc<LoungeConfig>,
+    screen_id: String,
+    device_name: String,
+    device_id: String,
+    session_state: Arc<RwLock<SessionState>>,
+    shared_state: Arc<RwLock<InnerState>>,
+    aid_atomic: Arc<AtomicU32>,
+    state_rx: watch::Receiver<ConnectionState>,
+    last_error_response: Arc<Mutex<Option<ResponseDiagnostics>>>,
+    commands_in_flight: Arc<watch::Sender<usize>>,
+    throttle: Arc<CommandThrottle>,
 }
 
 pub struct LoungeClient {
     client: Arc<Client>,
+    // Whether `client` was passed in, so config changes must not replace it
+    #[cfg(not(target_arch = "wasm32"))]
+    custom_http_client: bool,
+    config: Arc<LoungeConfig>,
     device_id: String,
     screen_id: String,
     device_name: String,
@@ -94,12 +167,48 @@ pub struct LoungeClient {
     // Flag to signal the connection manager task to stop
     stop_signal: Arc<AtomicBool>,
     // JoinHandle for the management task
-    management_task: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    management_task: Arc<RwLock<Option<rt::JoinHandle<()>>>>,
     // Shutdown notifier for the management task
     shutdown_notify: Arc<Notify>,
     // Watch channel for observing the connection state
     connection_state_tx: Arc<watch::Sender<ConnectionState>>,
     connection_state_rx: watch::Receiver<ConnectionState>,
+    // The std locks below only guard caches that stay valid even if a holder
+    // panicked, so poisoning is ignored rather than bricking the client.
+    // Diagnostics of the most recent unsuccessful HTTP response
+    last_error_response: Arc<Mutex<Option<ResponseDiagnostics>>>,
+    // Player state assembled from events by the connection manager
+    player_state: Arc<std::sync::RwLock<CurrentState>>,
+    // Rolling byte rate of the event stream, reset for each connection manager
+    throughput: Arc<Mutex<Option<ThroughputMeter>>>,
+    // Sizes of decoded event stream messages, across connections
+    message_sizes: Arc<Mutex<MessageSizeStats>>,
+    // Number of command requests awaiting a response
+    commands_in_flight: Arc<watch::Sender<usize>>,
+    // Rate limiting and coalescing state of outgoing commands
+    command_throttle: Arc<CommandThrottle>,
+    // Per-kind subscriptions from `subscribe_filtered`
+    event_router: EventRouter,
+    // Recent events for late subscribers, if enabled in the config
+    event_history: Option<Arc<Mutex<EventHistory>>>,
+    // Drift between local time and the TV's reported playback progress
+    clock_skew: Arc<Mutex<ClockSkewEstimator>>,
+    // Runtime switch for automatic playback actions (e.g. auto-skip)
+    automation_enabled: Arc<AtomicBool>,
+    // When the token was last refreshed; locked for the duration of a refresh
+    last_token_refresh: Arc<tokio::sync::Mutex<TokenRefreshState>>,
+    // Wakes the connection manager out of a reconnect backoff
+    network_changed: Arc<Notify>,
+    // When the state last became Connected; only meaningful while it still is
+    connected_since: Arc<Mutex<Option<rt::StdInstant>>>,
+    // Why the most recent long-poll cycle ended
+    last_poll_end_reason: Arc<Mutex<Option<PollEndReason>>>,
+    // When the screen last sent any event, noops included
+    last_event_at: Arc<Mutex<Option<rt::StdInstant>>>,
+    // Latest session per CPN, kept across reconnects
+    sessions: Arc<SessionTracker>,
+    // Emit raw payloads alongside parsed events
+    debug_mode: Arc<AtomicBool>,
 }
 
 impl LoungeClient {
@@ -113,13 +222,11 @@ impl LoungeClient {
         device_id: Option<&str>,
         custom_client: Option<Arc<Client>>,
     ) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let custom_http_client = custom_client.is_some();
         let client = custom_client.unwrap_or_else(|| {
             Arc::new(
-                Client::builder()
-                    .pool_idle_timeout(Some(Duration::from_secs(600)))
-                    .pool_max_idle_per_host(256)
-                    .timeout(SETTINGS.request_timeout) // Default request timeout
-                    .connect_timeout(SETTINGS.request_timeout) // Connection timeout
+                Self::http_client_builder(&LoungeConfig::default())
                     .build()
                     .unwrap(),
             )
@@ -132,10 +239,17 @@ impl LoungeClient {
         let initial_state = InnerState {
             lounge_token: lounge_token.to_string(),
             token_refresh_callback: None, // Will be set later via method
+            poll_cycle_callback: None,
+            command_response_callback: None,
+            token_store: None,
+            metrics: None,
         };
 
         Self {
             client,
+            #[cfg(not(target_arch = "wasm32"))]
+            custom_http_client,
+            config: Arc::new(LoungeConfig::default()),
             device_id,
             screen_id: screen_id.to_string(),
             device_name: device_name.to_string(),
@@ -148,7 +262,183 @@ impl LoungeClient {
             shutdown_notify: Arc::new(Notify::new()),
             aid_atomic: Arc::new(AtomicU32::new(0)),
             stop_signal: Arc::new(AtomicBool::new(false)),
+            last_error_response: Arc::new(Mutex::new(None)),
+            player_state: Arc::new(std::sync::RwLock::new(CurrentState::default())),
+            throughput: Arc::new(Mutex::new(None)),
+            message_sizes: Arc::new(Mutex::new(MessageSizeStats::default())),
+            commands_in_flight: Arc::new(watch::Sender::new(0)),
+            command_throttle: Arc::new(CommandThrottle::default()),
+            event_router: EventRouter::default(),
+            event_history: None,
+            clock_skew: Arc::new(Mutex::new(ClockSkewEstimator::default())),
+            automation_enabled: Arc::new(AtomicBool::new(true)),
+            last_token_refresh: Arc::new(tokio::sync::Mutex::new(TokenRefreshState::default())),
+            network_changed: Arc::new(Notify::new()),
+            connected_since: Arc::new(Mutex::new(None)),
+            last_poll_end_reason: Arc::new(Mutex::new(None)),
+            last_event_at: Arc::new(Mutex::new(None)),
+            sessions: Arc::new(SessionTracker::new(
+                SETTINGS.event_buffer_capacity,
+                LoungeConfig::default().max_tracked_sessions,
+                None,
+            )),
+            debug_mode: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Start building a client with non-default HTTP or connection settings.
+    pub fn builder(screen_id: &str, lounge_token: &str, device_name: &str) -> LoungeClientBuilder {
+        LoungeClientBuilder::new(screen_id, lounge_token, device_name)
+    }
+
+    /// The settings of the HTTP client used unless a custom one is passed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn http_client_builder(config: &LoungeConfig) -> reqwest::ClientBuilder {
+        let builder = match &config.proxy {
+            Some(proxy) => Client::builder().proxy(proxy.clone()),
+            None => Client::builder(),
+        };
+        builder
+            .pool_idle_timeout(Some(Duration::from_secs(600)))
+            .pool_max_idle_per_host(256)
+            .timeout(SETTINGS.request_timeout) // Default request timeout
+            .connect_timeout(SETTINGS.request_timeout) // Connection timeout
+            // Follow regional gateway redirects on bind/poll; 307/308 keep
+            // the method and form body of bind POSTs
+            .redirect(reqwest::redirect::Policy::limited(10))
+    }
+
+    /// The browser's fetch pools connections, follows redirects and applies
+    /// timeouts itself; requests set their own timeouts where it matters.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn http_client_builder(_config: &LoungeConfig) -> reqwest::ClientBuilder {
+        Client::builder()
+    }
+
+    /// Replace the default [`LoungeConfig`]. Must be called before `connect()`
+    /// and before subscribing to events.
+    ///
+    /// A proxy in `config` needs a new HTTP client. Fails if it can't be
+    /// built, rather than leaving the client to bypass the proxy.
+    pub fn with_config(self, config: LoungeConfig) -> Result<Self, LoungeError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if config.proxy.is_some() && !self.custom_http_client {
+            let http_client = Self::http_client_builder(&config).build()?;
+            let mut client = self.apply_config(config);
+            client.client = Arc::new(http_client);
+            return Ok(client);
         }
+        Ok(self.apply_config(config))
+    }
+
+    /// Everything [`Self::with_config`] does besides replacing the HTTP client,
+    /// for callers whose client already matches `config`.
+    pub(crate) fn apply_config(mut self, config: LoungeConfig) -> Self {
+        let (event_tx, _) = broadcast::channel(config.event_channel_capacity);
+        self.event_sender = event_tx;
+        self.event_history = config.event_history_window.map(|window| {
+            Arc::new(Mutex::new(EventHistory::new(
+                window,
+                config.event_history_capacity,
+            )))
+        });
+        self.sessions = Arc::new(SessionTracker::new(
+            config.event_channel_capacity,
+            config.max_tracked_sessions,
+            config.session_ttl,
+        ));
+        self.config = Arc::new(config);
+        self
+    }
+
+    pub fn config(&self) -> &LoungeConfig {
+        &self.config
+    }
+
+    /// Emit every received event's type and JSON payload as `LoungeEvent::Raw`,
+    /// just before its parsed form, for troubleshooting event shapes in the
+    /// field. Takes effect immediately, including on a running connection.
+    pub fn enable_debug_mode(&self) {
+        self.debug_mode.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop emitting `LoungeEvent::Raw`.
+    pub fn disable_debug_mode(&self) {
+        self.debug_mode.store(false, Ordering::SeqCst);
+    }
+
+    /// Pause or resume the automatic playback actions enabled in the config
+    /// (such as `auto_skip_on_error`), e.g. while the user takes manual control.
+    /// Takes effect immediately, including on a running connection.
+    pub fn set_automation_enabled(&self, enabled: bool) {
+        self.automation_enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn automation_enabled(&self) -> bool {
+        self.automation_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Tell the client the network came back, e.g. after the device woke from
+    /// sleep. If the connection manager is waiting to reconnect it retries right
+    /// away instead of sitting out the backoff; should that attempt fail too,
+    /// backoff starts over from the minimum. Has no effect otherwise.
+    pub fn notify_network_changed(&self) {
+        debug!("Network change reported");
+        self.network_changed.notify_waiters();
+    }
+
+    /// A client for a screen whose credentials are in `store`, reusing the
+    /// stored device ID. Refreshed tokens are saved back, as with
+    /// [`Self::set_token_store`]. `None` if the store has nothing for the screen.
+    pub fn from_token_store(
+        store: Arc<dyn TokenStore>,
+        screen_id: &str,
+        device_name: &str,
+    ) -> Result<Option<Self>, LoungeError> {
+        let Some(stored) = store.load(screen_id)? else {
+            return Ok(None);
+        };
+        let client = Self::new(
+            &stored.screen_id,
+            &stored.lounge_token,
+            device_name,
+            stored.device_id.as_deref(),
+            None,
+        );
+        Ok(Some(client.with_token_store(store)))
+    }
+
+    /// Attach a store to a client that isn't shared yet.
+    pub(crate) fn with_token_store(self, store: Arc<dyn TokenStore>) -> Self {
+        self.shared_state
+            .try_write()
+            .expect("a new client's state is not shared")
+            .token_store = Some(store);
+        self
+    }
+
+    /// Save every refreshed token to `store`, and forget the screen there once
+    /// it turns out to be unpaired. Saving happens before the token refresh
+    /// callback runs; a failed save is logged and doesn't fail the refresh.
+    pub async fn set_token_store(&self, store: Arc<dyn TokenStore>) {
+        self.shared_state.write().await.token_store = Some(store);
+        debug!("Token store set.");
+    }
+
+    /// Attach a metrics sink to a client that isn't shared yet.
+    pub(crate) fn with_metrics(self, metrics: Arc<dyn Metrics>) -> Self {
+        self.shared_state
+            .try_write()
+            .expect("a new client's state is not shared")
+            .metrics = Some(metrics);
+        self
+    }
+
+    /// Report poll cycles, decoded messages, bind attempts, reconnects and
+    /// command requests to `metrics`, e.g. to export them to Prometheus.
+    pub async fn set_metrics(&self, metrics: Arc<dyn Metrics>) {
+        self.shared_state.write().await.metrics = Some(metrics);
+        debug!("Metrics set.");
     }
 
     pub async fn set_token_refresh_callback<F>(&self, callback: F)
@@ -160,6 +450,29 @@ impl LoungeClient {
         debug!("Token refresh callback set.");
     }
 
+    /// Register a callback invoked at the start and end of every long-poll cycle,
+    /// e.g. to feed metrics or to tell a stalled poll from an idle one.
+    pub async fn set_poll_cycle_callback<F>(&self, callback: F)
+    where
+        F: Fn(&PollCycleEvent) + Send + Sync + 'static,
+    {
+        let mut state_guard = self.shared_state.write().await;
+        state_guard.poll_cycle_callback = Some(Box::new(callback));
+        debug!("Poll cycle callback set.");
+    }
+
+    /// Register a callback invoked with the command name(s), HTTP status and raw
+    /// response body of every command request, for protocol research. Only called
+    /// when `observe_command_responses` is enabled in the config.
+    pub async fn set_command_response_callback<F>(&self, callback: F)
+    where
+        F: Fn(&str, u16, &str) + Send + Sync + 'static,
+    {
+        let mut state_guard = self.shared_state.write().await;
+        state_guard.command_response_callback = Some(Box::new(callback));
+        debug!("Command response callback set.");
+    }
+
     pub fn device_id(&self) -> &str {
         &self.device_id
     }
@@ -168,23 +481,375 @@ impl LoungeClient {
         &self.screen_id
     }
 
+    /// Short identifier derived from the device and screen IDs, stable across
+    /// reconnects and restarts. Handy for tagging logs when controlling many screens.
+    pub fn connection_fingerprint(&self) -> String {
+        let key = format!("{}:{}", self.device_id, self.screen_id);
+        format!("{:012x}", utils::fnv1a_64(key.as_bytes()) >> 16)
+    }
+
     pub fn event_receiver(&self) -> broadcast::Receiver<LoungeEvent> {
         self.event_sender.subscribe()
     }
 
+    /// Events as a [`Stream`], starting from now. Falling behind is handled
+    /// as set by `event_lag_policy`; the stream ends once the client is gone.
+    /// Pin it (e.g. `Box::pin`) before calling `next()` on it.
+    pub fn events(&self) -> impl Stream<Item = LoungeEvent> + Send + 'static {
+        let policy = self.config.event_lag_policy;
+        futures::stream::unfold(self.event_sender.subscribe(), move |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(broadcast::error::RecvError::Lagged(n)) => match policy {
+                        LagPolicy::Skip => {
+                            warn!("Event stream lagged, skipped {} events", n);
+                        }
+                        LagPolicy::End => {
+                            warn!("Event stream lagged by {} events, ending it", n);
+                            return None;
+                        }
+                    },
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Only the events carrying a `T`, e.g. `events_of::<NowPlaying>()`.
+    pub fn events_of<T>(&self) -> impl Stream<Item = T> + Send + 'static
+    where
+        T: FromLoungeEvent + Send + 'static,
+    {
+        self.events()
+            .filter_map(|event| futures::future::ready(T::from_event(event)))
+    }
+
+    /// Events of the given kinds only, e.g.
+    /// `subscribe_filtered(EventKind::NowPlaying | EventKind::PlaybackSession)`.
+    /// Each subscription has its own queue of `filtered_event_capacity`
+    /// events, so a slow subscriber drops its own events rather than lagging
+    /// other listeners. Must be called from within a tokio runtime.
+    pub fn subscribe_filtered(&self, kinds: impl Into<EventKindSet>) -> FilteredEvents {
+        self.event_router.subscribe(
+            &self.event_sender,
+            kinds.into(),
+            self.config.filtered_event_capacity,
+        )
+    }
+
     /// Get the current state of the connection manager.
     pub fn current_state(&self) -> ConnectionState {
         self.connection_state_rx.borrow().clone()
     }
 
+    /// A receiver for connection state transitions, e.g. to `wait_for` a state
+    /// or drive a UI. Like any watch channel, it only keeps the latest state,
+    /// so quick intermediate states may be missed.
+    pub fn state_receiver(&self) -> watch::Receiver<ConnectionState> {
+        self.connection_state_rx.clone()
+    }
+
+    /// When the current connection was established, or `None` while not connected.
+    /// A reconnect starts a new connection.
+    pub fn connected_since(&self) -> Option<rt::StdInstant> {
+        if self.current_state() != ConnectionState::Connected {
+            return None;
+        }
+        *self
+            .connected_since
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// How long the current connection has been established, e.g. for a
+    /// "connected for 2h 13m" display. `None` while not connected.
+    pub fn connected_duration(&self) -> Option<Duration> {
+        self.connected_since()
+            .map(|since| utils::now().saturating_duration_since(since))
+    }
+
+    /// Why the most recent long-poll cycle ended, e.g. to tell the server closing
+    /// polls from the client timing them out. `None` before the first cycle ends.
+    pub fn last_poll_end_reason(&self) -> Option<PollEndReason> {
+        *self
+            .last_poll_end_reason
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// When the screen last sent an event, keep-alive noops included. A growing
+    /// gap means the stream has gone quiet. `None` until the first event.
+    pub fn last_event_at(&self) -> Option<rt::StdInstant> {
+        *self
+            .last_event_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Snapshot of the session identifiers and counters, for debugging.
+    pub async fn debug_session(&self) -> SessionDebug {
+        let connected = self.current_state() == ConnectionState::Connected;
+        let aid = self.aid_atomic.load(Ordering::SeqCst);
+        self.session_state.read().await.debug_view(aid, connected)
+    }
+
+    /// Events received within the configured history window, oldest first. Empty
+    /// unless `event_history_window` is set in the config.
+    pub fn recent_events(&self) -> Vec<(rt::StdInstant, LoungeEvent)> {
+        self.event_history
+            .as_ref()
+            .map(|history| {
+                history
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .recent()
+            })
+            .unwrap_or_default()
+    }
+
+    /// How far the TV's reported playback progress drifts from local time, from
+    /// consecutive `StateChange` events while playing. `None` until measured.
+    pub fn estimated_clock_skew(&self) -> Option<Duration> {
+        self.clock_skew
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .estimate()
+    }
+
+    /// Snapshot of the latest player state assembled from events.
+    pub fn player_state(&self) -> CurrentState {
+        self.player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The TV's volume as last reported, or as last set by [`Self::volume_up`]
+    /// and [`Self::volume_down`]. `None` until known.
+    pub fn current_volume(&self) -> Option<Volume> {
+        self.player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .current_volume()
+    }
+
+    /// Whether the TV has no video loaded (home screen, screensaver), so that apps
+    /// can avoid commands that would wake it. `None` until the TV reported its state.
+    pub fn screen_is_idle(&self) -> Option<bool> {
+        self.player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_idle()
+    }
+
+    /// Quality levels (e.g. 1080) the current video can play at, as last reported
+    /// by the TV, for populating a quality picker. Empty until reported.
+    pub fn available_qualities(&self) -> Vec<i32> {
+        self.player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .video_quality
+            .as_ref()
+            .map(VideoQualityChanged::quality_levels)
+            .unwrap_or_default()
+    }
+
+    /// Subscribe to `PlaybackSession`s only, without the other events.
+    pub fn session_receiver(&self) -> broadcast::Receiver<PlaybackSession> {
+        self.sessions.subscribe()
+    }
+
+    /// The latest session of the playback identified by `cpn`, if it is among
+    /// the recently tracked ones.
+    pub fn session_by_cpn(&self, cpn: &str) -> Option<PlaybackSession> {
+        self.sessions.by_cpn(cpn)
+    }
+
+    /// The most recently updated session on this client's screen. A client
+    /// talks to a single screen, so this is the per-device session.
+    pub fn current_session(&self) -> Option<PlaybackSession> {
+        self.sessions.all().pop()
+    }
+
+    /// What `device_id` is playing: the latest session from the queue the last
+    /// `loungeStatus` listed the device in. `None` for devices not listed.
+    pub fn session_for_device(&self, device_id: &str) -> Option<PlaybackSession> {
+        self.sessions.for_device(device_id)
+    }
+
+    /// All tracked sessions still within the configured `session_ttl`, least
+    /// recently updated first.
+    pub fn all_sessions(&self) -> Vec<PlaybackSession> {
+        self.sessions.all()
+    }
+
+    /// Tracked sessions that are currently playing.
+    pub fn playing_sessions(&self) -> Vec<PlaybackSession> {
+        self.sessions
+            .all()
+            .into_iter()
+            .filter(|session| session.status() == PlaybackStatus::Playing)
+            .collect()
+    }
+
+    /// The current video's subtitle, audio and quality selections, as last
+    /// reported by the TV.
+    pub fn track_settings(&self) -> TrackSettings {
+        self.player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .track_settings()
+    }
+
+    /// Share URL of the playlist the TV is playing from, according to the last
+    /// `nowPlaying`. `None` when no playlist is known.
+    pub fn current_playlist_url(&self) -> Option<String> {
+        let state = self
+            .player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let list_id = state.now_playing.as_ref()?.list_id.as_deref()?;
+        (!list_id.is_empty()).then(|| format!("https://www.youtube.com/playlist?list={}", list_id))
+    }
+
+    /// Whether the TV is currently playing an ad, e.g. to hide seek controls.
+    pub fn is_playing_ad(&self) -> bool {
+        self.player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_playing_ad()
+    }
+
+    /// Bytes per second received on the event stream over the last minute, or
+    /// `None` before the first connection. A rate near zero while `Connected`
+    /// hints at a stalled stream.
+    pub fn event_throughput(&self) -> Option<f64> {
+        self.throughput
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()?
+            .rate()
+    }
+
+    /// Sizes of all messages decoded from the event stream so far, e.g. to
+    /// choose a `STREAMING_BUFFER_CAPACITY` that fits typical messages.
+    pub fn message_size_stats(&self) -> MessageSizeStats {
+        *self
+            .message_sizes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Status and diagnostic headers (`retry-after`, `x-goog-*`, ...) of the most
+    /// recent unsuccessful HTTP response, if any.
+    pub fn last_error_response(&self) -> Option<ResponseDiagnostics> {
+        self.last_error_response
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Poison the client's std locks by panicking on another thread while
+    /// holding them, so tests can check it keeps working after a panic
+    /// elsewhere. Not part of the supported API.
+    #[doc(hidden)]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poison_locks(&self) {
+        let player_state = self.player_state.clone();
+        let throughput = self.throughput.clone();
+        let message_sizes = self.message_sizes.clone();
+        let clock_skew = self.clock_skew.clone();
+        let connected_since = self.connected_since.clone();
+        let last_poll_end_reason = self.last_poll_end_reason.clone();
+        let last_event_at = self.last_event_at.clone();
+        let last_error_response = self.last_error_response.clone();
+        let _ = std::thread::spawn(move || {
+            let _guards = (
+                player_state.write(),
+                throughput.lock(),
+                message_sizes.lock(),
+                clock_skew.lock(),
+                connected_since.lock(),
+                last_poll_end_reason.lock(),
+                last_event_at.lock(),
+                last_error_response.lock(),
+            );
+            panic!("poisoning the client's locks");
+        })
+        .join();
+    }
+
+    /// Wait until every command sent so far has been acknowledged by the screen
+    /// or has failed, e.g. before reading state or shutting down.
+    pub async fn await_pending_commands(&self) -> Result<(), LoungeError> {
+        let mut in_flight = self.commands_in_flight.subscribe();
+        // The sender lives as long as `self`, so this cannot fail
+        let _ = in_flight.wait_for(|count| *count == 0).await;
+        Ok(())
+    }
+
+    /// How many command requests are waiting to be sent or for the screen to
+    /// answer. A batch from `send_commands` counts once. A persistently high
+    /// count points at a slow or stuck screen.
+    pub fn in_flight_commands(&self) -> usize {
+        *self.commands_in_flight.borrow()
+    }
+
+    /// Register a callback invoked with the previous and new `ConnectionState` on
+    /// every transition. Driven by the state watch channel, so rapid intermediate
+    /// states may be coalesced; each call's `old` is always the prior call's `new`.
+    /// Must be called from within a tokio runtime.
+    pub fn on_state_change<F>(&self, callback: F) -> rt::JoinHandle<()>
+    where
+        F: Fn(&ConnectionState, &ConnectionState) + Send + Sync + 'static,
+    {
+        let mut rx = self.connection_state_rx.clone();
+        let mut old = rx.borrow_and_update().clone();
+        rt::spawn(async move {
+            // Ends once every sender (client and manager task) has been dropped
+            while rx.changed().await.is_ok() {
+                let new = rx.borrow_and_update().clone();
+                if new != old {
+                    callback(&old, &new);
+                    old = new;
+                }
+            }
+        })
+    }
+
+    /// Whether `code` looks like a TV pairing code: 12 digits, optionally grouped
+    /// with spaces or dashes as the TV displays them ("123 456 789 012").
+    /// Only checks the format; [`Self::pair_with_screen`] tells whether the code
+    /// is actually valid.
+    pub fn is_valid_pairing_code_format(code: &str) -> bool {
+        let mut digits = 0;
+        for c in code.trim().chars() {
+            match c {
+                '0'..='9' => digits += 1,
+                ' ' | '-' => {}
+                _ => return false,
+            }
+        }
+        digits == 12
+    }
+
     /// Pair with a screen using a pairing code displayed on the TV
     pub async fn pair_with_screen(pairing_code: &str) -> Result<Screen, LoungeError> {
+        Self::pair_with_screen_with_config(pairing_code, &LoungeConfig::default()).await
+    }
+
+    /// [`Self::pair_with_screen`] against `config`'s API base and proxy.
+    pub async fn pair_with_screen_with_config(
+        pairing_code: &str,
+        config: &LoungeConfig,
+    ) -> Result<Screen, LoungeError> {
         info!("Pairing with screen using code: {}", pairing_code);
-        let client = Client::new();
+        let client = Self::http_client_builder(config).build()?;
         let params = [("pairing_code", pairing_code)];
 
         let response = client
-            .post("https://www.youtube.com/api/lounge/pairing/get_screen")
+            .post(config.endpoint("pairing/get_screen"))
             .form(&params)
             .send()
             .await?;
@@ -207,35 +872,125 @@ impl LoungeClient {
         Ok(screen_response.screen)
     }
 
+    /// Normalize a code from the TV's "Link with TV code" setting: spaces and
+    /// dashes dropped, and zero-padded to 12 digits, as codes copied or read
+    /// out often lose their leading zeros.
+    pub fn normalize_tv_code(code: &str) -> Result<String, LoungeError> {
+        let digits: String = code
+            .trim()
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-'))
+            .collect();
+        if digits.is_empty() || digits.len() > 12 || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(LoungeError::InvalidPairingCode(code.to_string()));
+        }
+        Ok(format!("{:0>12}", digits))
+    }
+
+    /// Pair with a screen using the code from its "Link with TV code" setting.
+    /// The code is normalized with [`Self::normalize_tv_code`] first; TV codes
+    /// are redeemed at the same `pairing/get_screen` endpoint as the codes on
+    /// the pairing screen.
+    pub async fn pair_with_tv_code(code: &str) -> Result<Screen, LoungeError> {
+        Self::pair_with_tv_code_with_config(code, &LoungeConfig::default()).await
+    }
+
+    /// [`Self::pair_with_tv_code`] against `config`'s API base and proxy.
+    pub async fn pair_with_tv_code_with_config(
+        code: &str,
+        config: &LoungeConfig,
+    ) -> Result<Screen, LoungeError> {
+        let code = Self::normalize_tv_code(code)?;
+        Self::pair_with_screen_with_config(&code, config).await
+    }
+
+    /// Pair with a screen by its ID, as found by [`discovery::discover_screens`],
+    /// without a pairing code.
+    pub async fn pair_with_screen_id(screen_id: &str) -> Result<Screen, LoungeError> {
+        Self::pair_with_screen_id_with_config(screen_id, &LoungeConfig::default()).await
+    }
+
+    /// [`Self::pair_with_screen_id`] against `config`'s API base and proxy.
+    pub async fn pair_with_screen_id_with_config(
+        screen_id: &str,
+        config: &LoungeConfig,
+    ) -> Result<Screen, LoungeError> {
+        info!("Pairing with screen using screen_id: {}", screen_id);
+        Self::fetch_lounge_token(config, screen_id).await
+    }
+
+    /// Fetch a fresh lounge token for a paired screen.
+    ///
+    /// Returns [`LoungeError::ScreenUnpaired`] when YouTube no longer knows the
+    /// screen, in which case only pairing again with a new code will help.
     pub async fn refresh_lounge_token(screen_id: &str) -> Result<Screen, LoungeError> {
+        Self::fetch_lounge_token(&LoungeConfig::default(), screen_id).await
+    }
+
+    /// [`Self::refresh_lounge_token`] against `config`'s API base and proxy.
+    pub async fn refresh_lounge_token_with_config(
+        screen_id: &str,
+        config: &LoungeConfig,
+    ) -> Result<Screen, LoungeError> {
+        Self::fetch_lounge_token(config, screen_id).await
+    }
+
+    async fn fetch_lounge_token(
+        config: &LoungeConfig,
+        screen_id: &str,
+    ) -> Result<Screen, LoungeError> {
         info!("Refreshing lounge token for screen_id: {}", screen_id);
-        let client = Client::new();
+        let client = Self::http_client_builder(config).build()?;
         let params = [("screen_ids", screen_id)];
 
         let response = client
-            .post("https://www.youtube.com/api/lounge/pairing/get_lounge_token_batch")
+            .post(config.endpoint("pairing/get_lounge_token_batch"))
             .form(&params)
             .send()
             .await?;
 
+        let context = ErrorContext::new(screen_id, "pairing/get_lounge_token_batch")
+            .status(response.status().as_u16());
         if !response.status().is_success() {
             let status = response.status();
+            let diagnostics = ResponseDiagnostics::from_response(&response);
+            // Auth throttling may come as 401/403 with a Retry-After, besides 429
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || diagnostics.retry_after().is_some()
+            {
+                warn!(%status, retry_after = ?diagnostics.retry_after(), "Token refresh rate limited");
+                let error = LoungeError::RateLimited {
+                    retry_after: diagnostics.retry_after(),
+                };
+                return Err(config.contextualize(error, context));
+            }
             let body_text = response.text().await.unwrap_or_default();
             let error_msg = format!("Failed to refresh token: {}: {}", status, body_text);
             error!("{}", error_msg);
-            if status == reqwest::StatusCode::UNAUTHORIZED {
-                return Err(LoungeError::TokenExpired);
-            }
-            return Err(LoungeError::InvalidResponse(error_msg));
+            let error = match status {
+                reqwest::StatusCode::UNAUTHORIZED => LoungeError::TokenExpired,
+                reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE => {
+                    LoungeError::ScreenUnpaired
+                }
+                _ => LoungeError::InvalidResponse(error_msg),
+            };
+            return Err(config.contextualize(error, context.body(&body_text)));
         }
 
         let screens_response = response.json::<ScreensResponse>().await?;
 
+        // An unpaired screen is simply left out of the batch (or comes back without a token)
         let screen = screens_response
             .screens
             .into_iter()
-            .next()
-            .ok_or_else(|| LoungeError::InvalidResponse("No screens returned".to_string()))?;
+            .find(|screen| screen.screen_id == screen_id && !screen.lounge_token.is_empty())
+            .ok_or_else(|| {
+                warn!(
+                    "No lounge token returned for screen_id {}, screen is no longer paired",
+                    screen_id
+                );
+                config.contextualize(LoungeError::ScreenUnpaired, context)
+            })?;
 
         debug!(
             "Token refreshed successfully for screen: {}",
@@ -259,14 +1014,19 @@ impl LoungeClient {
         let params = [("lounge_token", &token)];
         let response = self
             .client
-            .post("https://www.youtube.com/api/lounge/pairing/get_screen_availability")
+            .post(self.config.endpoint("pairing/get_screen_availability"))
             .form(&params)
             .send()
             .await?;
+        record_error_response(&self.last_error_response, &response);
 
         if response.status() == reqwest::StatusCode::UNAUTHORIZED {
             warn!("Token expired for screen_id: {}", self.screen_id);
-            return Err(LoungeError::TokenExpired);
+            let context =
+                ErrorContext::new(&self.screen_id, "pairing/get_screen_availability").status(401);
+            return Err(self
+                .config
+                .contextualize(LoungeError::TokenExpired, context));
         }
 
         let available = response.status().is_success();
@@ -278,18 +1038,9 @@ impl LoungeClient {
     pub async fn check_screen_availability_with_refresh(&self) -> Result<bool, LoungeError> {
         match self.check_screen_availability().await {
             Ok(available) => Ok(available),
-            Err(LoungeError::TokenExpired) => {
+            Err(e) if matches!(e.without_context(), LoungeError::TokenExpired) => {
                 info!("Refreshing expired token (check_screen_availability_with_refresh)");
-                let screen = Self::refresh_lounge_token(&self.screen_id).await?;
-                {
-                    let mut state = self.shared_state.write().await;
-                    state.lounge_token = screen.lounge_token.clone();
-                    debug!("Shared state updated with refreshed token.");
-                    if let Some(ref callback) = state.token_refresh_callback {
-                        debug!("Calling token refresh callback.");
-                        callback(&self.screen_id, &screen.lounge_token);
-                    }
-                }
+                self.refresh_token().await?;
                 self.check_screen_availability().await
             }
             Err(e) => Err(e),
@@ -314,33 +1065,41 @@ impl LoungeClient {
 
         let response = self
             .client
-            .post("https://www.youtube.com/api/lounge/bc/bind")
+            .post(self.config.endpoint("bc/bind"))
             .query(&params)
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(form_data)
             .send()
             .await?;
+        record_error_response(&self.last_error_response, &response);
 
+        let context =
+            ErrorContext::new(&self.screen_id, "bc/bind").status(response.status().as_u16());
         match response.status().as_u16() {
             401 => {
                 error!(
                     "Initial bind failed: 401 Unauthorized. Token is likely invalid or expired."
                 );
-                return Err(LoungeError::TokenExpired);
+                return Err(self
+                    .config
+                    .contextualize(LoungeError::TokenExpired, context));
             }
             404 => {
                 error!(
-                    "Initial bind failed: 404 Not Found. Screen ID might be invalid or unpaired."
+                    "Initial bind failed: 404 Not Found. Screen is offline or the ID is invalid."
                 );
-                return Err(LoungeError::InvalidResponse(
-                    "Screen not found (404)".to_string(),
-                ));
+                return Err(self
+                    .config
+                    .contextualize(LoungeError::ScreenOffline, context));
             }
             status if !response.status().is_success() => {
                 let body_text = response.text().await.map_err(LoungeError::RequestFailed)?;
                 let error_msg = format!("Initial bind failed: {}: {}", status, body_text);
                 error!("{}", error_msg);
-                return Err(LoungeError::InvalidResponse(error_msg));
+                return Err(self.config.contextualize(
+                    LoungeError::InvalidResponse(error_msg),
+                    context.body(&body_text),
+                ));
             }
             _ => {} // Success, proceed
         }
@@ -363,14 +1122,16 @@ impl LoungeClient {
                     "Initial bind response successful, but failed to extract SID/GSessionID. Body: {:?}",
                     String::from_utf8_lossy(&body)
                 );
-                Err(LoungeError::InvalidResponse(
-                    "Failed to extract session IDs from bind response".to_string(),
-                ))
+                Err(LoungeError::session_id_extraction_failed(&body))
             }
         }
     }
 
     /// Establish the initial connection and start the background connection manager.
+    ///
+    /// There's no need to call [`Self::check_screen_availability`] first: the
+    /// bind reports the same, and a screen that is off or unknown fails
+    /// straight away with [`LoungeError::ScreenOffline`].
     pub async fn connect(&self) -> Result<(), LoungeError> {
         info!("[{}] Connecting to screen", self.screen_id);
 
@@ -406,7 +1167,7 @@ impl LoungeClient {
                 // let _ = self.connection_state_tx.send(ConnectionState::Connected);
 
                 // Start the persistent connection manager task
-                self.start_connection_manager().await; // Make async to store handle
+                self.start_connection_manager(None).await; // Make async to store handle
 
                 info!("Connection established and manager task started.");
                 Ok(())
@@ -415,9 +1176,9 @@ impl LoungeClient {
                 error!(error = %e, "Initial connection failed");
                 let _ = self
                     .connection_state_tx
-                    .send(ConnectionState::Failed(format!(
-                        "Initial connection failed: {}",
-                        e
+                    .send(ConnectionState::Failed(Failure::new(
+                        format!("Initial connection failed: {}", e),
+                        &e,
                     )));
                 // Don't start the manager task if initial connect fails
                 Err(e)
@@ -429,33 +1190,22 @@ impl LoungeClient {
     pub async fn connect_with_refresh(&self) -> Result<(), LoungeError> {
         match self.connect().await {
             Ok(()) => Ok(()),
-            Err(LoungeError::TokenExpired) => {
+            Err(e) if matches!(e.without_context(), LoungeError::TokenExpired) => {
                 info!("Refreshing expired token (connect_with_refresh)");
-                match Self::refresh_lounge_token(&self.screen_id).await {
-                    Ok(screen) => {
-                        // Update shared state *before* retrying connect
-                        {
-                            let mut state = self.shared_state.write().await;
-                            state.lounge_token = screen.lounge_token.clone();
-                            debug!("Shared state updated with refreshed token.");
-                            if let Some(ref callback) = state.token_refresh_callback {
-                                debug!("Calling token refresh callback.");
-                                callback(&self.screen_id, &screen.lounge_token);
-                            }
-                        }
+                match self.refresh_token().await {
+                    Ok(()) => {
                         debug!("Retrying connect after successful token refresh.");
                         // Retry the connection attempt
                         self.connect().await
                     }
-                    Err(refresh_err) => {
-                        error!(error = %refresh_err, "Token refresh failed during connect_with_refresh");
-                        let err = LoungeError::TokenRefreshFailed(Box::new(refresh_err));
-                        let _ = self
-                            .connection_state_tx
-                            .send(ConnectionState::Failed(format!(
-                                "Token refresh failed: {}",
-                                err
-                            )));
+                    Err(err) => {
+                        error!(error = %err, "Token refresh failed during connect_with_refresh");
+                        let _ =
+                            self.connection_state_tx
+                                .send(ConnectionState::Failed(Failure::new(
+                                    format!("Token refresh failed: {}", err),
+                                    &err,
+                                )));
                         Err(err)
                     }
                 }
@@ -464,32 +1214,116 @@ impl LoungeClient {
         }
     }
 
-    // Make async to allow storing handle
-    async fn start_connection_manager(&self) {
+    /// Connect, resuming `snapshot` if there is one (e.g. as persisted before
+    /// a restart) so the TV doesn't see a new device connect. A session the
+    /// server has since dropped (400/410) is replaced by a fresh bind.
+    ///
+    /// Events up to the snapshot's AID count as processed, so ones the server
+    /// replays on resuming aren't emitted again.
+    pub async fn connect_with_snapshot(
+        &self,
+        snapshot: Option<SessionSnapshot>,
+    ) -> Result<(), LoungeError> {
+        let Some(snapshot) = snapshot else {
+            return self.connect().await;
+        };
+        info!("[{}] Resuming session", self.screen_id);
+
+        self.stop_signal.store(false, Ordering::SeqCst);
+        while self.shutdown_notify.notified().now_or_never().is_some() {}
+
+        *self.session_state.write().await = SessionState::from_snapshot(&snapshot);
+        self.aid_atomic.store(snapshot.aid, Ordering::SeqCst);
+        self.start_connection_manager(Some(snapshot.aid)).await;
+        Ok(())
+    }
+
+    /// The current session, for persisting and later passing to
+    /// [`Self::connect_with_snapshot`]. `None` while no session is bound.
+    ///
+    /// [`Self::disconnect`], [`Self::shutdown`] and dropping a client with a
+    /// reaper terminate the session, after which it can't be resumed. Stop
+    /// with [`Self::go_idle`] instead to keep it.
+    pub async fn session_snapshot(&self) -> Option<SessionSnapshot> {
+        let aid = self.aid_atomic.load(Ordering::SeqCst);
+        self.session_state.read().await.snapshot(aid)
+    }
+
+    // Make async to allow storing handle. `resume_aid` marks events up to that
+    // AID as already processed.
+    async fn start_connection_manager(&self, resume_aid: Option<u32>) {
+        *self
+            .throughput
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) =
+            Some(ThroughputMeter::new(ThroughputMeter::DEFAULT_WINDOW));
         // Create the context struct
         let ctx = ConnectionManagerContext {
             client: self.client.clone(),
+            config: self.config.clone(),
             screen_id: self.screen_id.clone(),
             device_name: self.device_name.clone(),
             device_id: self.device_id.clone(),
             shared_state: self.shared_state.clone(),
             session_state_rwlock: self.session_state.clone(),
             event_sender: self.event_sender.clone(),
-            latest_now_playing: Arc::new(RwLock::new(None::<NowPlaying>)), // Create locally
+            pipeline: EventPipeline {
+                sender: self.event_sender.clone(),
+                session_cache: Mutex::new(Default::default()),
+                aid_atomic: self.aid_atomic.clone(),
+                player_state: self.player_state.clone(),
+                last_session: Mutex::new(None),
+                aid_tracker: Mutex::new(
+                    resume_aid.map_or_else(AidTracker::default, AidTracker::resume_from),
+                ),
+                decompose_state_change: self.config.decompose_state_change,
+                partial_sessions: self.config.emit_partial_sessions,
+                state_change_parts: Mutex::new(Default::default()),
+                history: self.event_history.clone(),
+                clock_skew: self.clock_skew.clone(),
+                rotated_gsessionid: Mutex::new(None),
+                last_event_at: self.last_event_at.clone(),
+                sessions: self.sessions.clone(),
+                debug_mode: self.debug_mode.clone(),
+            },
             aid_atomic: self.aid_atomic.clone(),
             shutdown_notify: self.shutdown_notify.clone(),
+            network_changed: self.network_changed.clone(),
+            connected_since: self.connected_since.clone(),
+            last_poll_end_reason: self.last_poll_end_reason.clone(),
             state_tx: self.connection_state_tx.clone(),
+            commands: self.command_context(),
+            poll_cycles: AtomicU64::new(0),
+            throughput: self.throughput.clone(),
+            message_sizes: self.message_sizes.clone(),
+            last_token_refresh: self.last_token_refresh.clone(),
         };
 
         // Clone Arcs needed *outside* the task's main loop for storing the handle
         let stop_signal = self.stop_signal.clone();
         let management_task_arc = self.management_task.clone();
-
-        let handle = tokio::spawn(async move {
+        // Subscribe before spawning so automation sees events from the very first poll
+        let automation_rx = (ctx.config.auto_skip_on_error
+            || ctx.config.request_initial_state
+            || ctx.config.keepalive_interval.is_some())
+        .then(|| self.event_sender.subscribe());
+        let automation_enabled = self.automation_enabled.clone();
+        let state_tx = self.connection_state_tx.clone();
+
+        let manager = async move {
             // state_tx, shutdown_notify moved in
             info!("Connection manager task started.");
+            let automation_task = automation_rx.map(|rx| {
+                rt::spawn(automation::run(
+                    rx,
+                    ctx.commands.clone(),
+                    automation_enabled,
+                ))
+            });
             let _ = ctx.state_tx.send(ConnectionState::Connecting); // Initial state
-            let mut backoff = SETTINGS.min_backoff;
+            let mut backoff = ctx.config.min_backoff;
+            // What was playing when the session was lost, with `resume_on_reconnect`
+            let mut resume_point: Option<ResumePoint> = None;
             // Outer loop only breaks on explicit shutdown signal
             loop {
                 // Check if termination requested
@@ -527,20 +1361,34 @@ impl LoungeClient {
                          {
                              // --- State: Connected / Polling ---
                              trace!("Manager state: Polling events.");
-                             let _ = ctx.state_tx.send_if_modified(|prev| if *prev != ConnectionState::Connected {*prev = ConnectionState::Connected; true} else {false} );
+                             let _ = ctx.state_tx.send_if_modified(|prev| if *prev != ConnectionState::Connected {
+                                 *ctx.connected_since.lock().unwrap_or_else(PoisonError::into_inner) = Some(utils::now());
+                                 *prev = ConnectionState::Connected;
+                                 true
+                             } else {false} );
                              Self::poll_events(&ctx, &sid, &gsessionid).await // Pass ctx and IDs
                          } else {
                              // --- State: Disconnected / Reconnecting ---
                              debug!("Manager state: Attempting to bind session.");
                              let _ = ctx.state_tx.send_if_modified(|prev| if *prev != ConnectionState::Connecting {*prev = ConnectionState::Connecting; true} else {false} );
-                             Self::attempt_bind(&ctx).await // Pass ctx
+                             let started = Instant::now();
+                             let result = Self::attempt_bind(&ctx).await; // Pass ctx
+                             if let Some(metrics) = Self::metrics(&ctx.shared_state).await {
+                                 metrics.bind_attempt(started.elapsed(), matches!(result, Ok(ConnectionStatus::Success)));
+                             }
+                             if matches!(result, Ok(ConnectionStatus::Success)) {
+                                 if let Some(point) = resume_point.take() {
+                                     Self::restore_resume_point(&ctx, point);
+                                 }
+                             }
+                             result
                          };
 
                          // --- Handle Result ---
                          match result {
                              Ok(ConnectionStatus::Success) => {
                                  // Successful poll or bind, reset backoff. State is Connected or Connecting->Connected.
-                                 backoff = SETTINGS.min_backoff;
+                                 backoff = ctx.config.min_backoff;
                              },
                              Ok(ConnectionStatus::SessionInvalidated) => {
                                  warn!("Session invalidated (e.g., 400/404/410). Clearing session state.");
@@ -549,34 +1397,33 @@ impl LoungeClient {
                                      session_write.sid = None;
                                      session_write.gsessionid = None;
                                  }
+                                 Self::remember_resume_point(&ctx, &mut resume_point);
                                  send_event(&ctx.event_sender, &LoungeEvent::ScreenDisconnected);
                                  let _ = ctx.state_tx.send(ConnectionState::Connecting); // Will attempt to reconnect
                                  // Apply backoff before next attempt
-                                 let delay_duration = calculate_backoff_delay(backoff);
-                                 let _ = ctx.state_tx.send(ConnectionState::WaitingToReconnect { backoff: delay_duration });
-                                 debug!("Backing off for {:?}", delay_duration);
-                                 tokio::select! { // Sleep with interrupt
-                                     _ = sleep(delay_duration) => {},
-                                     _ = ctx.shutdown_notify.notified() => { return; } // Return from async block if interrupted
-                                 }
-                                 backoff = (backoff * 2).min(SETTINGS.max_backoff);
+                                 Self::wait_before_retry(&ctx, &mut backoff).await;
                              },
                              Ok(ConnectionStatus::TokenExpired) => {
                                  warn!("Token expired (401 detected). Attempting refresh.");
-                                 match Self::try_refresh_token(&ctx.screen_id, &ctx.shared_state).await {
-                                     Ok(()) => { info!("Token refreshed successfully."); backoff = SETTINGS.min_backoff; },
+                                 match Self::try_refresh_token(&ctx.config, &ctx.screen_id, &ctx.device_id, &ctx.device_name, &ctx.shared_state, &ctx.last_token_refresh).await {
+                                     Ok(TokenRefresh::Refreshed) => { info!("Token refreshed successfully."); backoff = ctx.config.min_backoff; },
+                                     Ok(TokenRefresh::Reused) => {
+                                         // The token may be the one just rejected; retrying at once
+                                         // would hammer the server until a refresh is allowed again
+                                         warn!("Token was refreshed moments ago, backing off before retrying.");
+                                         Self::wait_before_retry(&ctx, &mut backoff).await;
+                                     }
+                                     Err(e) if matches!(e.without_context(), LoungeError::ScreenUnpaired) => {
+                                         // Retrying cannot help; the user has to pair again
+                                         error!("Screen is no longer paired, stopping connection manager.");
+                                         let _ = ctx.state_tx.send(ConnectionState::Failed(Failure::new(LoungeError::ScreenUnpaired.to_string(), &e)));
+                                         stop_signal.store(true, Ordering::SeqCst);
+                                     }
                                      Err(e) => {
                                          error!(error = %e, "Token refresh attempt failed.");
-                                         let _ = ctx.state_tx.send(ConnectionState::Failed(format!("Token refresh failed: {}", e)));
+                                         let _ = ctx.state_tx.send(ConnectionState::Failed(Failure::new(format!("Token refresh failed: {}", e), &e)));
                                          // Apply backoff before next attempt
-                                         let delay_duration = calculate_backoff_delay(backoff);
-                                         let _ = ctx.state_tx.send(ConnectionState::WaitingToReconnect { backoff: delay_duration });
-                                         debug!("Backing off for {:?}", delay_duration);
-                                         tokio::select! { // Sleep with interrupt
-                                             _ = sleep(delay_duration) => {},
-                                             _ = ctx.shutdown_notify.notified() => { return; } // Return from async block if interrupted
-                                         }
-                                         backoff = (backoff * 2).min(SETTINGS.max_backoff);
+                                         Self::wait_before_retry(&ctx, &mut backoff).await;
                                      }
                                  }
                              },
@@ -595,27 +1442,42 @@ impl LoungeClient {
                                          warn!("Clearing session state due to error: {}", e);
                                          session_write.sid = None;
                                          session_write.gsessionid = None;
+                                         Self::remember_resume_point(&ctx, &mut resume_point);
                                          send_event(&ctx.event_sender, &LoungeEvent::ScreenDisconnected);
                                      }
                                  }
                                  // Apply backoff before next attempt
-                                 let delay_duration = calculate_backoff_delay(backoff);
-                                 let _ = ctx.state_tx.send(ConnectionState::WaitingToReconnect { backoff: delay_duration });
-                                 debug!("Backing off for {:?}", delay_duration);
-                                 tokio::select! { // Sleep with interrupt
-                                     _ = sleep(delay_duration) => {},
-                                     _ = ctx.shutdown_notify.notified() => { return; } // Return from async block if interrupted
-                                 }
-                                 backoff = (backoff * 2).min(SETTINGS.max_backoff);
+                                 Self::wait_before_retry(&ctx, &mut backoff).await;
                              },
                          }
                       } => { /* Normal async block completed */ }
                 } // end select!
             } // end loop
 
+            if let Some(task) = automation_task {
+                task.abort();
+            }
             info!("Connection manager task finished.");
             let _ = ctx.state_tx.send_replace(ConnectionState::Disconnected); // Use replace for final state on exit
-        }); // end tokio::spawn
+        }; // end manager
+
+        // A panic would otherwise end the task silently, leaving the last state
+        // (often Connected) in place
+        let handle = rt::spawn(async move {
+            if let Err(panic) = AssertUnwindSafe(manager).catch_unwind().await {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(ToString::to_string)
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                error!(panic = %message, "Connection manager task panicked");
+                let _ = state_tx.send_replace(ConnectionState::Failed(Failure {
+                    message: format!("Connection manager panicked: {}", message),
+                    kind: ErrorKind::Internal,
+                    fatal: false,
+                }));
+            }
+        });
 
         // Store the JoinHandle
         {
@@ -625,6 +1487,62 @@ impl LoungeClient {
         }
     }
 
+    /// With `resume_on_reconnect`, note what was playing as the session was lost.
+    fn remember_resume_point(
+        ctx: &ConnectionManagerContext,
+        resume_point: &mut Option<ResumePoint>,
+    ) {
+        if ctx.config.resume_on_reconnect {
+            *resume_point = ctx
+                .pipeline
+                .player_state
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .resume_point();
+        }
+    }
+
+    /// Report where playback was after a lost session has been re-established,
+    /// and restart it there if configured to.
+    fn restore_resume_point(ctx: &ConnectionManagerContext, point: ResumePoint) {
+        info!(video_id = %point.video_id, position = point.position, "Session resumed");
+        send_event(
+            &ctx.event_sender,
+            &LoungeEvent::SessionResumed {
+                video_id: point.video_id.clone(),
+                position: point.position,
+            },
+        );
+        if ctx.config.resume_playback_on_reconnect {
+            rt::spawn(automation::resume_playback(ctx.commands.clone(), point));
+        }
+    }
+
+    /// Sleep out the current backoff, then double it for next time. A network
+    /// change cuts the sleep short and resets the backoff, so the retry happens
+    /// immediately and a failure after that backs off from the minimum again.
+    async fn wait_before_retry(ctx: &ConnectionManagerContext, backoff: &mut Duration) {
+        let delay_duration = calculate_backoff_delay(*backoff);
+        let _ = ctx.state_tx.send(ConnectionState::WaitingToReconnect {
+            backoff: delay_duration,
+            retry_at: utils::now() + delay_duration,
+        });
+        debug!("Backing off for {:?}", delay_duration);
+        if let Some(metrics) = Self::metrics(&ctx.shared_state).await {
+            metrics.reconnect_scheduled(delay_duration);
+        }
+        tokio::select! {
+            _ = sleep(delay_duration) => {
+                *backoff = (*backoff * 2).min(ctx.config.max_backoff);
+            }
+            _ = ctx.network_changed.notified() => {
+                info!("Network changed, retrying immediately");
+                *backoff = ctx.config.min_backoff;
+            }
+            _ = ctx.shutdown_notify.notified() => {} // Manager loop sees the stop signal next
+        }
+    }
+
     /// Helper for the manager task to attempt a bind request.
     /// Updates the shared SessionState on success.
     async fn attempt_bind(
@@ -645,13 +1563,13 @@ impl LoungeClient {
             ("capabilities", "que,dsdtr,atp"),
             ("method", "setPlaylist"),
             ("magnaKey", "cloudPairedDevice"),
-            ("ui", "false"),
+            ("ui", if ctx.config.bind_ui { "true" } else { "false" }),
             ("deviceContext", "user_agent=dunno"),
             ("window_width_points", ""),
             ("window_height_points", ""),
             ("os_name", "android"),
             ("ms", ""),
-            ("theme", "cl"),
+            ("theme", &ctx.config.bind_theme),
             ("loungeIdToken", &current_lounge_token),
         ];
         // Use map_err to convert UrlEncodingFailed into LoungeError
@@ -682,7 +1600,7 @@ impl LoungeClient {
                 return Err(LoungeError::ConnectionClosed);
             }
             res = ctx.client
-                    .post("https://www.youtube.com/api/lounge/bc/bind")
+                    .post(ctx.config.endpoint("bc/bind"))
                     .query(&params)
                     .header("Content-Type", "application/x-www-form-urlencoded")
                     .body(form_data)
@@ -692,6 +1610,7 @@ impl LoungeClient {
 
         // Handle the result of the send operation
         let response = response_result.map_err(LoungeError::RequestFailed)?;
+        record_error_response(&ctx.commands.last_error_response, &response);
 
         match response.status().as_u16() {
             200 => {
@@ -722,6 +1641,12 @@ impl LoungeClient {
                         session_write.command_offset.store(0, Ordering::SeqCst);
                         debug!("Stored new SID/GSessionID, reset offset in shared SessionState.");
                     }
+                    // The new session numbers its events from scratch
+                    ctx.pipeline
+                        .aid_tracker
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .reset();
                     send_event(&ctx.event_sender, &LoungeEvent::SessionEstablished);
                     // let _ = state_tx.send(ConnectionState::Connected); // Let manager loop set state
                     Ok(ConnectionStatus::Success)
@@ -730,9 +1655,7 @@ impl LoungeClient {
                         "Bind response successful (200), but failed to extract SID/GSessionID. Body: {:?}",
                         String::from_utf8_lossy(&body)
                     );
-                    Err(LoungeError::InvalidResponse(
-                        "Failed to extract session IDs from bind response".to_string(),
-                    ))
+                    Err(LoungeError::session_id_extraction_failed(&body))
                 }
             }
             401 => {
@@ -768,15 +1691,97 @@ impl LoungeClient {
         }
     }
 
-    /// Helper for the manager task to perform one long-polling event request.
+    /// Helper for the manager task to perform one long-polling event request,
+    /// reporting the cycle boundaries to tracing and the poll cycle callback.
     async fn poll_events(
+        ctx: &ConnectionManagerContext,
+        sid: &str,
+        gsessionid: &str,
+    ) -> Result<ConnectionStatus, LoungeError> {
+        let cycle = ctx.poll_cycles.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!(cycle, "Poll cycle started");
+        Self::report_poll_cycle(ctx, &PollCycleEvent::Started { cycle }).await;
+
+        let started = Instant::now();
+        let mut stats = PollCycleStats {
+            cycle,
+            ..PollCycleStats::default()
+        };
+        let result = Self::poll_events_cycle(ctx, sid, gsessionid, &mut stats).await;
+        stats.elapsed = started.elapsed();
+        // Successful endings are classified where they happen; the rest follow from the result
+        let end_reason = stats.end_reason.unwrap_or(match &result {
+            Ok(ConnectionStatus::Success) => PollEndReason::ServerClosed,
+            Ok(ConnectionStatus::SessionInvalidated) => PollEndReason::SessionInvalidated,
+            Ok(ConnectionStatus::TokenExpired) => PollEndReason::TokenExpired,
+            Err(LoungeError::ConnectionClosed) => PollEndReason::Shutdown,
+            Err(_) => PollEndReason::Error,
+        });
+        stats.end_reason = Some(end_reason);
+        *ctx.last_poll_end_reason
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(end_reason);
+
+        info!(
+            cycle,
+            messages = stats.messages_decoded,
+            bytes = stats.bytes_received,
+            elapsed_ms = stats.elapsed.as_millis() as u64,
+            end_reason = ?end_reason,
+            "Poll cycle finished"
+        );
+        Self::report_poll_cycle(ctx, &PollCycleEvent::Finished(stats)).await;
+        result
+    }
+
+    /// Switch the session to a gsessionid the server announced mid-stream.
+    async fn apply_gsessionid_rotation(ctx: &ConnectionManagerContext) {
+        let rotated = ctx
+            .pipeline
+            .rotated_gsessionid
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let Some(gsessionid) = rotated else {
+            return;
+        };
+        let mut session = ctx.session_state_rwlock.write().await;
+        if session.gsessionid.as_deref() != Some(gsessionid.as_str()) {
+            info!(
+                old = ?session.gsessionid,
+                new = %gsessionid,
+                "Server rotated gsessionid, updating session"
+            );
+            session.gsessionid = Some(gsessionid);
+        }
+    }
+
+    async fn report_poll_cycle(ctx: &ConnectionManagerContext, event: &PollCycleEvent) {
+        let state_guard = ctx.shared_state.read().await;
+        if let (PollCycleEvent::Finished(stats), Some(metrics)) = (event, &state_guard.metrics) {
+            metrics.poll_cycle(stats);
+        }
+        if let Some(ref callback) = state_guard.poll_cycle_callback {
+            callback(event);
+        }
+    }
+
+    async fn metrics(shared_state: &RwLock<InnerState>) -> Option<Arc<dyn Metrics>> {
+        shared_state.read().await.metrics.clone()
+    }
+
+    async fn poll_events_cycle(
         ctx: &ConnectionManagerContext, // Use context struct
         sid: &str,                      // Pass specific session IDs
         gsessionid: &str,
+        stats: &mut PollCycleStats,
     ) -> Result<ConnectionStatus, LoungeError> {
-        let current_lounge_token = {
+        let (current_lounge_token, metrics) = {
             let state_guard = ctx.shared_state.read().await;
-            state_guard.lounge_token.clone()
+            (
+                state_guard.lounge_token.clone(),
+                state_guard.metrics.clone(),
+            )
         };
         let current_aid_val = ctx.aid_atomic.load(Ordering::SeqCst);
         let aid_string = current_aid_val.to_string();
@@ -807,9 +1812,9 @@ impl LoungeClient {
             }
             // Match the result of the send future directly
             res = ctx.client
-                .get("https://www.youtube.com/api/lounge/bc/bind")
+                .get(ctx.config.endpoint("bc/bind"))
                 .query(&params)
-                .timeout(SETTINGS.long_poll_timeout) // Use long poll timeout
+                .timeout(ctx.config.long_poll_timeout) // Use long poll timeout
                 .send() => res, // This assigns the Result<Response, reqwest::Error>
         };
 
@@ -830,6 +1835,8 @@ impl LoungeClient {
             }
         };
 
+        record_error_response(&ctx.commands.last_error_response, &response);
+
         // --- Check Status Codes ---
         match response.status().as_u16() {
             200 => {
@@ -892,8 +1899,8 @@ impl LoungeClient {
         // (The rest of the function with the select! around stream.next() remains the same)
         let mut stream = response.bytes_stream();
         let mut codec = LoungeCodec::new();
-        let mut buffer = BytesMut::with_capacity(SETTINGS.streaming_buffer_capacity);
-        let mut _received_data = false; // Keep track if we got any data in this poll cycle
+        let mut buffer = BytesMut::with_capacity(ctx.config.stream_buffer_capacity);
+        stats.buffer_capacity = buffer.capacity();
 
         loop {
             // Use select! to race stream reading against shutdown notification
@@ -907,7 +1914,7 @@ impl LoungeClient {
                 }
 
                 // Wait for the next chunk OR the inactivity timeout
-                maybe_chunk_result = timeout(SETTINGS.inactivity_timeout, stream.next()) => {
+                maybe_chunk_result = timeout(ctx.config.inactivity_timeout, stream.next()) => {
                         match maybe_chunk_result {
                         // --- Case 1: Data received within timeout ---
                         Ok(Some(Ok(chunk))) => {
@@ -915,20 +1922,27 @@ impl LoungeClient {
                                 trace!("Received empty chunk in event stream.");
                                 continue; // Ignore empty chunks, continue loop
                             }
-                            _received_data = true;
+                            stats.bytes_received += chunk.len();
+                            if let Some(meter) = ctx.throughput.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+                                meter.record(chunk.len());
+                            }
                             trace!("Received chunk of size {}", chunk.len());
                             buffer.extend_from_slice(&chunk);
+                            stats.buffer_capacity = stats.buffer_capacity.max(buffer.capacity());
                             loop {
                                 match codec.decode(&mut buffer) {
                                     Ok(Some(message)) => {
+                                        stats.messages_decoded += 1;
+                                        ctx.message_sizes
+                                            .lock()
+                                            .unwrap_or_else(PoisonError::into_inner)
+                                            .record(message.len());
+                                        if let Some(metrics) = &metrics {
+                                            metrics.message_decoded(message.len());
+                                        }
                                         trace!("Decoded message of size {}", message.len());
-                                        events::process_event_chunk(
-                                            &message, // Use ctx fields
-                                            &ctx.event_sender,
-                                            &ctx.latest_now_playing,
-                                            &ctx.aid_atomic,
-                                        )
-                                        .await;
+                                        events::process_event_chunk(&message, &ctx.pipeline);
+                                        Self::apply_gsessionid_rotation(ctx).await;
                                     }
                                     Ok(None) => {
                                         // Need more data in buffer to decode a full message
@@ -937,6 +1951,9 @@ impl LoungeClient {
                                     }
                                     Err(e) => {
                                         error!(error = %e, "Error decoding event message stream chunk");
+                                        if let Some(metrics) = &metrics {
+                                            metrics.decode_error();
+                                        }
                                         return Err(LoungeError::IoError(e)); // Fatal decoding error for this poll
                                     }
                                 }
@@ -962,6 +1979,7 @@ impl LoungeClient {
                                     "Timeout detected during stream read (reqwest internal or Body->TimedOut). Treating as Success and re-polling."
                                 );
                                 // Treat this specific timeout as a successful poll cycle end, prompting an immediate reconnect.
+                                stats.end_reason = Some(PollEndReason::StreamTimeout);
                                 return Ok(ConnectionStatus::Success);
                             } else {
                                     // It's a different kind of network or decoding error. Log details.
@@ -978,15 +1996,21 @@ impl LoungeClient {
                         // --- Case 3: Stream ended gracefully within timeout ---
                         Ok(None) => {
                             debug!("Event stream ended gracefully by server (EOF). Re-polling.");
+                            stats.end_reason = Some(PollEndReason::ServerClosed);
                             return Ok(ConnectionStatus::Success); // End of this poll cycle
                         }
 
                         // --- Case 4: Inactivity Timeout expired ---
+                        Err(_) if !ctx.config.repoll_on_inactivity => {
+                            trace!("No data for {:?}, still waiting on the poll.", ctx.config.inactivity_timeout);
+                            continue;
+                        }
                         Err(_) => {
                             debug!(
-                                "Inactivity detected (no data for >{}s), closing poll cycle. Re-polling.",
-                                SETTINGS.inactivity_timeout.as_secs()
+                                "Inactivity detected (no data for >{:?}), closing poll cycle. Re-polling.",
+                                ctx.config.inactivity_timeout
                             );
+                            stats.end_reason = Some(PollEndReason::InactivityTimeout);
                                 // Treat timeout like a graceful close, immediately try polling again
                                 return Ok(ConnectionStatus::Success);
                         }
@@ -998,224 +2022,201 @@ impl LoungeClient {
     }
 
     /// Helper function to attempt token refresh and update shared state.
+    async fn refresh_token(&self) -> Result<(), LoungeError> {
+        Self::try_refresh_token(
+            &self.config,
+            &self.screen_id,
+            &self.device_id,
+            &self.device_name,
+            &self.shared_state,
+            &self.last_token_refresh,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Refresh the lounge token, coalescing concurrent and rapid repeat requests:
+    /// a caller finding that a refresh finished within `min_token_refresh_interval`
+    /// (including one it queued behind) reuses that token instead of refreshing again,
+    /// and gets [`TokenRefresh::Reused`]. While the server throttles refreshes,
+    /// attempts fail with `RateLimited` without contacting it.
     async fn try_refresh_token(
+        config: &LoungeConfig,
         screen_id: &str,
+        device_id: &str,
+        device_name: &str,
         shared_state: &Arc<RwLock<InnerState>>,
-    ) -> Result<(), LoungeError> {
-        match LoungeClient::refresh_lounge_token(screen_id).await {
+        refresh_state: &tokio::sync::Mutex<TokenRefreshState>,
+    ) -> Result<TokenRefresh, LoungeError> {
+        let requested_at = Instant::now();
+        // Held for the whole refresh so concurrent callers queue behind it
+        let mut refresh_state = refresh_state.lock().await;
+        if let Some(at) = refresh_state.last_refresh {
+            if at >= requested_at
+                || requested_at.duration_since(at) < config.min_token_refresh_interval
+            {
+                debug!("Token was refreshed moments ago, reusing it.");
+                return Ok(TokenRefresh::Reused);
+            }
+        }
+        if let Some(until) = refresh_state.throttled_until {
+            if until > requested_at {
+                debug!("Token refresh still throttled by the server, not retrying yet.");
+                return Err(LoungeError::RateLimited {
+                    retry_after: Some(until - requested_at),
+                });
+            }
+        }
+
+        match LoungeClient::fetch_lounge_token(config, screen_id).await {
             Ok(screen) => {
                 info!("Successfully refreshed token for screen_id: {}", screen_id);
                 let mut state = shared_state.write().await;
                 let old_token_preview = state.lounge_token.chars().take(8).collect::<String>();
                 state.lounge_token = screen.lounge_token.clone();
                 debug!(old = %old_token_preview, "Stored new lounge token in shared state.");
+                if let Some(store) = &state.token_store {
+                    Self::save_refreshed_token(store.as_ref(), &screen, device_id, device_name);
+                }
                 if let Some(ref callback) = state.token_refresh_callback {
                     debug!("Calling token refresh callback.");
                     callback(screen_id, &screen.lounge_token);
                 } else {
                     debug!("No token refresh callback set.");
                 }
-                Ok(())
-            }
-            Err(refresh_err) => {
-                error!(error = %refresh_err, "Failed to refresh token");
-                Err(LoungeError::TokenRefreshFailed(Box::new(refresh_err)))
+                refresh_state.last_refresh = Some(Instant::now());
+                Ok(TokenRefresh::Refreshed)
             }
-        }
-    }
-
-    /// Send a playback command to the screen
-    pub async fn send_command(&self, command: PlaybackCommand) -> Result<(), LoungeError> {
-        // Check connection state first
-        let current_state = self.current_state();
-        if current_state != ConnectionState::Connected {
-            warn!(state=?current_state, "Attempted to send command while not connected.");
-            return Err(LoungeError::SessionLost);
-        }
-
-        let sid: String;
-        let gsessionid: String;
-        let rid_val: u32;
-        let ofs_val: u32;
-        let rid_string: String;
-        let ofs_string: String;
-
-        let token: String;
-
-        {
-            let session = self.session_state.read().await;
-            // These unwraps are now safe due to the ConnectionState::Connected check above
-            sid = session.sid.clone().ok_or(LoungeError::SessionLost)?;
-            gsessionid = session.gsessionid.clone().ok_or(LoungeError::SessionLost)?;
-
-            rid_val = session.rid.fetch_add(1, Ordering::SeqCst);
-            ofs_val = session.command_offset.fetch_add(1, Ordering::SeqCst);
-            rid_string = rid_val.to_string();
-            ofs_string = ofs_val.to_string();
-        }; // Release read lock on session_state
-
-        {
-            let state_guard = self.shared_state.read().await;
-            token = state_guard.lounge_token.clone();
-        }; // Release read lock on shared_state (token)
-
-        let current_aid = self.aid_atomic.load(Ordering::SeqCst);
-        let aid_string: String = current_aid.to_string();
-
-        let command_name = command.name();
-        debug!(
-            "Sending command: {} (RID: {}, offset: {})",
-            command_name, rid_val, ofs_val
-        );
-
-        let mut form_fields: Vec<(&str, String)> = Vec::with_capacity(16);
-        form_fields.push(("count", "1".to_string()));
-        form_fields.push(("ofs", ofs_string));
-        form_fields.push(("req0__sc", command_name.to_string()));
-
-        match &command {
-            PlaybackCommand::SetPlaylist {
-                video_id,
-                list_id,
-                current_index,
-                current_time,
-                audio_only,
-                params,
-                player_params,
-            } => {
-                form_fields.push(("req0_videoId", video_id.clone()));
-                if let Some(idx) = current_index {
-                    form_fields.push(("req0_currentIndex", idx.to_string()));
-                }
-                if let Some(list) = list_id {
-                    form_fields.push(("req0_listId", list.clone()));
-                }
-                if let Some(time) = current_time {
-                    form_fields.push(("req0_currentTime", time.to_string()));
-                }
-                if let Some(audio) = audio_only {
-                    form_fields.push(("req0_audioOnly", audio.to_string()));
-                }
-                if let Some(p) = params {
-                    form_fields.push(("req0_params", p.clone()));
+            Err(refresh_err) => match refresh_err.without_context() {
+                LoungeError::RateLimited { retry_after } => {
+                    refresh_state.throttled_until = retry_after.map(|delay| Instant::now() + delay);
+                    Err(refresh_err)
                 }
-                if let Some(pp) = player_params {
-                    form_fields.push(("req0_playerParams", pp.clone()));
+                LoungeError::ScreenUnpaired => {
+                    error!("Failed to refresh token: screen is no longer paired");
+                    if let Some(store) = &shared_state.read().await.token_store {
+                        if let Err(e) = store.delete(screen_id) {
+                            warn!(error = %e, "Failed to remove unpaired screen from token store");
+                        }
+                    }
+                    Err(refresh_err)
                 }
-                form_fields.push((
-                    "req0_prioritizeMobileSenderPlaybackStateOnConnection",
-                    "true".to_string(),
-                ));
-            }
-            PlaybackCommand::AddVideo {
-                video_id,
-                video_sources,
-            } => {
-                form_fields.push(("req0_videoId", video_id.clone()));
-                if let Some(sources) = video_sources {
-                    form_fields.push(("req0_videoSources", sources.clone()));
+                _ => {
+                    error!(error = %refresh_err, "Failed to refresh token");
+                    Err(LoungeError::TokenRefreshFailed(Box::new(refresh_err)))
                 }
-            }
-            PlaybackCommand::SeekTo { new_time } => {
-                form_fields.push(("req0_newTime", new_time.to_string()));
-            }
-            PlaybackCommand::SetVolume { volume } => {
-                form_fields.push(("req0_volume", volume.to_string()));
-            }
-            PlaybackCommand::SetAutoplayMode { autoplay_mode } => {
-                form_fields.push(("req0_autoplayMode", autoplay_mode.clone()));
-            }
-            _ => {}
+            },
         }
+    }
 
-        let params = [
-            ("SID", sid.as_str()),
-            ("gsessionid", gsessionid.as_str()),
-            ("RID", rid_string.as_str()),
-            ("VER", "8"),
-            ("v", "2"),
-            ("TYPE", "bind"),
-            ("t", "1"),
-            ("AID", aid_string.as_str()),
-            ("CI", "0"),
-            ("name", self.device_name.as_str()),
-            ("id", self.device_id.as_str()),
-            ("device", "REMOTE_CONTROL"),
-            ("loungeIdToken", token.as_str()),
-        ];
+    /// Persist a refreshed token, keeping a stored screen name the refresh
+    /// response lacks.
+    fn save_refreshed_token(
+        store: &dyn TokenStore,
+        screen: &Screen,
+        device_id: &str,
+        device_name: &str,
+    ) {
+        let mut stored = StoredScreen::new(screen, device_id);
+        stored.device_name = Some(device_name.to_string());
+        if stored.name.is_none() {
+            stored.name = store
+                .load(&screen.screen_id)
+                .ok()
+                .flatten()
+                .and_then(|previous| previous.name);
+        }
+        match store.save(&stored) {
+            Ok(()) => debug!("Saved refreshed token to token store."),
+            Err(e) => warn!(error = %e, "Failed to save refreshed token to token store"),
+        }
+    }
 
-        debug!(?params, ?form_fields, "Sending command request");
+    /// Send a playback command to the screen
+    pub async fn send_command(&self, command: PlaybackCommand) -> Result<(), LoungeError> {
+        self.command_context().send(command).await
+    }
 
-        let response = self
-            .client
-            .post("https://www.youtube.com/api/lounge/bc/bind")
-            .query(&params)
-            .form(&form_fields)
-            .send()
-            .await
-            .map_err(LoungeError::RequestFailed)?; // Map send error
+    /// Send a command, then wait up to `wait` for the TV to report its effect,
+    /// as judged by [`PlaybackCommand::is_acknowledged_by`]. Only events
+    /// arriving after the command was sent count. Errors are those of
+    /// [`Self::send_command`]; a command that isn't acknowledged in time is
+    /// still `Ok`, with [`CommandOutcome::TimedOut`].
+    pub async fn send_command_ack(
+        &self,
+        command: PlaybackCommand,
+        wait: Duration,
+    ) -> Result<CommandOutcome, LoungeError> {
+        if !command.is_confirmable() {
+            self.send_command(command).await?;
+            return Ok(CommandOutcome::Unconfirmable);
+        }
+        // Subscribe first so a quick answer isn't missed
+        let mut rx = self.event_sender.subscribe();
+        self.send_command(command.clone()).await?;
 
-        match response.status().as_u16() {
-            200 => {
-                debug!("Command sent successfully: {}", command_name);
-                Ok(())
-            }
-            400 => {
-                warn!(
-                    "Session likely expired (HTTP 400) sending command: {}",
-                    command_name
-                );
-                Err(LoungeError::SessionInvalidatedByServer(400))
-            }
-            401 => {
-                warn!("Token expired (HTTP 401) sending command: {}", command_name);
-                Err(LoungeError::TokenExpired)
-            }
-            404 => {
-                warn!(
-                    "Session not found (HTTP 404) sending command: {}",
-                    command_name
-                );
-                Err(LoungeError::SessionInvalidatedByServer(404))
-            }
-            410 => {
-                warn!(
-                    "Connection closed (HTTP 410) sending command: {}",
-                    command_name
-                );
-                Err(LoungeError::ConnectionClosed) // Or SessionInvalidated? ConnectionClosed seems slightly better.
+        let acknowledgement = timeout(wait, async {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if command.is_acknowledged_by(&event) == Some(true) => {
+                        return Some(event)
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        debug!("Acknowledgement wait lagged, skipped {} events", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
             }
-            status if !response.status().is_success() => {
-                let body_text = response.text().await.map_err(LoungeError::RequestFailed)?;
-                let error_msg = format!(
-                    "Command '{}' failed with status {} and response body:\n{}",
-                    command_name, status, body_text
-                );
-                error!("{}", error_msg);
-                Err(LoungeError::InvalidResponse(error_msg))
+        })
+        .await;
+        Ok(match acknowledgement {
+            Ok(Some(event)) => {
+                debug!(command = command.name(), "Command acknowledged");
+                CommandOutcome::Acknowledged(Box::new(event))
             }
             _ => {
-                warn!(status=%response.status(), "Unexpected successful status code sending command.");
-                Err(LoungeError::InvalidResponse(format!(
-                    "Unexpected status {} sending command",
-                    response.status()
-                )))
+                debug!(command = command.name(), "Command not acknowledged in time");
+                CommandOutcome::TimedOut
             }
+        })
+    }
+
+    fn command_context(&self) -> CommandContext {
+        CommandContext {
+            client: self.client.clone(),
+            config: self.config.clone(),
+            screen_id: self.screen_id.clone(),
+            device_name: self.device_name.clone(),
+            device_id: self.device_id.clone(),
+            session_state: self.session_state.clone(),
+            shared_state: self.shared_state.clone(),
+            aid_atomic: self.aid_atomic.clone(),
+            state_rx: self.connection_state_rx.clone(),
+            last_error_response: self.last_error_response.clone(),
+            commands_in_flight: self.commands_in_flight.clone(),
+            throttle: self.command_throttle.clone(),
         }
     }
 
+    /// Send several commands in a single request, e.g. to queue a batch of
+    /// videos without one round-trip each.
+    pub async fn send_commands(&self, commands: Vec<PlaybackCommand>) -> Result<(), LoungeError> {
+        self.command_context().send_batch(&commands).await
+    }
+
     pub async fn send_command_with_refresh(
         &self,
         command: PlaybackCommand,
     ) -> Result<(), LoungeError> {
         match self.send_command(command.clone()).await {
             Ok(()) => Ok(()),
-            Err(LoungeError::TokenExpired) => {
+            Err(e) if matches!(e.without_context(), LoungeError::TokenExpired) => {
                 info!(
                     "Refreshing expired token (send_command_with_refresh for '{}')",
                     command.name()
                 );
-                Self::try_refresh_token(&self.screen_id, &self.shared_state).await?;
+                self.refresh_token().await?;
                 debug!(
                     "Retrying send_command for '{}' after refresh",
                     command.name()
@@ -1228,8 +2229,12 @@ impl LoungeClient {
                     Err(LoungeError::SessionLost) // Session might have been lost during refresh
                 }
             }
-            Err(e @ LoungeError::SessionInvalidatedByServer(_))
-            | Err(e @ LoungeError::SessionLost) => {
+            Err(e)
+                if matches!(
+                    e.without_context(),
+                    LoungeError::SessionInvalidatedByServer(_) | LoungeError::SessionLost
+                ) =>
+            {
                 warn!("Command failed because session is invalid/lost: {}", e);
                 Err(e) // Don't retry if session is known dead
             }
@@ -1264,8 +2269,70 @@ impl LoungeClient {
         Ok(())
     }
 
+    /// Stop polling and reconnecting without terminating the session, so no
+    /// traffic flows until [`Self::resume_polling`], e.g. while the app is
+    /// minimized. The screen may expire the session in the meantime, in which
+    /// case resuming binds a new one.
+    pub async fn go_idle(&self) -> Result<(), LoungeError> {
+        info!("[{}] Going idle", self.screen_id);
+        self.stop_and_await_manager().await?;
+        let _ = self.connection_state_tx.send_replace(ConnectionState::Idle);
+        Ok(())
+    }
+
+    /// Pick up again after [`Self::go_idle`], continuing the kept session, or
+    /// binding a new one if there is none.
+    pub async fn resume_polling(&self) -> Result<(), LoungeError> {
+        self.connect_with_snapshot(self.session_snapshot().await)
+            .await
+    }
+
+    /// Disconnect, giving the connection manager and the terminate request at
+    /// most `timeout` between them. If they overrun, the manager task is
+    /// aborted and `LoungeError::ShutdownTimedOut` returned; the screen may
+    /// then keep the session around until it expires.
+    pub async fn shutdown(self, timeout: Duration) -> Result<(), LoungeError> {
+        // Taken up front, as a disconnect cut short drops the handle it was awaiting
+        let manager = self
+            .management_task
+            .read()
+            .await
+            .as_ref()
+            .map(rt::JoinHandle::abort_handle);
+
+        match rt::timeout(timeout, self.disconnect()).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    "[{}] Shutdown did not finish within {:?}, aborting connection manager",
+                    self.screen_id, timeout
+                );
+                if let Some(manager) = manager {
+                    manager.abort();
+                }
+                let _ = self
+                    .connection_state_tx
+                    .send_replace(ConnectionState::Disconnected);
+                Err(LoungeError::ShutdownTimedOut(timeout))
+            }
+        }
+    }
+
     pub async fn disconnect(&self) -> Result<(), LoungeError> {
-        info!("Disconnecting from screen: {}", self.screen_id);
+        self.disconnect_with_reason(DisconnectReason::default())
+            .await
+    }
+
+    /// Disconnect, reporting `reason` to the TV in the terminate request.
+    pub async fn disconnect_with_reason(
+        &self,
+        reason: DisconnectReason,
+    ) -> Result<(), LoungeError> {
+        info!(
+            "Disconnecting from screen: {} ({})",
+            self.screen_id,
+            reason.as_str()
+        );
 
         // 1. Signal the connection manager task to stop & await it
         self.stop_and_await_manager().await?; // Await completion before proceeding
@@ -1275,75 +2342,10 @@ impl LoungeClient {
             .connection_state_tx
             .send_replace(ConnectionState::Stopping); // Ensure state reflects intention
 
-        // 2. Read current session details FOR the terminate request
-        let sid: Option<String>;
-        let gsessionid: Option<String>;
-        let rid_val: u32;
-        let rid_string: String;
-        // Token is needed for the terminate request parameters
-        let token: String;
-
-        {
-            let session = self.session_state.read().await;
-            sid = session.sid.clone();
-            gsessionid = session.gsessionid.clone();
-            rid_val = session.rid.fetch_add(1, Ordering::SeqCst);
-            rid_string = rid_val.to_string();
-        }
-
-        {
-            let state_guard = self.shared_state.read().await;
-            token = state_guard.lounge_token.clone();
-        }
-
-        // 3. Send terminate request (best effort) if session existed
-        if let (Some(sid_val), Some(gsessionid_val)) = (sid, gsessionid) {
-            // Re-checked parameters based on earlier fix for 411 error
-            let params = [
-                ("RID", rid_string.as_str()), // Use incremented RID from session state
-                ("VER", "8"),
-                ("CVER", "1"),
-                ("gsessionid", gsessionid_val.as_str()), // Session ID from session state
-                ("SID", sid_val.as_str()),               // Other Session ID from session state
-                ("auth_failure_option", "send_error"),
-                ("name", self.device_name.as_str()),
-                ("id", self.device_id.as_str()),
-                ("device", "REMOTE_CONTROL"),
-                ("loungeIdToken", token.as_str()), // Added token back, potentially needed
-            ];
-
-            let body_data = "ui=&TYPE=terminate&clientDisconnectReason=MDX_SESSION_DISCONNECT_REASON_DISCONNECTED_BY_USER";
-
-            debug!(?params, "Sending disconnect (terminate) request");
-            let res = self
-                .client
-                .post("https://www.youtube.com/api/lounge/bc/bind")
-                .query(&params)
-                .header(
-                    reqwest::header::CONTENT_TYPE,
-                    "application/x-www-form-urlencoded",
-                )
-                .body(body_data)
-                .timeout(Duration::from_secs(5))
-                .send()
-                .await;
-
-            match res {
-                Ok(response) if response.status().is_success() => {
-                    debug!("Terminate request successful.");
-                }
-                Ok(response) => {
-                    warn!(status=%response.status(), "Terminate request failed (status)");
-                }
-                Err(e) => {
-                    warn!("Error sending terminate request (ignored): {}", e);
-                }
-            }
-        } else {
-            warn!("No valid session details found when disconnecting, cannot send explicit terminate request.");
-        }
+        // 2. Send terminate request (best effort) if a session existed
+        self.command_context().terminate(reason).await?;
 
-        // 4. Clear local session state AFTER attempting terminate and awaiting manager
+        // 3. Clear local session state AFTER attempting terminate and awaiting manager
         {
             let mut session_write = self.session_state.write().await;
             if session_write.sid.is_some() || session_write.gsessionid.is_some() {
@@ -1352,7 +2354,7 @@ impl LoungeClient {
             }
         }
 
-        // 5. Send disconnect event and set final state
+        // 4. Send disconnect event and set final state
         send_event(&self.event_sender, &LoungeEvent::ScreenDisconnected);
         let _ = self
             .connection_state_tx
@@ -1376,8 +2378,8 @@ impl LoungeClient {
             ("device", "REMOTE_CONTROL"),
             ("capabilities", "que,dsdtr,atp"),
             ("magnaKey", "cloudPairedDevice"),
-            ("ui", "false"),
-            ("theme", "cl"),
+            ("ui", if self.config.bind_ui { "true" } else { "false" }),
+            ("theme", &self.config.bind_theme),
             ("loungeIdToken", &token),
         ];
 
@@ -1410,6 +2412,27 @@ impl LoungeClient {
             .await
     }
 
+    /// Skip `n` tracks ahead. Each `Next` is sent only after the previous request
+    /// completed, so the TV receives them in order; stops at the first error.
+    /// This doesn't wait for the TV to report each skip; see
+    /// [`Self::send_command_ack`] for that.
+    pub async fn skip_forward(&self, n: u32) -> Result<(), LoungeError> {
+        for _ in 0..n {
+            self.send_command_with_refresh(PlaybackCommand::Next)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Skip `n` tracks back, with the same ordering guarantee as [`Self::skip_forward`].
+    pub async fn skip_backward(&self, n: u32) -> Result<(), LoungeError> {
+        for _ in 0..n {
+            self.send_command_with_refresh(PlaybackCommand::Previous)
+                .await?;
+        }
+        Ok(())
+    }
+
     pub async fn skip_ad(&self) -> Result<(), LoungeError> {
         self.send_command_with_refresh(PlaybackCommand::SkipAd)
             .await
@@ -1424,28 +2447,244 @@ impl LoungeClient {
             .await
     }
 
-    pub async fn seek_to(&self, new_time: f64) -> Result<(), LoungeError> {
-        self.send_command_with_refresh(PlaybackCommand::SeekTo { new_time })
+    /// Ask for the current video's subtitle tracks; they arrive as a
+    /// `LoungeEvent::SubtitlesTrackList`.
+    pub async fn request_subtitles_track_list(&self) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::GetSubtitlesTrackList)
             .await
     }
 
-    pub async fn set_volume(&self, volume: i32) -> Result<(), LoungeError> {
-        self.send_command_with_refresh(PlaybackCommand::SetVolume { volume })
-            .await
+    /// Show subtitles in `language_code` for `video_id`; an empty code turns them off.
+    pub async fn set_subtitles_track(
+        &self,
+        video_id: String,
+        language_code: String,
+    ) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SetSubtitlesTrack {
+            video_id,
+            language_code,
+        })
+        .await
     }
 
-    pub async fn set_autoplay_mode(&self, autoplay_mode: String) -> Result<(), LoungeError> {
-        self.send_command_with_refresh(PlaybackCommand::SetAutoplayMode { autoplay_mode })
-            .await
+    pub async fn set_audio_track(
+        &self,
+        video_id: String,
+        audio_track_id: String,
+    ) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SetAudioTrack {
+            video_id,
+            audio_track_id,
+        })
+        .await
     }
 
-    pub async fn play_video(&self, video_id: String) -> Result<(), LoungeError> {
-        self.send_command_with_refresh(PlaybackCommand::set_playlist(video_id))
-            .await
+    pub async fn set_video_quality(
+        &self,
+        video_id: String,
+        quality_level: i32,
+    ) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SetVideoQuality {
+            video_id,
+            quality_level,
+        })
+        .await
     }
 
-    pub async fn add_video_to_queue(&self, video_id: String) -> Result<(), LoungeError> {
-        self.send_command_with_refresh(PlaybackCommand::add_video(video_id))
+    /// Press a key on the remote's directional pad, e.g. to navigate the TV's UI.
+    pub async fn dpad(&self, key: DpadKey) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::Dpad { key })
+            .await
+    }
+
+    pub async fn voice_command(&self, status: String) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::VoiceCommand { status })
+            .await
+    }
+
+    pub async fn stop_video(&self) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::StopVideo)
+            .await
+    }
+
+    /// Jump to the video at `index` (zero-based) of the current playlist.
+    pub async fn set_playlist_index(&self, index: i32) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SetPlaylistIndex { index })
+            .await
+    }
+
+    pub async fn seek_to(&self, new_time: f64) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SeekTo { new_time })
+            .await
+    }
+
+    pub async fn set_volume(&self, volume: i32) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SetVolume { volume })
+            .await
+    }
+
+    /// Raise the volume by `step` from the last known level, up to 100.
+    /// Returns the level set. See [`Self::current_volume`].
+    pub async fn volume_up(&self, step: i32) -> Result<i32, LoungeError> {
+        self.step_volume(step).await
+    }
+
+    /// Lower the volume by `step` from the last known level, down to 0.
+    /// Returns the level set.
+    pub async fn volume_down(&self, step: i32) -> Result<i32, LoungeError> {
+        self.step_volume(step.saturating_neg()).await
+    }
+
+    async fn step_volume(&self, delta: i32) -> Result<i32, LoungeError> {
+        let current = match self.current_volume() {
+            Some(volume) => volume,
+            None => self.fetch_volume().await?,
+        };
+        let level = current.level.saturating_add(delta).clamp(0, 100);
+        self.set_volume(level).await?;
+        self.player_state
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record_volume_level(level);
+        Ok(level)
+    }
+
+    /// Ask the TV for its volume and wait for the answer.
+    async fn fetch_volume(&self) -> Result<Volume, LoungeError> {
+        debug!("Volume not known yet, asking the TV.");
+        let outcome = self
+            .send_command_ack(PlaybackCommand::GetVolume, SETTINGS.request_timeout)
+            .await?;
+        match outcome {
+            CommandOutcome::Acknowledged(event) => match *event {
+                LoungeEvent::VolumeChanged(volume) => Ok(Volume::from(&volume)),
+                _ => Err(LoungeError::VolumeUnknown),
+            },
+            _ => Err(LoungeError::VolumeUnknown),
+        }
+    }
+
+    pub async fn set_autoplay_mode(&self, autoplay_mode: String) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SetAutoplayMode { autoplay_mode })
+            .await
+    }
+
+    pub async fn set_playlist_mode(
+        &self,
+        loop_enabled: bool,
+        shuffle_enabled: bool,
+    ) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::SetPlaylistMode {
+            loop_enabled,
+            shuffle_enabled,
+        })
+        .await
+    }
+
+    pub async fn play_video(&self, video_id: String) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::set_playlist(video_id))
+            .await
+    }
+
+    /// Start playing `video_id` and return a stream of the `PlaybackSession`s for
+    /// that playback only (locked to the CPN of its first session). The stream ends
+    /// once the video stops or the TV moves on to a different video.
+    pub async fn play_and_watch(
+        &self,
+        video_id: String,
+    ) -> Result<impl Stream<Item = PlaybackSession>, LoungeError> {
+        // Subscribe before starting playback so no early session is missed
+        let receiver = self.event_sender.subscribe();
+        self.play_video(video_id.clone()).await?;
+
+        struct Watch {
+            receiver: broadcast::Receiver<LoungeEvent>,
+            video_id: String,
+            cpn: Option<String>,
+            finished: bool,
+        }
+
+        let watch = Watch {
+            receiver,
+            video_id,
+            cpn: None,
+            finished: false,
+        };
+        Ok(futures::stream::unfold(watch, |mut watch| async move {
+            if watch.finished {
+                return None;
+            }
+            loop {
+                let session = match watch.receiver.recv().await {
+                    Ok(LoungeEvent::PlaybackSession(session)) => session,
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!(
+                            "play_and_watch lagged behind event stream, skipped {} events",
+                            n
+                        );
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                };
+
+                match (&watch.cpn, &session.cpn) {
+                    (Some(cpn), Some(session_cpn)) if cpn != session_cpn => {
+                        if session.video_id != watch.video_id {
+                            debug!("TV moved on to another video, ending play_and_watch stream.");
+                            return None;
+                        }
+                        continue; // Stale session from an earlier playback of the same video
+                    }
+                    (None, _) if session.video_id != watch.video_id => continue,
+                    (None, Some(session_cpn)) => watch.cpn = Some(session_cpn.clone()),
+                    _ => {}
+                }
+
+                watch.finished = session.status() == PlaybackStatus::Stopped;
+                return Some((session, watch));
+            }
+        }))
+    }
+
+    pub async fn add_video_to_queue(&self, video_id: String) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::add_video(video_id))
+            .await
+    }
+
+    /// Move a video already in the queue to `to_index` (zero-based).
+    pub async fn move_in_queue(&self, video_id: String, to_index: i32) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::MoveVideo { video_id, to_index })
+            .await
+    }
+
+    /// The play queue as tracked from the TV's `playlistModified` reports.
+    pub fn get_queue(&self) -> Queue {
+        self.player_state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .queue
+            .clone()
+    }
+
+    /// Remove the video at `index` (zero-based) of the tracked queue.
+    pub async fn remove_video(&self, index: usize) -> Result<(), LoungeError> {
+        let video_id = queued_video(&self.get_queue(), index)?;
+        self.send_command_with_refresh(PlaybackCommand::RemoveVideo { video_id })
+            .await
+    }
+
+    /// Move the video at `from` to position `to` (both zero-based) of the tracked queue.
+    pub async fn move_video(&self, from: usize, to: usize) -> Result<(), LoungeError> {
+        let queue = self.get_queue();
+        let video_id = queued_video(&queue, from)?;
+        queued_video(&queue, to)?;
+        self.move_in_queue(video_id, to as i32).await
+    }
+
+    /// Remove every video from the queue.
+    pub async fn clear_queue(&self) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::ClearPlaylist)
             .await
     }
 
@@ -1454,6 +2693,21 @@ impl LoungeClient {
             .await
     }
 
+    pub async fn play_playlist_from_video(
+        &self,
+        list_id: String,
+        video_id: String,
+    ) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::set_playlist_from_video(list_id, video_id))
+            .await
+    }
+
+    /// Start a radio mix from a seed video; the TV keeps queueing related videos.
+    pub async fn play_mix(&self, video_id: String) -> Result<(), LoungeError> {
+        self.send_command_with_refresh(PlaybackCommand::mix(video_id))
+            .await
+    }
+
     pub async fn play_playlist_at_index(
         &self,
         list_id: String,
@@ -1464,6 +2718,308 @@ impl LoungeClient {
     }
 }
 
+impl CommandContext {
+    /// Ask the screen to drop the session, reporting `reason`. Best effort:
+    /// failures are only logged.
+    async fn terminate(&self, reason: DisconnectReason) -> Result<(), LoungeError> {
+        // Read current session details FOR the terminate request
+        let sid: Option<String>;
+        let gsessionid: Option<String>;
+        let rid_val: u32;
+        let rid_string: String;
+        // Token is needed for the terminate request parameters
+        let token: String;
+
+        {
+            let session = self.session_state.read().await;
+            sid = session.sid.clone();
+            gsessionid = session.gsessionid.clone();
+            rid_val = session.rid.fetch_add(1, Ordering::SeqCst);
+            rid_string = rid_val.to_string();
+        }
+
+        {
+            let state_guard = self.shared_state.read().await;
+            token = state_guard.lounge_token.clone();
+        }
+
+        // Send terminate request (best effort) if session existed
+        if let (Some(sid_val), Some(gsessionid_val)) = (sid, gsessionid) {
+            // Re-checked parameters based on earlier fix for 411 error
+            let params = [
+                ("RID", rid_string.as_str()), // Use incremented RID from session state
+                ("VER", "8"),
+                ("CVER", "1"),
+                ("gsessionid", gsessionid_val.as_str()), // Session ID from session state
+                ("SID", sid_val.as_str()),               // Other Session ID from session state
+                ("auth_failure_option", "send_error"),
+                ("name", self.device_name.as_str()),
+                ("id", self.device_id.as_str()),
+                ("device", "REMOTE_CONTROL"),
+                ("loungeIdToken", token.as_str()), // Added token back, potentially needed
+            ];
+
+            let body_data = serde_urlencoded::to_string([
+                ("ui", ""),
+                ("TYPE", "terminate"),
+                ("clientDisconnectReason", reason.as_str()),
+            ])?;
+
+            debug!(?params, "Sending disconnect (terminate) request");
+            let res = self
+                .client
+                .post(self.config.endpoint("bc/bind"))
+                .query(&params)
+                .header(
+                    reqwest::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .body(body_data)
+                .timeout(self.config.terminate_timeout)
+                .send()
+                .await;
+
+            match res {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Terminate request successful.");
+                }
+                Ok(response) => {
+                    warn!(status=%response.status(), "Terminate request failed (status)");
+                }
+                Err(e) => {
+                    warn!("Error sending terminate request (ignored): {}", e);
+                }
+            }
+        } else {
+            warn!("No valid session details found when disconnecting, cannot send explicit terminate request.");
+        }
+
+        Ok(())
+    }
+
+    /// Send a playback command to the screen
+    async fn send(&self, command: PlaybackCommand) -> Result<(), LoungeError> {
+        let command = match self.config.command_coalesce_window {
+            Some(window) if command.is_coalescable() => {
+                match self.throttle.coalesce(command, window).await {
+                    Some(latest) => latest,
+                    None => {
+                        debug!("Command coalesced into a later one of its kind");
+                        return Ok(());
+                    }
+                }
+            }
+            _ => command,
+        };
+        self.send_batch(std::slice::from_ref(&command)).await
+    }
+
+    /// Send several commands in one request. The batch occupies `commands.len()`
+    /// consecutive offsets, starting at the `ofs` sent with it.
+    async fn send_batch(&self, commands: &[PlaybackCommand]) -> Result<(), LoungeError> {
+        if commands.is_empty() {
+            return Ok(());
+        }
+        let started = Instant::now();
+        let result = self.send_batch_request(commands).await;
+        if let Some(metrics) = LoungeClient::metrics(&self.shared_state).await {
+            metrics.command_sent(commands, started.elapsed(), result.is_ok());
+        }
+        result
+    }
+
+    async fn send_batch_request(&self, commands: &[PlaybackCommand]) -> Result<(), LoungeError> {
+        self.commands_in_flight.send_modify(|count| *count += 1);
+        // Decrements again however the request ends, including cancellation
+        let _in_flight = InFlightGuard(&self.commands_in_flight);
+        if let Some(interval) = self.config.min_command_interval {
+            self.throttle.acquire(interval).await;
+        }
+        // Check connection state first
+        let mut current_state = self.state_rx.borrow().clone();
+        if self.config.queue_commands_until_connected
+            && matches!(
+                current_state,
+                ConnectionState::Connecting | ConnectionState::WaitingToReconnect { .. }
+            )
+        {
+            debug!(state=?current_state, "Holding command until connected.");
+            let mut state_rx = self.state_rx.clone();
+            let connected = timeout(
+                SETTINGS.request_timeout,
+                state_rx.wait_for(|state| *state == ConnectionState::Connected),
+            )
+            .await
+            .is_ok_and(|waited| waited.is_ok());
+            if connected {
+                current_state = ConnectionState::Connected;
+            }
+        }
+        if current_state != ConnectionState::Connected {
+            warn!(state=?current_state, "Attempted to send command while not connected.");
+            return Err(LoungeError::SessionLost);
+        }
+
+        let sid: String;
+        let gsessionid: String;
+        let rid_val: u32;
+        let ofs_val: u32;
+        let rid_string: String;
+        let ofs_string: String;
+
+        let token: String;
+
+        {
+            let session = self.session_state.read().await;
+            // These unwraps are now safe due to the ConnectionState::Connected check above
+            sid = session.sid.clone().ok_or(LoungeError::SessionLost)?;
+            gsessionid = session.gsessionid.clone().ok_or(LoungeError::SessionLost)?;
+
+            rid_val = session.rid.fetch_add(1, Ordering::SeqCst);
+            ofs_val = session
+                .command_offset
+                .fetch_add(commands.len() as u32, Ordering::SeqCst);
+            rid_string = rid_val.to_string();
+            ofs_string = ofs_val.to_string();
+        }; // Release read lock on session_state
+
+        {
+            let state_guard = self.shared_state.read().await;
+            token = state_guard.lounge_token.clone();
+        }; // Release read lock on shared_state (token)
+
+        let current_aid = self.aid_atomic.load(Ordering::SeqCst);
+        let aid_string: String = current_aid.to_string();
+
+        let command_name = commands
+            .iter()
+            .map(PlaybackCommand::name)
+            .collect::<Vec<_>>()
+            .join(",");
+        debug!(
+            "Sending command: {} (RID: {}, offset: {})",
+            command_name, rid_val, ofs_val
+        );
+
+        let mut form_fields: Vec<(String, String)> = Vec::with_capacity(2 + 8 * commands.len());
+        form_fields.push(("count".to_string(), commands.len().to_string()));
+        form_fields.push(("ofs".to_string(), ofs_string));
+        for (index, command) in commands.iter().enumerate() {
+            form_fields.push((format!("req{}__sc", index), command.name().to_string()));
+            for (key, value) in command.form_fields() {
+                form_fields.push((format!("req{}_{}", index, key), value));
+            }
+        }
+
+        let params = [
+            ("SID", sid.as_str()),
+            ("gsessionid", gsessionid.as_str()),
+            ("RID", rid_string.as_str()),
+            ("VER", "8"),
+            ("v", "2"),
+            ("TYPE", "bind"),
+            ("t", "1"),
+            ("AID", aid_string.as_str()),
+            ("CI", "0"),
+            ("name", self.device_name.as_str()),
+            ("id", self.device_id.as_str()),
+            ("device", "REMOTE_CONTROL"),
+            ("loungeIdToken", token.as_str()),
+        ];
+
+        debug!(?params, ?form_fields, "Sending command request");
+
+        let response = self
+            .client
+            .post(self.config.endpoint("bc/bind"))
+            .query(&params)
+            .form(&form_fields)
+            .send()
+            .await
+            .map_err(|e| {
+                let context = ErrorContext::new(&self.screen_id, "bc/bind");
+                self.config
+                    .contextualize(LoungeError::RequestFailed(e), context)
+            })?;
+        record_error_response(&self.last_error_response, &response);
+
+        let status = response.status();
+        let body_text = if self.config.observe_command_responses || !status.is_success() {
+            response.text().await.map_err(LoungeError::RequestFailed)?
+        } else {
+            String::new()
+        };
+        if self.config.observe_command_responses {
+            let state_guard = self.shared_state.read().await;
+            if let Some(ref callback) = state_guard.command_response_callback {
+                callback(&command_name, status.as_u16(), &body_text);
+            }
+        }
+
+        let result = match status.as_u16() {
+            200 => {
+                debug!("Command sent successfully: {}", command_name);
+                Ok(())
+            }
+            400 => {
+                warn!(
+                    "Session likely expired (HTTP 400) sending command: {}",
+                    command_name
+                );
+                Err(LoungeError::SessionInvalidatedByServer(400))
+            }
+            401 => {
+                warn!("Token expired (HTTP 401) sending command: {}", command_name);
+                Err(LoungeError::TokenExpired)
+            }
+            404 => {
+                warn!(
+                    "Session not found (HTTP 404) sending command: {}",
+                    command_name
+                );
+                Err(LoungeError::SessionInvalidatedByServer(404))
+            }
+            410 => {
+                warn!(
+                    "Connection closed (HTTP 410) sending command: {}",
+                    command_name
+                );
+                Err(LoungeError::ConnectionClosed) // Or SessionInvalidated? ConnectionClosed seems slightly better.
+            }
+            code if !status.is_success() => {
+                let error_msg = format!(
+                    "Command '{}' failed with status {} and response body:\n{}",
+                    command_name, code, body_text
+                );
+                error!("{}", error_msg);
+                Err(LoungeError::InvalidResponse(error_msg))
+            }
+            _ => {
+                warn!(status=%status, "Unexpected successful status code sending command.");
+                Err(LoungeError::InvalidResponse(format!(
+                    "Unexpected status {} sending command",
+                    status
+                )))
+            }
+        };
+        result.map_err(|error| {
+            let context = ErrorContext::new(&self.screen_id, "bc/bind")
+                .status(status.as_u16())
+                .body(&body_text);
+            self.config.contextualize(error, context)
+        })
+    }
+}
+
+/// Marks a command request as finished when dropped.
+struct InFlightGuard<'a>(&'a watch::Sender<usize>);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
 // Safety traits
 impl std::fmt::Debug for LoungeClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1489,9 +3045,59 @@ impl Drop for LoungeClient {
             self.stop_signal.store(true, Ordering::SeqCst);
             self.shutdown_notify.notify_one(); // Use notify_one if only one task needs waking
         }
+
+        // Don't leave the manager, and any long poll it has open, running
+        // until it next checks the stop signal
+        if let Ok(mut task_guard) = self.management_task.try_write() {
+            if let Some(handle) = task_guard.take() {
+                handle.abort();
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(runtime) = &self.config.reaper {
+            let has_session = self
+                .session_state
+                .try_read()
+                .is_ok_and(|session| session.sid.is_some());
+            if has_session {
+                debug!("[{}] Reaper sending terminate request", self.screen_id);
+                let ctx = self.command_context();
+                runtime.spawn(async move {
+                    if let Err(e) = ctx.terminate(DisconnectReason::default()).await {
+                        warn!("Reaper could not send terminate request: {}", e);
+                    }
+                });
+            }
+        }
     }
 }
 
+/// Remember (and log) the status and diagnostic headers of an unsuccessful response.
+fn record_error_response(slot: &Mutex<Option<ResponseDiagnostics>>, response: &reqwest::Response) {
+    if response.status().is_success() {
+        return;
+    }
+    let diagnostics = ResponseDiagnostics::from_response(response);
+    debug!(
+        status = diagnostics.status,
+        headers = ?diagnostics.headers,
+        "Unsuccessful response headers"
+    );
+    *slot.lock().unwrap_or_else(PoisonError::into_inner) = Some(diagnostics);
+}
+
+/// The video at `index` of `queue`, or `QueueIndexOutOfRange`.
+fn queued_video(queue: &Queue, index: usize) -> Result<String, LoungeError> {
+    queue
+        .get(index)
+        .map(str::to_string)
+        .ok_or(LoungeError::QueueIndexOutOfRange {
+            index,
+            len: queue.len(),
+        })
+}
+
 /// Helper to calculate backoff delay with jitter
 fn calculate_backoff_delay(base_backoff: Duration) -> Duration {
     let jitter_factor = rand::random::<f32>() * 0.6 - 0.3; // -0.3 to +0.3
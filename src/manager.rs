@@ -0,0 +1,274 @@
+// Several clients behind one handle: one event stream, broadcast commands and
+// restarts of clients that gave up.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use futures::future::join_all;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, info, warn};
+
+use crate::rt::{self, JoinHandle};
+use crate::{ConnectionState, LoungeClient, LoungeError, LoungeEvent, PlaybackCommand, SETTINGS};
+
+/// An event from one of the screens of a [`LoungeManager`].
+#[derive(Debug, Clone)]
+pub struct ScreenEvent {
+    pub screen_id: String,
+    pub event: LoungeEvent,
+}
+
+struct ManagedScreen {
+    client: Arc<LoungeClient>,
+    // Event forwarding and supervision, stopped when the screen is removed
+    tasks: [JoinHandle<()>; 2],
+}
+
+impl ManagedScreen {
+    fn abort(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Controls several screens at once, one [`LoungeClient`] per screen ID.
+///
+/// Events of all screens arrive on one channel, tagged with their screen.
+/// Each client still reconnects by itself after recoverable errors; the
+/// manager additionally restarts clients that ended up `Failed`, e.g. when
+/// the initial connect failed, with a delay that doubles with each further
+/// failure up to the client's `max_backoff`. Fatal failures, such as a
+/// screen that is no longer paired, are not restarted.
+pub struct LoungeManager {
+    screens: Mutex<HashMap<String, ManagedScreen>>,
+    events: broadcast::Sender<ScreenEvent>,
+    restart_delay: Duration,
+}
+
+impl Default for LoungeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoungeManager {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(SETTINGS.event_buffer_capacity);
+        Self {
+            screens: Mutex::new(HashMap::new()),
+            events,
+            restart_delay: Duration::from_secs(5),
+        }
+    }
+
+    /// How long a client must stay `Failed` before it is restarted (5s by
+    /// default). Only affects screens added afterwards.
+    pub fn with_restart_delay(mut self, delay: Duration) -> Self {
+        self.restart_delay = delay;
+        self
+    }
+
+    /// Manage `client`, keyed by its screen ID. A client already managed for
+    /// that screen is replaced and disconnected. Must be called from within a
+    /// tokio runtime.
+    pub async fn add(&self, client: LoungeClient) -> Arc<LoungeClient> {
+        let client = Arc::new(client);
+        let screen_id = client.screen_id().to_string();
+        let tasks = [
+            rt::spawn(forward_events(
+                screen_id.clone(),
+                client.event_receiver(),
+                self.events.clone(),
+            )),
+            rt::spawn(supervise(client.clone(), self.restart_delay)),
+        ];
+        let previous = self.lock().insert(
+            screen_id.clone(),
+            ManagedScreen {
+                client: client.clone(),
+                tasks,
+            },
+        );
+        if let Some(previous) = previous {
+            info!("[{}] Replacing managed client", screen_id);
+            previous.abort();
+            let _ = previous.client.disconnect().await;
+        }
+        client
+    }
+
+    /// Stop managing a screen and disconnect its client.
+    pub async fn remove(&self, screen_id: &str) -> Option<Arc<LoungeClient>> {
+        let removed = self.lock().remove(screen_id)?;
+        removed.abort();
+        if let Err(e) = removed.client.disconnect().await {
+            warn!(error = %e, "[{}] Disconnect of removed client failed", screen_id);
+        }
+        Some(removed.client)
+    }
+
+    pub fn client(&self, screen_id: &str) -> Option<Arc<LoungeClient>> {
+        self.lock()
+            .get(screen_id)
+            .map(|screen| screen.client.clone())
+    }
+
+    pub fn screen_ids(&self) -> Vec<String> {
+        self.lock().keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Events of every managed screen. Like [`LoungeClient::event_receiver`],
+    /// a receiver only sees events sent after it was created.
+    pub fn event_receiver(&self) -> broadcast::Receiver<ScreenEvent> {
+        self.events.subscribe()
+    }
+
+    /// The connection state of every managed screen.
+    pub fn states(&self) -> HashMap<String, ConnectionState> {
+        self.lock()
+            .iter()
+            .map(|(id, screen)| (id.clone(), screen.client.current_state()))
+            .collect()
+    }
+
+    /// Connect every screen concurrently, refreshing expired tokens. Screens
+    /// that fail are restarted later like any other failed client.
+    pub async fn connect_all(&self) -> HashMap<String, Result<(), LoungeError>> {
+        self.for_each(|client| async move { client.connect_with_refresh().await })
+            .await
+    }
+
+    pub async fn disconnect_all(&self) -> HashMap<String, Result<(), LoungeError>> {
+        self.for_each(|client| async move { client.disconnect().await })
+            .await
+    }
+
+    /// Send `command` to every screen concurrently.
+    pub async fn send_all(
+        &self,
+        command: PlaybackCommand,
+    ) -> HashMap<String, Result<(), LoungeError>> {
+        self.for_each(|client| {
+            let command = command.clone();
+            async move { client.send_command(command).await }
+        })
+        .await
+    }
+
+    pub async fn play_all(&self) -> HashMap<String, Result<(), LoungeError>> {
+        self.send_all(PlaybackCommand::Play).await
+    }
+
+    pub async fn pause_all(&self) -> HashMap<String, Result<(), LoungeError>> {
+        self.send_all(PlaybackCommand::Pause).await
+    }
+
+    /// Run `action` on every client concurrently, collecting the results by screen ID.
+    async fn for_each<F, Fut>(&self, action: F) -> HashMap<String, Result<(), LoungeError>>
+    where
+        F: Fn(Arc<LoungeClient>) -> Fut,
+        Fut: std::future::Future<Output = Result<(), LoungeError>>,
+    {
+        // Not held across the awaits below
+        let clients: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(id, screen)| (id.clone(), screen.client.clone()))
+            .collect();
+        join_all(clients.into_iter().map(|(id, client)| {
+            let result = action(client);
+            async move { (id, result.await) }
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ManagedScreen>> {
+        self.screens.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for LoungeManager {
+    fn drop(&mut self) {
+        for screen in self.lock().values() {
+            screen.abort();
+        }
+    }
+}
+
+/// Tag the client's events with its screen ID and pass them on.
+async fn forward_events(
+    screen_id: String,
+    mut rx: broadcast::Receiver<LoungeEvent>,
+    events: broadcast::Sender<ScreenEvent>,
+) {
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                // No subscribers is fine; nobody is listening yet
+                let _ = events.send(ScreenEvent {
+                    screen_id: screen_id.clone(),
+                    event,
+                });
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(
+                    skipped,
+                    "[{}] Manager fell behind the client's events", screen_id
+                )
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Restart the client whenever it stays `Failed` for the restart delay.
+/// Failures the client recovers from by itself (it leaves `Failed` again,
+/// e.g. for `WaitingToReconnect`) are left to it.
+async fn supervise(client: Arc<LoungeClient>, restart_delay: Duration) {
+    let screen_id = client.screen_id().to_string();
+    let mut states = client.state_receiver();
+    let mut delay = restart_delay;
+    loop {
+        let failure = match &*states.borrow_and_update() {
+            ConnectionState::Failed(failure) => Some(failure.clone()),
+            ConnectionState::Connected => {
+                delay = restart_delay;
+                None
+            }
+            _ => None,
+        };
+        match failure {
+            Some(failure) if failure.fatal => {
+                info!(%failure, "[{}] Retrying can't help, not restarting", screen_id);
+            }
+            Some(failure) => match rt::timeout(delay, states.changed()).await {
+                Ok(Ok(())) => continue,
+                Ok(Err(_)) => break,
+                Err(_) => {
+                    info!(%failure, "[{}] Restarting failed client after {:?}", screen_id, delay);
+                    delay = (delay * 2).min(client.config().max_backoff);
+                    if let Err(e) = client.connect_with_refresh().await {
+                        debug!(error = %e, "[{}] Restart failed", screen_id);
+                    }
+                    continue;
+                }
+            },
+            None => {}
+        }
+        if states.changed().await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// Instrumentation hooks, so operators can export counts and latencies of the
+// client's network activity to Prometheus or any other metrics system.
+
+use std::time::Duration;
+
+use crate::{PlaybackCommand, PollCycleStats};
+
+/// Receives measurements from a client, set with
+/// [`crate::LoungeClient::set_metrics`].
+///
+/// Every method has a no-op default, so implementations only override what
+/// they export. Calls are synchronous and made from the client's tasks, some
+/// once per decoded message, so they should do no more than update counters
+/// or histograms.
+pub trait Metrics: Send + Sync {
+    /// A long-poll cycle ended.
+    fn poll_cycle(&self, _stats: &PollCycleStats) {}
+
+    /// One protocol message of `_bytes` bytes was decoded from the event stream.
+    fn message_decoded(&self, _bytes: usize) {}
+
+    /// The event stream couldn't be decoded, which ends the poll cycle.
+    fn decode_error(&self) {}
+
+    /// A bind request to establish a session finished. `_established` is
+    /// false for any failure, including an expired token.
+    fn bind_attempt(&self, _elapsed: Duration, _established: bool) {}
+
+    /// The connection manager is waiting `_backoff` before reconnecting.
+    fn reconnect_scheduled(&self, _backoff: Duration) {}
+
+    /// A command request finished; a batch is reported once with all its
+    /// commands. `_elapsed` includes any time spent throttled or queued
+    /// until connected.
+    fn command_sent(&self, _commands: &[PlaybackCommand], _elapsed: Duration, _succeeded: bool) {}
+}
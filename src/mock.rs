@@ -0,0 +1,502 @@
+//! An in-process Lounge API for running clients without a TV or network.
+//!
+//! [`MockLoungeServer`] listens on a local port and implements enough of the
+//! pairing and `bc/bind` endpoints for full connect, poll and command cycles:
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), youtube_lounge_rs::LoungeError> {
+//! use youtube_lounge_rs::mock::MockLoungeServer;
+//! use youtube_lounge_rs::{LoungeClient, PlaybackCommand};
+//!
+//! let server = MockLoungeServer::start().await?;
+//! let screen = LoungeClient::pair_with_screen_with_config(
+//!     MockLoungeServer::PAIRING_CODE,
+//!     &server.config(),
+//! )
+//! .await?;
+//! let client = LoungeClient::builder(&screen.screen_id, &screen.lounge_token, "Test Remote")
+//!     .config(server.config())
+//!     .build()?;
+//! client.connect().await?;
+//! client.send_command(PlaybackCommand::Pause).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Commands are recorded and, for the basic playback and volume commands,
+//! answered with the event a TV would send (`play` with an `onStateChange`,
+//! `setVolume` with an `onVolumeChanged`, ...). Other events can be pushed
+//! with [`MockLoungeServer::push_event`].
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use bytes::Bytes;
+use futures::stream;
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+use crate::{LoungeClient, LoungeConfig, LoungeError, Screen};
+
+type Body = UnsyncBoxBody<Bytes, Infallible>;
+
+/// A command received by a [`MockLoungeServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedCommand {
+    /// The command as sent, e.g. `setVolume`.
+    pub name: String,
+    /// Its parameters without the `reqN_` prefix, e.g. `volume`.
+    pub params: HashMap<String, String>,
+}
+
+/// What the mock TV is playing, kept to echo commands like a TV would.
+struct Player {
+    video_id: String,
+    list_id: Option<String>,
+    current_time: f64,
+    state: &'static str,
+    volume: i32,
+    muted: bool,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            video_id: String::new(),
+            list_id: None,
+            current_time: 0.0,
+            // Unstarted
+            state: "-1",
+            volume: 100,
+            muted: false,
+        }
+    }
+}
+
+impl Player {
+    fn state_change(&self) -> (&'static str, Value) {
+        (
+            "onStateChange",
+            json!({
+                "currentTime": self.current_time.to_string(),
+                "state": self.state,
+                "duration": "0",
+                "cpn": "mock-cpn",
+                "loadedTime": "0",
+            }),
+        )
+    }
+
+    fn now_playing(&self) -> (&'static str, Value) {
+        let mut payload = json!({
+            "videoId": self.video_id,
+            "currentTime": self.current_time.to_string(),
+            "state": self.state,
+            "cpn": "mock-cpn",
+        });
+        if let Some(list_id) = &self.list_id {
+            payload["listId"] = json!(list_id);
+        }
+        ("nowPlaying", payload)
+    }
+
+    fn volume_changed(&self) -> (&'static str, Value) {
+        (
+            "onVolumeChanged",
+            json!({"volume": self.volume.to_string(), "muted": self.muted.to_string()}),
+        )
+    }
+
+    /// Apply a command, returning the events reporting its effect.
+    fn apply(&mut self, command: &ReceivedCommand) -> Vec<(&'static str, Value)> {
+        let param = |key: &str| command.params.get(key).map(String::as_str);
+        match command.name.as_str() {
+            "play" => {
+                self.state = "1";
+                vec![self.state_change()]
+            }
+            "pause" => {
+                self.state = "2";
+                vec![self.state_change()]
+            }
+            "stopVideo" => {
+                self.state = "0";
+                vec![self.state_change()]
+            }
+            "seekTo" => {
+                self.current_time = param("newTime").and_then(|t| t.parse().ok()).unwrap_or(0.0);
+                vec![self.state_change()]
+            }
+            "setPlaylist" => {
+                self.video_id = param("videoId").unwrap_or_default().to_string();
+                self.list_id = param("listId").map(str::to_string);
+                self.current_time = param("currentTime")
+                    .and_then(|t| t.parse().ok())
+                    .unwrap_or(0.0);
+                self.state = "1";
+                vec![self.now_playing(), self.state_change()]
+            }
+            "setVolume" => {
+                if let Some(volume) = param("volume").and_then(|v| v.parse().ok()) {
+                    self.volume = volume;
+                }
+                vec![self.volume_changed()]
+            }
+            "mute" | "unMute" => {
+                self.muted = command.name == "mute";
+                vec![self.volume_changed()]
+            }
+            "setPlaylistMode" => {
+                let enabled = |key: &str| param(key).unwrap_or("false").to_string();
+                vec![(
+                    "onPlaylistModeChanged",
+                    json!({"loopEnabled": enabled("loopEnabled"), "shuffleEnabled": enabled("shuffleEnabled")}),
+                )]
+            }
+            "getNowPlaying" => vec![self.now_playing()],
+            "getVolume" => vec![self.volume_changed()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A bound session and the long poll currently streaming its events.
+struct Session {
+    gsessionid: String,
+    next_aid: u32,
+    poll: Option<mpsc::UnboundedSender<Bytes>>,
+    // Events that arrived while no poll was open
+    queued: Vec<Bytes>,
+}
+
+impl Session {
+    fn send(&mut self, event_type: &str, payload: Value) {
+        let message = json!([[self.next_aid, [event_type, payload]]]).to_string();
+        self.next_aid += 1;
+        let chunk = Bytes::from(frame(&message));
+        match &self.poll {
+            Some(poll) if poll.send(chunk.clone()).is_ok() => {}
+            _ => self.queued.push(chunk),
+        }
+    }
+}
+
+struct MockState {
+    lounge_token: String,
+    token_generation: u32,
+    sessions: HashMap<String, Session>,
+    next_session: u32,
+    commands: Vec<ReceivedCommand>,
+    player: Player,
+}
+
+/// A local stand-in for the YouTube Lounge API with one paired screen.
+///
+/// Point clients at it with [`Self::config`]. The server stops when dropped.
+pub struct MockLoungeServer {
+    api_base: String,
+    state: Arc<Mutex<MockState>>,
+    task: JoinHandle<()>,
+}
+
+impl MockLoungeServer {
+    /// The code `pairing/get_screen` accepts.
+    pub const PAIRING_CODE: &'static str = "123456789012";
+    pub const SCREEN_ID: &'static str = "mock-screen";
+    pub const SCREEN_NAME: &'static str = "Mock TV";
+
+    /// Listen on a free port on localhost. Must be called from within a tokio runtime.
+    pub async fn start() -> Result<Self, LoungeError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            lounge_token: "mock-token-1".to_string(),
+            token_generation: 1,
+            sessions: HashMap::new(),
+            next_session: 1,
+            commands: Vec::new(),
+            player: Player::default(),
+        }));
+
+        let shared = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = shared.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(state.clone(), req));
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        debug!(error = %e, "Mock connection ended with an error");
+                    }
+                });
+            }
+        });
+        info!("Mock Lounge API listening on {}", addr);
+
+        Ok(Self {
+            api_base: format!("http://{}/api/lounge", addr),
+            state,
+            task,
+        })
+    }
+
+    /// Base URL to use as [`LoungeConfig::api_base`].
+    pub fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    /// The default config, pointed at this server.
+    pub fn config(&self) -> LoungeConfig {
+        LoungeConfig {
+            api_base: self.api_base.clone(),
+            ..LoungeConfig::default()
+        }
+    }
+
+    /// The paired screen, with its currently valid token.
+    pub fn screen(&self) -> Screen {
+        Screen {
+            name: Some(Self::SCREEN_NAME.to_string()),
+            screen_id: Self::SCREEN_ID.to_string(),
+            lounge_token: self.lock().lounge_token.clone(),
+        }
+    }
+
+    /// A client for the paired screen using [`Self::config`].
+    pub fn client(&self, device_name: &str) -> LoungeClient {
+        let screen = self.screen();
+        LoungeClient::new(
+            &screen.screen_id,
+            &screen.lounge_token,
+            device_name,
+            None,
+            None,
+        )
+        // No proxy, so the default HTTP client stays
+        .apply_config(self.config())
+    }
+
+    /// Send an event to every bound session, e.g.
+    /// `push_event("onAdStateChange", json!({...}))`. Sessions without an open
+    /// long poll get it with their next one.
+    pub fn push_event(&self, event_type: &str, payload: Value) {
+        for session in self.lock().sessions.values_mut() {
+            session.send(event_type, payload.clone());
+        }
+    }
+
+    /// Every command received so far, in arrival order.
+    pub fn commands(&self) -> Vec<ReceivedCommand> {
+        self.lock().commands.clone()
+    }
+
+    /// Issue a new lounge token. Requests with the old one get a 401 until
+    /// the client fetches the new one from `pairing/get_lounge_token_batch`.
+    pub fn expire_token(&self) {
+        let mut state = self.lock();
+        state.token_generation += 1;
+        state.lounge_token = format!("mock-token-{}", state.token_generation);
+    }
+
+    /// Forget every session, as the server does when one expires. Open long
+    /// polls end, and further requests for those sessions get a 400.
+    pub fn end_sessions(&self) {
+        self.lock().sessions.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for MockLoungeServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.end_sessions();
+    }
+}
+
+/// Length-prefix a message the way the long poll does.
+fn frame(message: &str) -> String {
+    let content = format!("{}\n", message);
+    format!("{}\n{}", content.len(), content)
+}
+
+fn respond(status: StatusCode, body: impl Into<Bytes>) -> Response<Body> {
+    let mut response = Response::new(
+        Full::new(body.into())
+            .map_err(|never| match never {})
+            .boxed_unsync(),
+    );
+    *response.status_mut() = status;
+    response
+}
+
+async fn handle(
+    state: Arc<Mutex<MockState>>,
+    req: Request<Incoming>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let query: HashMap<String, String> =
+        serde_urlencoded::from_str(req.uri().query().unwrap_or_default()).unwrap_or_default();
+    let body = match req.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(e) => {
+            debug!(error = %e, "Failed to read mock request body");
+            return Ok(respond(StatusCode::BAD_REQUEST, "Bad request"));
+        }
+    };
+    let form: HashMap<String, String> = serde_urlencoded::from_bytes(&body).unwrap_or_default();
+    debug!(%method, %path, "Mock request");
+
+    let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+    let endpoint = path.strip_prefix("/api/lounge/").unwrap_or(&path);
+    let response = match (method, endpoint) {
+        (Method::POST, "pairing/get_screen") => {
+            if form.get("pairing_code").map(String::as_str) == Some(MockLoungeServer::PAIRING_CODE)
+            {
+                let screen = screen_json(&state);
+                respond(StatusCode::OK, json!({ "screen": screen }).to_string())
+            } else {
+                respond(StatusCode::NOT_FOUND, "Unknown pairing code")
+            }
+        }
+        (Method::POST, "pairing/get_lounge_token_batch") => {
+            let screens: Vec<_> = form
+                .get("screen_ids")
+                .into_iter()
+                .flat_map(|ids| ids.split(','))
+                .filter(|id| *id == MockLoungeServer::SCREEN_ID)
+                .map(|_| screen_json(&state))
+                .collect();
+            respond(StatusCode::OK, json!({ "screens": screens }).to_string())
+        }
+        (Method::POST, "pairing/get_screen_availability") => {
+            if form.get("lounge_token") == Some(&state.lounge_token) {
+                let screens = [json!({"loungeToken": state.lounge_token, "status": "online"})];
+                respond(StatusCode::OK, json!({ "screens": screens }).to_string())
+            } else {
+                respond(StatusCode::UNAUTHORIZED, "Expired token")
+            }
+        }
+        // The handshake sends the token in the form, everything else in the query
+        (method, "bc/bind")
+            if query.get("loungeIdToken").or(form.get("loungeIdToken"))
+                != Some(&state.lounge_token) =>
+        {
+            debug!(%method, "Mock bind with an expired token");
+            respond(StatusCode::UNAUTHORIZED, "Expired token")
+        }
+        (method, "bc/bind") => bind(&mut state, method, &query, &form),
+        _ => respond(StatusCode::NOT_FOUND, "Not found"),
+    };
+    Ok(response)
+}
+
+fn screen_json(state: &MockState) -> Value {
+    json!({
+        "screenId": MockLoungeServer::SCREEN_ID,
+        "loungeToken": state.lounge_token,
+        "name": MockLoungeServer::SCREEN_NAME,
+    })
+}
+
+/// The `bc/bind` endpoint: handshake, commands, terminate and long polls.
+fn bind(
+    state: &mut MockState,
+    method: Method,
+    query: &HashMap<String, String>,
+    form: &HashMap<String, String>,
+) -> Response<Body> {
+    let Some(sid) = query.get("SID") else {
+        if method != Method::POST {
+            return respond(StatusCode::BAD_REQUEST, "Missing SID");
+        }
+        let sid = format!("mock-sid-{}", state.next_session);
+        let gsessionid = format!("mock-gsessionid-{}", state.next_session);
+        state.next_session += 1;
+        let handshake = frame(&format!(
+            r#"[[0,["c","{}","",8]],[1,["S","{}"]],[2,["noop"]]]"#,
+            sid, gsessionid
+        ));
+        state.sessions.insert(
+            sid,
+            Session {
+                gsessionid,
+                next_aid: 3,
+                poll: None,
+                queued: Vec::new(),
+            },
+        );
+        return respond(StatusCode::OK, handshake);
+    };
+    if form.get("TYPE").map(String::as_str) == Some("terminate") {
+        state.sessions.remove(sid);
+        return respond(StatusCode::OK, "");
+    }
+    let known = state
+        .sessions
+        .get(sid)
+        .is_some_and(|session| query.get("gsessionid") == Some(&session.gsessionid));
+    if !known {
+        return respond(StatusCode::BAD_REQUEST, "Unknown SID");
+    }
+
+    if method == Method::GET {
+        let session = state.sessions.get_mut(sid).expect("session checked above");
+        let (tx, rx) = mpsc::unbounded_channel();
+        for chunk in session.queued.drain(..) {
+            let _ = tx.send(chunk);
+        }
+        // A newer poll replaces the previous one, ending its stream
+        session.poll = Some(tx);
+        let events = stream::unfold(rx, |mut rx| async move {
+            let chunk = rx.recv().await?;
+            Some((Ok(Frame::data(chunk)), rx))
+        });
+        return Response::new(StreamBody::new(events).boxed_unsync());
+    }
+
+    let count: usize = form.get("count").and_then(|c| c.parse().ok()).unwrap_or(0);
+    for index in 0..count {
+        let Some(name) = form.get(&format!("req{}__sc", index)) else {
+            continue;
+        };
+        let prefix = format!("req{}_", index);
+        let params = form
+            .iter()
+            .filter(|(key, _)| !key.ends_with("__sc"))
+            .filter_map(|(key, value)| {
+                key.strip_prefix(&prefix)
+                    .map(|key| (key.to_string(), value.clone()))
+            })
+            .collect();
+        let command = ReceivedCommand {
+            name: name.clone(),
+            params,
+        };
+        debug!(command = %command.name, "Mock received command");
+        let echoes = state.player.apply(&command);
+        state.commands.push(command);
+        // Like a TV, tell every connected remote
+        for session in state.sessions.values_mut() {
+            for (event_type, payload) in &echoes {
+                session.send(event_type, payload.clone());
+            }
+        }
+    }
+    respond(StatusCode::OK, "")
+}
@@ -70,6 +70,30 @@ pub struct PlaybackState {
     pub loaded_time: String,
 }
 
+impl VideoData {
+    /// Placeholder metadata carrying only the video ID. The lounge stream does not
+    /// include titles or authors, so these stay empty until filled in elsewhere.
+    pub fn for_video(video_id: &str) -> Self {
+        Self {
+            video_id: video_id.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether this holds real metadata rather than a placeholder (i.e. has a title).
+    pub fn is_populated(&self) -> bool {
+        !self.title.trim().is_empty()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        Some(self.title.as_str()).filter(|t| !t.trim().is_empty())
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        Some(self.author.as_str()).filter(|a| !a.trim().is_empty())
+    }
+}
+
 // Helper function to provide default state value of "-1" (Stopped)
 pub fn default_state() -> String {
     "-1".to_string()
@@ -105,6 +129,9 @@ pub struct NowPlaying {
     pub seekable_start_time: String,
     #[serde(rename = "seekableEndTime", default)]
     pub seekable_end_time: String,
+    /// Chapter markers as sent by the TV, if any. See [`NowPlaying::chapters`].
+    #[serde(rename = "chapters", default)]
+    pub chapter_data: Option<serde_json::Value>,
 }
 
 impl NowPlaying {
@@ -112,9 +139,61 @@ impl NowPlaying {
     pub fn status(&self) -> PlaybackStatus {
         PlaybackStatus::from(self.state.as_str())
     }
+
+    /// Duration in seconds, or `None` when the TV did not report one. A live
+    /// stream reports `Some(0.0)`.
+    pub fn duration_opt(&self) -> Option<f64> {
+        youtube_parse::parse_float_opt(&self.duration)
+    }
+
+    /// Start of the seekable range in seconds, if reported.
+    pub fn seekable_start_time_opt(&self) -> Option<f64> {
+        youtube_parse::parse_float_opt(&self.seekable_start_time)
+    }
+
+    /// End of the seekable range in seconds, if reported.
+    pub fn seekable_end_time_opt(&self) -> Option<f64> {
+        youtube_parse::parse_float_opt(&self.seekable_end_time)
+    }
+
+    /// Chapters of the current video in playback order, or empty when the video
+    /// has none. Entries the TV sent without a title or start time are dropped.
+    pub fn chapters(&self) -> Vec<Chapter> {
+        // Like other list payloads, chapters may arrive as a JSON-encoded string
+        let entries = match &self.chapter_data {
+            Some(serde_json::Value::String(raw)) => {
+                serde_json::from_str::<Vec<serde_json::Value>>(raw).unwrap_or_default()
+            }
+            Some(serde_json::Value::Array(entries)) => entries.clone(),
+            _ => Vec::new(),
+        };
+        let mut chapters: Vec<Chapter> = entries.iter().filter_map(Chapter::from_json).collect();
+        chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+        chapters
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// A chapter marker within a video.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    /// Offset of the chapter from the start of the video, in seconds.
+    pub start: f64,
+    pub title: String,
+}
+
+impl Chapter {
+    fn from_json(entry: &serde_json::Value) -> Option<Self> {
+        let title = entry.get("title")?.as_str()?.to_string();
+        let start = match entry.get("startTime").or_else(|| entry.get("start"))? {
+            serde_json::Value::Number(n) => n.as_f64()?,
+            serde_json::Value::String(s) => youtube_parse::parse_float_opt(s)?,
+            _ => return None,
+        };
+        Some(Self { start, title })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct AdState {
     #[serde(rename = "adState")]
     pub ad_state: String,
@@ -158,6 +237,26 @@ pub struct AdPlaying {
 pub struct SubtitlesTrackChanged {
     #[serde(rename = "videoId")]
     pub video_id: String,
+    /// Language of the selected track, e.g. "en". Empty or absent when
+    /// subtitles are off.
+    #[serde(rename = "languageCode", default)]
+    pub language_code: Option<String>,
+}
+
+/// One entry of a subtitles track list.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SubtitleTrack {
+    #[serde(rename = "languageCode")]
+    pub language_code: String,
+    /// Human-readable name, e.g. "English (auto-generated)".
+    #[serde(rename = "languageName", default)]
+    pub language_name: String,
+    /// Track identifier, e.g. ".en" or "a.en" for automatic captions.
+    #[serde(rename = "vssId", default)]
+    pub vss_id: String,
+    /// `"asr"` for automatically generated tracks.
+    #[serde(default)]
+    pub kind: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -182,7 +281,7 @@ pub struct HasPreviousNextChanged {
     pub has_previous: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct VideoQualityChanged {
     #[serde(rename = "availableQualityLevels")]
     pub available_quality_levels: String,
@@ -192,12 +291,21 @@ pub struct VideoQualityChanged {
     pub video_id: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct VolumeChanged {
     pub muted: String,
     pub volume: String,
 }
 
+/// Seekable window of the current video, as reported inside `onStateChange`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SeekableRange {
+    #[serde(rename = "seekableStartTime")]
+    pub seekable_start_time: String,
+    #[serde(rename = "seekableEndTime")]
+    pub seekable_end_time: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct PlaylistModified {
     #[serde(rename = "currentIndex", default)]
@@ -208,6 +316,36 @@ pub struct PlaylistModified {
     pub list_id: String,
     #[serde(rename = "videoId", default)]
     pub video_id: String,
+    /// The whole queue as comma-separated video IDs, when the TV includes it.
+    #[serde(rename = "videoIds", default)]
+    pub video_ids: Option<String>,
+    /// What kind of change this was, when the TV says. `None` if the payload
+    /// has no action or one this crate doesn't know.
+    #[serde(alias = "type", default, deserialize_with = "playlist_action")]
+    pub action: Option<PlaylistAction>,
+}
+
+/// The kind of change reported by `playlistModified`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistAction {
+    Added,
+    Removed,
+    Reordered,
+    Cleared,
+}
+
+fn playlist_action<'de, D>(deserializer: D) -> Result<Option<PlaylistAction>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(match raw.to_ascii_lowercase().as_str() {
+        "add" | "added" | "insert" | "inserted" => Some(PlaylistAction::Added),
+        "remove" | "removed" | "delete" | "deleted" => Some(PlaylistAction::Removed),
+        "move" | "moved" | "reorder" | "reordered" => Some(PlaylistAction::Reordered),
+        "clear" | "cleared" => Some(PlaylistAction::Cleared),
+        _ => None,
+    })
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -224,6 +362,15 @@ pub struct AutoplayUpNext {
     pub video_id: String,
 }
 
+/// Reported by the TV when a video fails to play (e.g. unavailable or region-blocked).
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaybackError {
+    #[serde(rename = "videoId", default)]
+    pub video_id: Option<String>,
+    #[serde(rename = "errorCode", default)]
+    pub error_code: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct LoungeStatus {
     pub devices: String,
@@ -247,6 +394,12 @@ impl VideoQualityChanged {
     pub fn available_qualities(&self) -> Vec<String> {
         youtube_parse::parse_list(&self.available_quality_levels)
     }
+
+    /// Available quality levels as numbers (e.g. 1080), from a list such as
+    /// `"[0,1080,720,480]"`. Entries that aren't numeric are skipped.
+    pub fn quality_levels(&self) -> Vec<i32> {
+        youtube_parse::parse_int_list(&self.available_quality_levels)
+    }
 }
 
 // Helper methods for VolumeChanged
@@ -260,6 +413,17 @@ impl VolumeChanged {
     }
 }
 
+// Helper methods for SeekableRange
+impl SeekableRange {
+    pub fn start(&self) -> f64 {
+        youtube_parse::parse_float(&self.seekable_start_time)
+    }
+
+    pub fn end(&self) -> f64 {
+        youtube_parse::parse_float(&self.seekable_end_time)
+    }
+}
+
 // Helper methods for PlaylistModified
 impl PlaylistModified {
     pub fn current_index_value(&self) -> Option<i32> {
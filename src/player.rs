@@ -0,0 +1,305 @@
+use crate::events::{LoungeEvent, PlaybackStatus};
+use crate::models::{
+    AdState, AudioTrackChanged, NowPlaying, PlaybackState, PlaylistAction, PlaylistModified,
+    SubtitlesTrackChanged, VideoQualityChanged, VolumeChanged,
+};
+use crate::youtube_parse;
+
+/// Latest known player state, assembled from the event stream.
+#[derive(Debug, Clone, Default)]
+pub struct CurrentState {
+    pub now_playing: Option<NowPlaying>,
+    pub playback_state: Option<PlaybackState>,
+    /// Playback status from whichever of `nowPlaying`/`onStateChange` arrived last.
+    pub status: Option<PlaybackStatus>,
+    /// Set by `onAdStateChange` and cleared once content playback resumes.
+    pub ad_state: Option<AdState>,
+    pub volume: Option<VolumeChanged>,
+    /// Last quality report for the current video; cleared when the video changes.
+    pub video_quality: Option<VideoQualityChanged>,
+    /// Last subtitles report for the current video; cleared when the video changes.
+    pub subtitles: Option<SubtitlesTrackChanged>,
+    /// Last audio track report for the current video; cleared when the video changes.
+    pub audio_track: Option<AudioTrackChanged>,
+    /// The play queue, tracked from `playlistModified`.
+    pub queue: Queue,
+}
+
+/// The TV's volume, typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Volume {
+    /// 0 to 100.
+    pub level: i32,
+    pub muted: bool,
+}
+
+impl From<&VolumeChanged> for Volume {
+    fn from(volume: &VolumeChanged) -> Self {
+        Self {
+            level: volume.volume_level(),
+            muted: volume.is_muted(),
+        }
+    }
+}
+
+/// Where playback was, to pick it up again after the session was lost.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResumePoint {
+    pub(crate) video_id: String,
+    pub(crate) list_id: Option<String>,
+    pub(crate) position: f64,
+}
+
+/// The TV's play queue, as far as `playlistModified` reports reveal it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Queue {
+    /// The queue's playlist ID; a new one replaces the queue.
+    pub list_id: Option<String>,
+    /// Queued video IDs in play order.
+    pub video_ids: Vec<String>,
+    /// Position of the playing video in `video_ids`, when reported.
+    pub current_index: Option<usize>,
+}
+
+impl Queue {
+    pub fn len(&self) -> usize {
+        self.video_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.video_ids.is_empty()
+    }
+
+    /// The video at `index`, if the queue is that long.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.video_ids.get(index).map(String::as_str)
+    }
+
+    fn switch_to(&mut self, list_id: &str) {
+        if !list_id.is_empty() && self.list_id.as_deref() != Some(list_id) {
+            *self = Queue {
+                list_id: Some(list_id.to_string()),
+                ..Queue::default()
+            };
+        }
+    }
+
+    fn apply(&mut self, change: &PlaylistModified) {
+        self.switch_to(&change.list_id);
+        // A full listing, when sent, beats replaying the change on our copy
+        if let Some(ids) = &change.video_ids {
+            self.video_ids = youtube_parse::parse_list(ids)
+                .into_iter()
+                .filter(|id| !id.is_empty())
+                .collect();
+        } else {
+            match change.action {
+                Some(PlaylistAction::Added) if !change.video_id.is_empty() => {
+                    self.video_ids.push(change.video_id.clone());
+                }
+                Some(PlaylistAction::Removed) => {
+                    if let Some(pos) = self.video_ids.iter().position(|id| *id == change.video_id) {
+                        self.video_ids.remove(pos);
+                    }
+                }
+                Some(PlaylistAction::Cleared) => self.video_ids.clear(),
+                // A move doesn't say where to; wait for a full listing
+                _ => {}
+            }
+        }
+        self.current_index = change
+            .current_index_value()
+            .and_then(|index| usize::try_from(index).ok())
+            .filter(|index| *index < self.video_ids.len());
+    }
+}
+
+/// The current video's subtitle, audio and quality selections, e.g. for a
+/// settings panel. Each is `None` until the TV reports it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackSettings {
+    /// Subtitle language code; `None` also while subtitles are off.
+    pub subtitle: Option<String>,
+    pub audio: Option<String>,
+    /// Quality level, e.g. 1080.
+    pub quality: Option<i32>,
+}
+
+/// Which parts of the player state differ between two snapshots, so a renderer
+/// can redraw only the affected widgets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// A different video is loaded (or none is).
+    pub video: bool,
+    /// The video is playing from a different playlist.
+    pub playlist: bool,
+    /// The playback position or duration changed.
+    pub position: bool,
+    pub status: bool,
+    pub volume: bool,
+    /// An ad started, ended or changed state.
+    pub ad: bool,
+}
+
+impl StateDiff {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl CurrentState {
+    /// Fold an event into the cached state.
+    pub(crate) fn apply(&mut self, event: &LoungeEvent) {
+        match event {
+            LoungeEvent::StateChange(state) => {
+                self.set_status(state.status());
+                self.playback_state = Some(state.clone());
+            }
+            LoungeEvent::NowPlaying(np) => {
+                if self
+                    .video_quality
+                    .as_ref()
+                    .is_some_and(|quality| quality.video_id != np.video_id)
+                {
+                    self.video_quality = None;
+                }
+                if self
+                    .subtitles
+                    .as_ref()
+                    .is_some_and(|subtitles| subtitles.video_id != np.video_id)
+                {
+                    self.subtitles = None;
+                }
+                if self
+                    .audio_track
+                    .as_ref()
+                    .is_some_and(|audio| audio.video_id != np.video_id)
+                {
+                    self.audio_track = None;
+                }
+                if let Some(list_id) = &np.list_id {
+                    self.queue.switch_to(list_id);
+                }
+                self.set_status(np.status());
+                self.now_playing = Some(np.clone());
+            }
+            LoungeEvent::AdStateChange(ad_state) => {
+                self.ad_state = Some(ad_state.clone());
+            }
+            LoungeEvent::VolumeChanged(volume) => {
+                self.volume = Some(volume.clone());
+            }
+            LoungeEvent::VideoQualityChanged(quality) => {
+                self.video_quality = Some(quality.clone());
+            }
+            LoungeEvent::SubtitlesTrackChanged(subtitles) => {
+                self.subtitles = Some(subtitles.clone());
+            }
+            LoungeEvent::AudioTrackChanged(audio) => {
+                self.audio_track = Some(audio.clone());
+            }
+            LoungeEvent::PlaylistModified(change) => self.queue.apply(change),
+            _ => {}
+        }
+    }
+
+    fn set_status(&mut self, status: PlaybackStatus) {
+        if status != PlaybackStatus::Advertisement {
+            self.ad_state = None;
+        }
+        self.status = Some(status);
+    }
+
+    /// The loaded video and its last reported position, unless nothing is
+    /// loaded or playback had stopped.
+    pub(crate) fn resume_point(&self) -> Option<ResumePoint> {
+        let np = self
+            .now_playing
+            .as_ref()
+            .filter(|np| !np.video_id.is_empty())?;
+        if self.status == Some(PlaybackStatus::Stopped) {
+            return None;
+        }
+        // The state change's position is fresher, unless it belongs to another video
+        let position = match &self.playback_state {
+            Some(state) if state.cpn.is_none() || state.cpn == np.cpn => &state.current_time,
+            _ => &np.current_time,
+        };
+        Some(ResumePoint {
+            video_id: np.video_id.clone(),
+            list_id: np.list_id.clone(),
+            position: youtube_parse::parse_float(position),
+        })
+    }
+
+    /// The last reported volume. `None` until the TV reported one.
+    pub fn current_volume(&self) -> Option<Volume> {
+        self.volume.as_ref().map(Volume::from)
+    }
+
+    /// Take a level set through this client as the current one, ahead of the
+    /// TV confirming it, so quick successive steps build on each other.
+    pub(crate) fn record_volume_level(&mut self, level: i32) {
+        let muted = self
+            .volume
+            .as_ref()
+            .map_or_else(|| "false".to_string(), |volume| volume.muted.clone());
+        self.volume = Some(VolumeChanged {
+            muted,
+            volume: level.to_string(),
+        });
+    }
+
+    /// Whether the TV is idle (no video loaded, e.g. showing its home screen or
+    /// ambient mode), judged from the last `nowPlaying`. `None` until one arrived.
+    pub fn is_idle(&self) -> Option<bool> {
+        self.now_playing.as_ref().map(|np| np.video_id.is_empty())
+    }
+
+    /// The subtitle, audio and quality selections in one place.
+    pub fn track_settings(&self) -> TrackSettings {
+        TrackSettings {
+            subtitle: self
+                .subtitles
+                .as_ref()
+                .and_then(|s| s.language_code.clone())
+                .filter(|code| !code.is_empty()),
+            audio: self
+                .audio_track
+                .as_ref()
+                .map(|audio| audio.audio_track_id.clone())
+                .filter(|id| !id.is_empty()),
+            quality: self
+                .video_quality
+                .as_ref()
+                .and_then(|quality| quality.quality_level.trim().parse().ok()),
+        }
+    }
+
+    /// Compare with a later snapshot, reporting which parts changed.
+    pub fn diff(&self, other: &CurrentState) -> StateDiff {
+        let video_id = |s: &CurrentState| s.now_playing.as_ref().map(|np| np.video_id.clone());
+        let list_id = |s: &CurrentState| s.now_playing.as_ref().and_then(|np| np.list_id.clone());
+        // Prefer the state change's position, which the TV updates more often
+        let position = |s: &CurrentState| match (&s.playback_state, &s.now_playing) {
+            (Some(state), _) => Some((state.current_time.clone(), state.duration.clone())),
+            (None, Some(np)) => Some((np.current_time.clone(), np.duration.clone())),
+            (None, None) => None,
+        };
+        StateDiff {
+            video: video_id(self) != video_id(other),
+            playlist: list_id(self) != list_id(other),
+            position: position(self) != position(other),
+            status: self.status != other.status,
+            volume: self.volume != other.volume,
+            ad: self.ad_state != other.ad_state,
+        }
+    }
+
+    /// Whether an ad is currently playing, according to either the playback
+    /// status (1081) or a pending ad state.
+    pub fn is_playing_ad(&self) -> bool {
+        self.status == Some(PlaybackStatus::Advertisement) || self.ad_state.is_some()
+    }
+}
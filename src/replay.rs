@@ -0,0 +1,40 @@
+// Offline decoding of recorded long-poll bodies, for regression tests and analysis.
+
+use bytes::BytesMut;
+use tokio::sync::broadcast;
+use tokio_util::codec::Decoder;
+use tracing::warn;
+
+use crate::events::{self, EventPipeline};
+use crate::{LoungeCodec, LoungeEvent, SETTINGS};
+
+/// Decode a recorded long-poll response body into the events a client would
+/// have emitted for it, including synthesized ones such as `PlaybackSession`.
+///
+/// Uses default [`crate::LoungeConfig`] behaviour. Decoding stops at the first
+/// malformed frame; the events before it are still returned.
+pub fn parse_recording(bytes: &[u8]) -> Vec<LoungeEvent> {
+    let (sender, mut receiver) = broadcast::channel(SETTINGS.event_buffer_capacity);
+    let pipeline = EventPipeline::detached(sender);
+
+    let mut codec = LoungeCodec::new();
+    let mut buffer = BytesMut::from(bytes);
+    let mut decoded = Vec::new();
+    loop {
+        match codec.decode(&mut buffer) {
+            Ok(Some(message)) => {
+                events::process_event_chunk(&message, &pipeline);
+                // Drain after every message so long recordings can't overflow the channel
+                while let Ok(event) = receiver.try_recv() {
+                    decoded.push(event);
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!(error = %e, "Malformed frame in recording, stopping");
+                break;
+            }
+        }
+    }
+    decoded
+}
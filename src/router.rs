@@ -0,0 +1,255 @@
+// Per-kind event subscriptions. Each has a bounded queue of its own, fed by a
+// single router task off the client's broadcast channel, so a slow listener
+// only ever loses its own events instead of lagging everyone else's.
+
+use std::ops::BitOr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{debug, trace, warn};
+
+use crate::{rt, LoungeEvent};
+
+/// The kind of a [`LoungeEvent`], without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    StateChange,
+    NowPlaying,
+    PlaybackSession,
+    LoungeStatus,
+    ScreenDisconnected,
+    SessionEstablished,
+    SessionResumed,
+    AdPlaying,
+    AdStateChange,
+    SubtitlesTrackChanged,
+    SubtitlesTrackList,
+    AudioTrackChanged,
+    AutoplayModeChanged,
+    HasPreviousNextChanged,
+    VideoQualityChanged,
+    VolumeChanged,
+    SeekableRangeChanged,
+    PlaylistModified,
+    PlaylistModeChanged,
+    AutoplayUpNext,
+    PlaybackError,
+    Unknown,
+    Raw,
+}
+
+impl LoungeEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            LoungeEvent::StateChange(_) => EventKind::StateChange,
+            LoungeEvent::NowPlaying(_) => EventKind::NowPlaying,
+            LoungeEvent::PlaybackSession(_) => EventKind::PlaybackSession,
+            LoungeEvent::LoungeStatus(..) => EventKind::LoungeStatus,
+            LoungeEvent::ScreenDisconnected => EventKind::ScreenDisconnected,
+            LoungeEvent::SessionEstablished => EventKind::SessionEstablished,
+            LoungeEvent::SessionResumed { .. } => EventKind::SessionResumed,
+            LoungeEvent::AdPlaying(_) => EventKind::AdPlaying,
+            LoungeEvent::AdStateChange(_) => EventKind::AdStateChange,
+            LoungeEvent::SubtitlesTrackChanged(_) => EventKind::SubtitlesTrackChanged,
+            LoungeEvent::SubtitlesTrackList { .. } => EventKind::SubtitlesTrackList,
+            LoungeEvent::AudioTrackChanged(_) => EventKind::AudioTrackChanged,
+            LoungeEvent::AutoplayModeChanged(_) => EventKind::AutoplayModeChanged,
+            LoungeEvent::HasPreviousNextChanged(_) => EventKind::HasPreviousNextChanged,
+            LoungeEvent::VideoQualityChanged(_) => EventKind::VideoQualityChanged,
+            LoungeEvent::VolumeChanged(_) => EventKind::VolumeChanged,
+            LoungeEvent::SeekableRangeChanged(_) => EventKind::SeekableRangeChanged,
+            LoungeEvent::PlaylistModified(_) => EventKind::PlaylistModified,
+            LoungeEvent::PlaylistModeChanged(_) => EventKind::PlaylistModeChanged,
+            LoungeEvent::AutoplayUpNext(_) => EventKind::AutoplayUpNext,
+            LoungeEvent::PlaybackError(_) => EventKind::PlaybackError,
+            LoungeEvent::Unknown(_) => EventKind::Unknown,
+            LoungeEvent::Raw(_) => EventKind::Raw,
+        }
+    }
+}
+
+/// A set of [`EventKind`]s, e.g. `EventKind::NowPlaying | EventKind::PlaybackSession`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventKindSet(u32);
+
+impl EventKindSet {
+    pub const fn empty() -> Self {
+        EventKindSet(0)
+    }
+
+    pub const fn all() -> Self {
+        EventKindSet(u32::MAX)
+    }
+
+    pub const fn with(self, kind: EventKind) -> Self {
+        EventKindSet(self.0 | 1 << kind as u32)
+    }
+
+    pub const fn contains(self, kind: EventKind) -> bool {
+        self.0 & 1 << kind as u32 != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<EventKind> for EventKindSet {
+    fn from(kind: EventKind) -> Self {
+        EventKindSet::empty().with(kind)
+    }
+}
+
+impl FromIterator<EventKind> for EventKindSet {
+    fn from_iter<I: IntoIterator<Item = EventKind>>(kinds: I) -> Self {
+        kinds
+            .into_iter()
+            .fold(EventKindSet::empty(), EventKindSet::with)
+    }
+}
+
+impl BitOr for EventKind {
+    type Output = EventKindSet;
+
+    fn bitor(self, other: EventKind) -> EventKindSet {
+        EventKindSet::from(self).with(other)
+    }
+}
+
+impl BitOr<EventKind> for EventKindSet {
+    type Output = EventKindSet;
+
+    fn bitor(self, kind: EventKind) -> EventKindSet {
+        self.with(kind)
+    }
+}
+
+impl BitOr for EventKindSet {
+    type Output = EventKindSet;
+
+    fn bitor(self, other: EventKindSet) -> EventKindSet {
+        EventKindSet(self.0 | other.0)
+    }
+}
+
+/// Events of the kinds asked for with [`crate::LoungeClient::subscribe_filtered`],
+/// from a queue of their own. While the queue is full, further events for it
+/// are dropped and counted, without holding up other subscribers.
+#[derive(Debug)]
+pub struct FilteredEvents {
+    rx: mpsc::Receiver<LoungeEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl FilteredEvents {
+    /// The next event, or `None` once the client is gone.
+    pub async fn recv(&mut self) -> Option<LoungeEvent> {
+        self.rx.recv().await
+    }
+
+    /// An event already queued, without waiting.
+    pub fn try_recv(&mut self) -> Option<LoungeEvent> {
+        self.rx.try_recv().ok()
+    }
+
+    /// How many events were dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Stream for FilteredEvents {
+    type Item = LoungeEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<LoungeEvent>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+struct Subscription {
+    kinds: EventKindSet,
+    tx: mpsc::Sender<LoungeEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// The filtered subscriptions of one client. The router task starts with the
+/// first subscription.
+#[derive(Default)]
+pub(crate) struct EventRouter {
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+    started: AtomicBool,
+}
+
+impl EventRouter {
+    pub(crate) fn subscribe(
+        &self,
+        sender: &broadcast::Sender<LoungeEvent>,
+        kinds: EventKindSet,
+        capacity: usize,
+    ) -> FilteredEvents {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Subscription {
+                kinds,
+                tx,
+                dropped: dropped.clone(),
+            });
+        if !self.started.swap(true, Ordering::SeqCst) {
+            rt::spawn(route(sender.subscribe(), self.subscriptions.clone()));
+        }
+        FilteredEvents { rx, dropped }
+    }
+}
+
+/// Hand each event to the subscriptions wanting its kind, forgetting those
+/// whose receiver is gone. Never waits on a subscriber.
+async fn route(
+    mut rx: broadcast::Receiver<LoungeEvent>,
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+) {
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!(
+                    "Event router lagged behind event stream, skipped {} events",
+                    n
+                );
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let kind = event.kind();
+        subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscription| {
+                if !subscription.kinds.contains(kind) {
+                    return !subscription.tx.is_closed();
+                }
+                match subscription.tx.try_send(event.clone()) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(_)) => {
+                        trace!(?kind, "Filtered subscription full, dropping event");
+                        subscription.dropped.fetch_add(1, Ordering::Relaxed);
+                        true
+                    }
+                    Err(TrySendError::Closed(_)) => false,
+                }
+            });
+    }
+    // Ends every subscription's stream along with the client
+    subscriptions
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+    debug!("Event router stopped");
+}
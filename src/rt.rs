@@ -0,0 +1,156 @@
+// The runtime services the client needs (spawning tasks, timers and a clock),
+// so the rest of the crate doesn't depend on tokio's runtime directly.
+// Natively these are tokio's own; on wasm32 they run on the browser's event
+// loop, where tokio has no runtime and `std` has no clock.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use native::*;
+// `JoinHandle` and `JoinError` are part of the public API there
+#[cfg(target_arch = "wasm32")]
+pub use wasm::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::future::Future;
+    use std::time::Duration;
+
+    pub(crate) use std::time::Instant as StdInstant;
+    pub(crate) use tokio::task::{JoinError, JoinHandle};
+    pub(crate) use tokio::time::{sleep, sleep_until, timeout, Instant, Interval};
+
+    pub(crate) fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        tokio::spawn(future)
+    }
+
+    /// Ticks every `period` from `start`, delaying the schedule after a
+    /// missed tick rather than catching up in a burst.
+    pub(crate) fn interval_at(start: Instant, period: Duration) -> Interval {
+        let mut interval = tokio::time::interval_at(start, period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures::channel::oneshot;
+    use futures::future::{self, AbortHandle, Either};
+
+    #[cfg(not(feature = "wasm"))]
+    compile_error!("building for wasm32 requires the `wasm` feature");
+
+    pub(crate) use web_time::Instant;
+    pub(crate) use web_time::Instant as StdInstant;
+
+    /// A spawned task ended without finishing: it was aborted.
+    #[derive(Debug)]
+    pub struct JoinError;
+
+    impl std::fmt::Display for JoinError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("task was cancelled")
+        }
+    }
+
+    impl std::error::Error for JoinError {}
+
+    /// Handle to a task spawned on the browser's event loop. Awaiting it
+    /// yields the task's output; dropping it detaches the task.
+    #[derive(Debug)]
+    pub struct JoinHandle<T> {
+        output: oneshot::Receiver<T>,
+        abort: AbortHandle,
+    }
+
+    impl<T> JoinHandle<T> {
+        /// Stop the task at its next await point.
+        pub fn abort(&self) {
+            self.abort.abort();
+        }
+
+        pub fn abort_handle(&self) -> AbortHandle {
+            self.abort.clone()
+        }
+    }
+
+    impl<T> Future for JoinHandle<T> {
+        type Output = Result<T, JoinError>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            Pin::new(&mut self.output)
+                .poll(cx)
+                .map(|output| output.map_err(|_| JoinError))
+        }
+    }
+
+    pub(crate) fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let (output_tx, output) = oneshot::channel();
+        let (task, abort) = future::abortable(future);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Ok(value) = task.await {
+                let _ = output_tx.send(value);
+            }
+        });
+        JoinHandle { output, abort }
+    }
+
+    pub(crate) async fn sleep(duration: Duration) {
+        gloo_timers::future::sleep(duration).await;
+    }
+
+    pub(crate) async fn sleep_until(deadline: Instant) {
+        sleep(deadline.saturating_duration_since(Instant::now())).await;
+    }
+
+    /// The future passed to [`timeout`] didn't complete in time.
+    #[derive(Debug)]
+    pub(crate) struct Elapsed;
+
+    pub(crate) async fn timeout<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        let future = std::pin::pin!(future);
+        let deadline = std::pin::pin!(sleep(duration));
+        match future::select(future, deadline).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(Elapsed),
+        }
+    }
+
+    /// Ticks every `period` from `start`, delaying the schedule after a
+    /// missed tick rather than catching up in a burst.
+    pub(crate) struct Interval {
+        next: Instant,
+        period: Duration,
+    }
+
+    impl Interval {
+        pub(crate) async fn tick(&mut self) -> Instant {
+            sleep_until(self.next).await;
+            let now = Instant::now();
+            self.next = now + self.period;
+            now
+        }
+    }
+
+    pub(crate) fn interval_at(start: Instant, period: Duration) -> Interval {
+        Interval {
+            next: start,
+            period,
+        }
+    }
+}
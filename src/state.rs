@@ -1,9 +1,29 @@
-use std::sync::{atomic::AtomicU32, Arc};
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
 
-use crate::TokenCallback;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CommandResponseCallback, ErrorKind, LoungeError, Metrics, PollCycleCallback, TokenCallback,
+    TokenStore,
+};
 
 /// Represents the observable state of the background connection manager.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// A client starts `Disconnected`. `connect()` moves it to `Connecting`, then
+/// `Connected` once polling works. Recoverable errors go through
+/// `WaitingToReconnect` back to `Connecting`; unrecoverable ones end in
+/// `Failed`. `disconnect()` passes through `Stopping` to `Disconnected`.
+/// Observe transitions with [`crate::LoungeClient::state_receiver`].
+///
+/// Serializes to JSON (e.g. for IPC with a UI process) with the reconnect
+/// backoff in milliseconds: `{"WaitingToReconnect":{"backoff":1500}}`. The
+/// `retry_at` instant is local to this process and is not serialized; a
+/// deserialized state carries the time it was parsed. For the same reason it
+/// is ignored when comparing states.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConnectionState {
     /// Initial state or after explicit disconnection.
     Disconnected,
@@ -12,11 +32,76 @@ pub enum ConnectionState {
     /// Successfully bound and actively polling for events.
     Connected,
     /// A recoverable error occurred, waiting before retrying connection.
-    WaitingToReconnect { backoff: std::time::Duration },
+    WaitingToReconnect {
+        #[serde(with = "duration_millis")]
+        backoff: std::time::Duration,
+        /// When the next attempt is scheduled, i.e. when the backoff ends.
+        /// Useful for rendering a countdown.
+        #[serde(skip, default = "crate::utils::now")]
+        retry_at: crate::rt::StdInstant,
+    },
     /// An unrecoverable error occurred (e.g., invalid screen ID, repeated auth failures).
-    Failed(String), // Include an error message
+    Failed(Failure),
     /// The manager task is shutting down (e.g., after disconnect() or Drop).
     Stopping,
+    /// Paused by `go_idle()`: no polling or reconnecting, but the session is
+    /// kept for `resume_polling()`.
+    Idle,
+}
+
+/// Why a client is [`ConnectionState::Failed`]. Displays as its message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    pub message: String,
+    /// Category of the error behind the failure.
+    pub kind: ErrorKind,
+    /// Whether retrying can't help, see [`LoungeError::is_fatal`].
+    pub fatal: bool,
+}
+
+impl Failure {
+    /// A failure caused by `error`, described as `message`.
+    pub fn new(message: impl Into<String>, error: &LoungeError) -> Self {
+        Failure {
+            message: message.into(),
+            kind: error.kind(),
+            fatal: error.is_fatal(),
+        }
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl PartialEq for ConnectionState {
+    fn eq(&self, other: &Self) -> bool {
+        use ConnectionState::*;
+        match (self, other) {
+            (WaitingToReconnect { backoff: a, .. }, WaitingToReconnect { backoff: b, .. }) => {
+                a == b
+            }
+            (Failed(a), Failed(b)) => a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for ConnectionState {}
+
+mod duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub(super) fn serialize<S: Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_millis)
+    }
 }
 
 // Represents the outcome of a connection manager cycle (poll or bind attempt)
@@ -31,6 +116,26 @@ pub enum ConnectionStatus {
 pub(crate) struct InnerState {
     pub(crate) lounge_token: String,
     pub(crate) token_refresh_callback: TokenCallback,
+    pub(crate) poll_cycle_callback: PollCycleCallback,
+    pub(crate) command_response_callback: CommandResponseCallback,
+    pub(crate) token_store: Option<Arc<dyn TokenStore>>,
+    pub(crate) metrics: Option<Arc<dyn Metrics>>,
+}
+
+// Token refresh bookkeeping, held locked for the whole of a refresh
+#[derive(Debug, Default)]
+pub(crate) struct TokenRefreshState {
+    // When the last refresh succeeded
+    pub(crate) last_refresh: Option<crate::rt::Instant>,
+    // Refreshing is throttled by the server until then
+    pub(crate) throttled_until: Option<crate::rt::Instant>,
+}
+
+// What a token refresh request did about an expired token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenRefresh {
+    Refreshed, // A new token was fetched
+    Reused,    // A token refreshed within `min_token_refresh_interval` was kept
 }
 
 // Shared state representing the current session status
@@ -55,4 +160,67 @@ impl SessionState {
             command_offset: Arc::new(AtomicU32::new(0)),
         }
     }
+
+    pub(crate) fn debug_view(&self, aid: u32, connected: bool) -> SessionDebug {
+        SessionDebug {
+            sid: self.sid.clone(),
+            gsessionid: self.gsessionid.clone(),
+            rid: self.rid.load(Ordering::SeqCst),
+            aid,
+            command_offset: self.command_offset.load(Ordering::SeqCst),
+            connected,
+        }
+    }
+}
+
+impl SessionState {
+    /// The identifiers and counters needed to resume polling, if bound.
+    pub(crate) fn snapshot(&self, aid: u32) -> Option<SessionSnapshot> {
+        Some(SessionSnapshot {
+            sid: self.sid.clone()?,
+            gsessionid: self.gsessionid.clone()?,
+            rid: self.rid.load(Ordering::SeqCst),
+            aid,
+            command_offset: self.command_offset.load(Ordering::SeqCst),
+        })
+    }
+
+    /// Rebuild the session from a snapshot taken by [`Self::snapshot`].
+    pub(crate) fn from_snapshot(snapshot: &SessionSnapshot) -> Self {
+        Self {
+            sid: Some(snapshot.sid.clone()),
+            gsessionid: Some(snapshot.gsessionid.clone()),
+            rid: Arc::new(AtomicU32::new(snapshot.rid)),
+            command_offset: Arc::new(AtomicU32::new(snapshot.command_offset)),
+        }
+    }
+}
+
+/// A bound session that can be persisted and later resumed with
+/// `LoungeClient::connect_with_snapshot`, e.g. across a process restart.
+///
+/// The stored `aid` is the last event already processed: it is sent with the
+/// first poll and events at or below it are skipped if the server replays them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub sid: String,
+    pub gsessionid: String,
+    pub rid: u32,
+    pub aid: u32,
+    pub command_offset: u32,
+}
+
+/// Read-only snapshot of the session's protocol counters, for debugging.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionDebug {
+    pub sid: Option<String>,
+    pub gsessionid: Option<String>,
+    /// Request ID the next bind request will use.
+    pub rid: u32,
+    /// ID of the last event received.
+    pub aid: u32,
+    /// Offset the next command will use.
+    pub command_offset: u32,
+    /// Whether the connection manager is in the `Connected` state.
+    pub connected: bool,
 }
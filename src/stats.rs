@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::models::PlaybackState;
+use crate::rt::StdInstant as Instant;
+use crate::PlaybackStatus;
+
+/// Summary of one long-poll cycle, reported when the cycle ends.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PollCycleStats {
+    /// Sequence number of the cycle, starting at 1 for each connection manager.
+    pub cycle: u64,
+    /// Number of protocol messages decoded from the response stream.
+    pub messages_decoded: usize,
+    /// Raw bytes received from the response stream.
+    pub bytes_received: usize,
+    /// Largest capacity in bytes the receive buffer reached during the cycle.
+    pub buffer_capacity: usize,
+    /// Wall time from sending the poll request to the cycle ending.
+    pub elapsed: Duration,
+    /// Why the cycle ended.
+    pub end_reason: Option<PollEndReason>,
+}
+
+/// Why a long-poll cycle ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollEndReason {
+    /// The server finished the response (the normal end of a long poll).
+    ServerClosed,
+    /// No data arrived within the inactivity timeout, so the poll was abandoned.
+    InactivityTimeout,
+    /// The HTTP request timed out while reading the response.
+    StreamTimeout,
+    /// The server rejected the session (HTTP 400/404/410).
+    SessionInvalidated,
+    /// The lounge token was rejected (HTTP 401).
+    TokenExpired,
+    /// The client is shutting down.
+    Shutdown,
+    /// Any other request, status or decoding error.
+    Error,
+}
+
+/// Long-poll cycle boundaries, delivered to the callback registered with
+/// [`crate::LoungeClient::set_poll_cycle_callback`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PollCycleEvent {
+    Started { cycle: u64 },
+    Finished(PollCycleStats),
+}
+
+/// Rolling byte rate over a fixed time window, used to spot a stalled event stream.
+#[derive(Debug, Clone)]
+pub struct ThroughputMeter {
+    window: Duration,
+    since: Instant,
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl ThroughputMeter {
+    /// Window used for [`crate::LoungeClient::event_throughput`].
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+    pub fn new(window: Duration) -> Self {
+        Self::new_at(window, crate::utils::now())
+    }
+
+    /// Start measuring at `since`; the rate is averaged over at most the time since then.
+    pub fn new_at(window: Duration, since: Instant) -> Self {
+        Self {
+            window,
+            since,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, bytes: usize) {
+        self.record_at(crate::utils::now(), bytes);
+    }
+
+    pub fn record_at(&mut self, at: Instant, bytes: usize) {
+        self.samples.push_back((at, bytes));
+        self.prune(at);
+    }
+
+    /// Bytes per second over the window ending now.
+    pub fn rate(&self) -> Option<f64> {
+        self.rate_at(crate::utils::now())
+    }
+
+    /// Bytes per second over the window ending at `now`, or `None` if no time has
+    /// passed since measuring started.
+    pub fn rate_at(&self, now: Instant) -> Option<f64> {
+        let span = now.saturating_duration_since(self.since).min(self.window);
+        if span.is_zero() {
+            return None;
+        }
+        let window_start = now.checked_sub(self.window);
+        let bytes: usize = self
+            .samples
+            .iter()
+            .filter(|(at, _)| window_start.is_none_or(|start| *at > start) && *at <= now)
+            .map(|(_, bytes)| bytes)
+            .sum();
+        Some(bytes as f64 / span.as_secs_f64())
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let Some(window_start) = now.checked_sub(self.window) else {
+            return;
+        };
+        while self
+            .samples
+            .front()
+            .is_some_and(|(at, _)| *at <= window_start)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// Sizes of the messages decoded from the event stream, for tuning
+/// `STREAMING_BUFFER_CAPACITY`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageSizeStats {
+    pub count: u64,
+    /// Smallest message in bytes; 0 before the first.
+    pub min: usize,
+    pub max: usize,
+    pub total_bytes: u64,
+}
+
+impl MessageSizeStats {
+    pub fn record(&mut self, size: usize) {
+        self.min = if self.count == 0 {
+            size
+        } else {
+            self.min.min(size)
+        };
+        self.max = self.max.max(size);
+        self.count += 1;
+        self.total_bytes += size as u64;
+    }
+
+    /// Mean message size in bytes, or `None` before the first message.
+    pub fn average(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total_bytes as f64 / self.count as f64)
+    }
+}
+
+/// Larger disagreements between reported and local elapsed time are taken as a
+/// seek rather than drift, and restart the measurement.
+const SEEK_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// Estimates how far the TV's reported playback position drifts from local time,
+/// by comparing both across `StateChange` events during uninterrupted playback.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSkewEstimator {
+    /// First sample of the current run: arrival time, cpn and reported position.
+    baseline: Option<(Instant, Option<String>, f64)>,
+    skew: Option<Duration>,
+}
+
+impl ClockSkewEstimator {
+    pub fn observe(&mut self, state: &PlaybackState) {
+        self.observe_at(crate::utils::now(), state);
+    }
+
+    pub fn observe_at(&mut self, at: Instant, state: &PlaybackState) {
+        let position = state.current_time.parse::<f64>().ok();
+        let (Some(position), PlaybackStatus::Playing) = (position, state.status()) else {
+            // Paused, buffering etc.: the position stops advancing with the clock
+            self.baseline = None;
+            return;
+        };
+        let Some((since, cpn, start_position)) = &self.baseline else {
+            self.baseline = Some((at, state.cpn.clone(), position));
+            return;
+        };
+        if *cpn != state.cpn {
+            self.baseline = Some((at, state.cpn.clone(), position));
+            return;
+        }
+
+        let local = at.saturating_duration_since(*since).as_secs_f64();
+        let reported = position - start_position;
+        let skew = (local - reported).abs();
+        if reported < 0.0 || skew > SEEK_THRESHOLD.as_secs_f64() {
+            self.baseline = Some((at, state.cpn.clone(), position));
+            return;
+        }
+        self.skew = Some(Duration::from_secs_f64(skew));
+    }
+
+    /// Magnitude of the difference between local elapsed time and the TV's
+    /// reported progress over the latest playback run, if measured yet.
+    pub fn estimate(&self) -> Option<Duration> {
+        self.skew
+    }
+}
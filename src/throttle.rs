@@ -0,0 +1,88 @@
+// Pacing of command requests, so bursts (e.g. from a volume knob) don't get
+// the session rejected by the server.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use std::time::Duration;
+
+use crate::rt::{sleep, sleep_until, Instant};
+
+use crate::PlaybackCommand;
+
+/// Shared by everything sending commands for one client. The intervals come
+/// from the config at each call, so only the bookkeeping lives here.
+#[derive(Default)]
+pub(crate) struct CommandThrottle {
+    // Earliest time the next command request may go out
+    next_slot: Mutex<Option<Instant>>,
+    // Latest command of each coalesced kind, while its window is open
+    pending: Mutex<HashMap<&'static str, PlaybackCommand>>,
+}
+
+impl CommandThrottle {
+    /// Wait for the next free slot, keeping requests at least `interval`
+    /// apart. Callers are served in the order they arrive.
+    pub(crate) async fn acquire(&self, interval: Duration) {
+        let slot = {
+            let mut next_slot = self
+                .next_slot
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let slot = next_slot.map_or_else(Instant::now, |next| next.max(Instant::now()));
+            *next_slot = Some(slot + interval);
+            slot
+        };
+        sleep_until(slot).await;
+    }
+
+    /// Coalesce `command` with others of its kind sent within `window`.
+    ///
+    /// The first command of a kind opens the window and, once it closes, gets
+    /// back whichever command of that kind was sent last, to send in its place.
+    /// Commands arriving while the window is open only replace the pending one
+    /// and get `None`.
+    pub(crate) async fn coalesce(
+        &self,
+        command: PlaybackCommand,
+        window: Duration,
+    ) -> Option<PlaybackCommand> {
+        let kind = command.name();
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(slot) = pending.get_mut(kind) {
+                *slot = command;
+                return None;
+            }
+            pending.insert(kind, command);
+        }
+        // Closes the window even if the caller gives up while waiting
+        let window_guard = WindowGuard {
+            pending: &self.pending,
+            kind,
+        };
+        sleep(window).await;
+        window_guard.close()
+    }
+}
+
+/// Removes a kind's pending command when its window closes.
+struct WindowGuard<'a> {
+    pending: &'a Mutex<HashMap<&'static str, PlaybackCommand>>,
+    kind: &'static str,
+}
+
+impl WindowGuard<'_> {
+    fn close(&self) -> Option<PlaybackCommand> {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(self.kind)
+    }
+}
+
+impl Drop for WindowGuard<'_> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,132 @@
+// Persistence of screen credentials, so refreshed lounge tokens survive restarts.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{LoungeError, Screen};
+
+/// What it takes to reconnect to a paired screen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredScreen {
+    pub screen_id: String,
+    pub lounge_token: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The remote's device ID; reusing it lets the screen recognise the remote.
+    #[serde(default)]
+    pub device_id: Option<String>,
+    #[serde(default)]
+    pub device_name: Option<String>,
+}
+
+impl StoredScreen {
+    /// Credentials of a freshly paired screen, for the client using `device_id`.
+    pub fn new(screen: &Screen, device_id: &str) -> Self {
+        Self {
+            screen_id: screen.screen_id.clone(),
+            lounge_token: screen.lounge_token.clone(),
+            name: screen.name.clone(),
+            device_id: Some(device_id.to_string()),
+            device_name: None,
+        }
+    }
+}
+
+/// Storage for screen credentials, keyed by screen ID.
+///
+/// A client with a store saves every token it refreshes there. Calls are
+/// synchronous and made from async code, so implementations should be quick.
+pub trait TokenStore: Send + Sync {
+    /// The stored credentials of a screen, or `None` if there are none.
+    fn load(&self, screen_id: &str) -> Result<Option<StoredScreen>, LoungeError>;
+    /// Store credentials, replacing any for the same screen.
+    fn save(&self, screen: &StoredScreen) -> Result<(), LoungeError>;
+    /// Forget a screen. Not an error if it wasn't stored.
+    fn delete(&self, screen_id: &str) -> Result<(), LoungeError>;
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct TokenFile {
+    #[serde(default)]
+    screens: BTreeMap<String, StoredScreen>,
+}
+
+/// Keeps all screens in one JSON file, `{"screens": {"<screen id>": {...}}}`.
+///
+/// The file is replaced atomically on every change, so a crash mid-write
+/// leaves the previous version intact. Writes from clients sharing one store
+/// are serialized; separate processes writing the same file are not.
+pub struct JsonFileTokenStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JsonFileTokenStore {
+    /// A store backed by `path`. The file is created on the first save.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every stored screen, ordered by screen ID.
+    pub fn screens(&self) -> Result<Vec<StoredScreen>, LoungeError> {
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(self.read()?.screens.into_values().collect())
+    }
+
+    fn read(&self) -> Result<TokenFile, LoungeError> {
+        match fs::read(&self.path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(TokenFile::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, file: &TokenFile) -> Result<(), LoungeError> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(file)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Apply `change` to the file, writing it back if `change` returns `true`.
+    fn update(&self, change: impl FnOnce(&mut TokenFile) -> bool) -> Result<(), LoungeError> {
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut file = self.read()?;
+        if change(&mut file) {
+            self.write(&file)?;
+        }
+        Ok(())
+    }
+}
+
+impl TokenStore for JsonFileTokenStore {
+    fn load(&self, screen_id: &str) -> Result<Option<StoredScreen>, LoungeError> {
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(self.read()?.screens.remove(screen_id))
+    }
+
+    fn save(&self, screen: &StoredScreen) -> Result<(), LoungeError> {
+        self.update(|file| {
+            file.screens
+                .insert(screen.screen_id.clone(), screen.clone());
+            true
+        })
+    }
+
+    fn delete(&self, screen_id: &str) -> Result<(), LoungeError> {
+        self.update(|file| file.screens.remove(screen_id).is_some())
+    }
+}
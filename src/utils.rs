@@ -1,7 +1,11 @@
+use bytes::BytesMut;
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde_json::Value;
+use tokio_util::codec::Decoder;
+use tracing::debug;
 
-use crate::LoungeError;
+use crate::{LoungeCodec, LoungeError};
 
 // Helper module for parsing YouTube's string values
 pub mod youtube_parse {
@@ -10,6 +14,12 @@ pub mod youtube_parse {
         s.parse::<f64>().unwrap_or(0.0)
     }
 
+    /// Like [`parse_float`], but `None` when the value is empty or malformed
+    /// rather than collapsing it to 0.
+    pub fn parse_float_opt(s: &str) -> Option<f64> {
+        s.trim().parse::<f64>().ok()
+    }
+
     pub fn parse_int(s: &str) -> i32 {
         s.parse::<i32>().unwrap_or(0)
     }
@@ -28,13 +38,71 @@ pub mod youtube_parse {
     pub fn parse_list(s: &str) -> Vec<String> {
         s.split(',').map(|s| s.trim().to_string()).collect()
     }
+
+    /// A list of integers such as `"[0,1080,720]"`, brackets optional.
+    /// Entries that aren't numeric are skipped.
+    pub fn parse_int_list(s: &str) -> Vec<i32> {
+        s.trim_matches(|c| c == '[' || c == ']')
+            .split(',')
+            .filter_map(|item| item.trim().parse().ok())
+            .collect()
+    }
 }
 
 lazy_static! {
     static ref SID_RE: Regex = Regex::new(r#"\["c","([^"]*)""#).unwrap();
     static ref GSESSIONID_RE: Regex = Regex::new(r#"\["S","([^"]*)""#).unwrap();
 }
+
+/// Extract the SID (`["c", ...]`) and gsessionid (`["S", ...]`) from a bind response.
+///
+/// The body is decoded into messages and searched structurally for the frames,
+/// wherever they are nested. The substring search is only a fallback for bodies
+/// that don't decode as length-prefixed JSON.
 pub fn extract_session_ids(body: &[u8]) -> Result<(Option<String>, Option<String>), LoungeError> {
+    let (sid, gsessionid) = match extract_session_ids_structured(body) {
+        (Some(sid), Some(gsessionid)) => (Some(sid), Some(gsessionid)),
+        _ => {
+            debug!("Structured session ID extraction failed, falling back to marker search");
+            extract_session_ids_by_marker(body)
+        }
+    };
+    match (sid, gsessionid) {
+        (Some(sid), Some(gsessionid)) => Ok((Some(sid), Some(gsessionid))),
+        _ => Err(LoungeError::session_id_extraction_failed(body)),
+    }
+}
+
+fn extract_session_ids_structured(body: &[u8]) -> (Option<String>, Option<String>) {
+    fn find_frames(value: &Value, sid: &mut Option<String>, gsessionid: &mut Option<String>) {
+        let Some(items) = value.as_array() else {
+            return;
+        };
+        match (
+            items.first().and_then(Value::as_str),
+            items.get(1).and_then(Value::as_str),
+        ) {
+            (Some("c"), Some(id)) if sid.is_none() => *sid = Some(id.to_string()),
+            (Some("S"), Some(id)) if gsessionid.is_none() => *gsessionid = Some(id.to_string()),
+            _ => items
+                .iter()
+                .for_each(|item| find_frames(item, sid, gsessionid)),
+        }
+    }
+
+    let mut codec = LoungeCodec::new();
+    let mut buffer = BytesMut::from(body);
+    let mut sid = None;
+    let mut gsessionid = None;
+    while let Ok(Some(message)) = codec.decode(&mut buffer) {
+        if let Ok(value) = serde_json::from_str::<Value>(&message) {
+            find_frames(&value, &mut sid, &mut gsessionid);
+        }
+    }
+    (sid, gsessionid)
+}
+
+fn extract_session_ids_by_marker(body: &[u8]) -> (Option<String>, Option<String>) {
     let full_response = String::from_utf8_lossy(body);
     let sid = SID_RE
         .captures(&full_response)
@@ -42,10 +110,25 @@ pub fn extract_session_ids(body: &[u8]) -> Result<(Option<String>, Option<String
     let gsessionid = GSESSIONID_RE
         .captures(&full_response)
         .and_then(|cap| cap.get(1).map(|m| m.as_str().to_string()));
-    match (sid, gsessionid) {
-        (Some(sid), Some(gsessionid)) => Ok((Some(sid), Some(gsessionid))),
-        _ => Err(LoungeError::InvalidResponse(
-            "Failed to obtain session IDs".to_string(),
-        )),
-    }
+    (sid, gsessionid)
+}
+
+/// 64-bit FNV-1a hash; deterministic across runs and Rust versions, unlike `DefaultHasher`.
+pub(crate) fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The current time, read through `tokio::time` so a paused test clock
+/// (`tokio::time::pause`) governs timestamps and ages exposed as `std` instants.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> std::time::Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+/// The current time, from the browser's clock as `std` has none on wasm32.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> web_time::Instant {
+    web_time::Instant::now()
 }
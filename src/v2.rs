@@ -0,0 +1,300 @@
+//! Event models with typed fields.
+//!
+//! The TV sends numbers and booleans as strings, which the top-level models keep
+//! as they arrive. The models here parse them during deserialization, so
+//! `current_time` is an `f64` and `muted` a `bool`. Each converts from its
+//! string-typed counterpart (`v2::VolumeChanged::from(&volume)`), so code can
+//! migrate one event at a time while [`crate::LoungeEvent`] still carries the
+//! original models.
+
+use serde::{Deserialize, Deserializer};
+
+use crate::events::PlaybackStatus;
+use crate::models;
+use crate::youtube_parse;
+
+/// Deserializers accepting a value either as sent by the TV (a string) or as
+/// a plain JSON number or boolean.
+mod de {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Str(String),
+        Num(serde_json::Number),
+        Bool(bool),
+    }
+
+    impl Raw {
+        fn into_string(self) -> String {
+            match self {
+                Raw::Str(s) => s,
+                Raw::Num(n) => n.to_string(),
+                Raw::Bool(b) => b.to_string(),
+            }
+        }
+    }
+
+    fn raw<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        Ok(Raw::deserialize(deserializer)?.into_string())
+    }
+
+    pub fn string_as_f64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(youtube_parse::parse_float(raw(deserializer)?.trim()))
+    }
+
+    pub fn string_as_opt_f64<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<f64>, D::Error> {
+        Ok(youtube_parse::parse_float_opt(&raw(deserializer)?))
+    }
+
+    pub fn string_as_i32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+        Ok(youtube_parse::parse_int(raw(deserializer)?.trim()))
+    }
+
+    pub fn string_as_opt_i32<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<i32>, D::Error> {
+        Ok(raw(deserializer)?.trim().parse().ok())
+    }
+
+    pub fn string_as_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        Ok(youtube_parse::parse_bool(&raw(deserializer)?))
+    }
+
+    pub fn string_as_status<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PlaybackStatus, D::Error> {
+        Ok(PlaybackStatus::from(raw(deserializer)?.as_str()))
+    }
+
+    pub fn quality_levels<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<i32>, D::Error> {
+        Ok(youtube_parse::parse_int_list(&raw(deserializer)?))
+    }
+}
+
+fn default_status() -> PlaybackStatus {
+    PlaybackStatus::Stopped
+}
+
+/// Typed [`crate::PlaybackState`] (`onStateChange`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PlaybackState {
+    #[serde(
+        rename = "currentTime",
+        default,
+        deserialize_with = "de::string_as_f64"
+    )]
+    pub current_time: f64,
+    #[serde(default = "default_status", deserialize_with = "de::string_as_status")]
+    pub state: PlaybackStatus,
+    #[serde(default, deserialize_with = "de::string_as_f64")]
+    pub duration: f64,
+    #[serde(default)]
+    pub cpn: Option<String>,
+    #[serde(rename = "loadedTime", default, deserialize_with = "de::string_as_f64")]
+    pub loaded_time: f64,
+}
+
+impl From<&models::PlaybackState> for PlaybackState {
+    fn from(state: &models::PlaybackState) -> Self {
+        Self {
+            current_time: youtube_parse::parse_float(&state.current_time),
+            state: state.status(),
+            duration: youtube_parse::parse_float(&state.duration),
+            cpn: state.cpn.clone(),
+            loaded_time: youtube_parse::parse_float(&state.loaded_time),
+        }
+    }
+}
+
+/// Typed [`crate::NowPlaying`] (`nowPlaying`), without video data and chapters.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NowPlaying {
+    #[serde(rename = "videoId", default)]
+    pub video_id: String,
+    #[serde(
+        rename = "currentTime",
+        default,
+        deserialize_with = "de::string_as_f64"
+    )]
+    pub current_time: f64,
+    #[serde(default = "default_status", deserialize_with = "de::string_as_status")]
+    pub state: PlaybackStatus,
+    #[serde(default)]
+    pub cpn: Option<String>,
+    #[serde(rename = "listId", default)]
+    pub list_id: Option<String>,
+    /// `None` when the TV did not report a duration; live streams report 0.
+    #[serde(default, deserialize_with = "de::string_as_opt_f64")]
+    pub duration: Option<f64>,
+    #[serde(
+        rename = "loadedTime",
+        default,
+        deserialize_with = "de::string_as_opt_f64"
+    )]
+    pub loaded_time: Option<f64>,
+    #[serde(
+        rename = "seekableStartTime",
+        default,
+        deserialize_with = "de::string_as_opt_f64"
+    )]
+    pub seekable_start_time: Option<f64>,
+    #[serde(
+        rename = "seekableEndTime",
+        default,
+        deserialize_with = "de::string_as_opt_f64"
+    )]
+    pub seekable_end_time: Option<f64>,
+}
+
+impl From<&models::NowPlaying> for NowPlaying {
+    fn from(np: &models::NowPlaying) -> Self {
+        Self {
+            video_id: np.video_id.clone(),
+            current_time: youtube_parse::parse_float(&np.current_time),
+            state: np.status(),
+            cpn: np.cpn.clone(),
+            list_id: np.list_id.clone(),
+            duration: np.duration_opt(),
+            loaded_time: youtube_parse::parse_float_opt(&np.loaded_time),
+            seekable_start_time: np.seekable_start_time_opt(),
+            seekable_end_time: np.seekable_end_time_opt(),
+        }
+    }
+}
+
+/// Typed [`crate::VolumeChanged`] (`onVolumeChanged`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct VolumeChanged {
+    #[serde(deserialize_with = "de::string_as_bool")]
+    pub muted: bool,
+    #[serde(deserialize_with = "de::string_as_i32")]
+    pub volume: i32,
+}
+
+impl From<&models::VolumeChanged> for VolumeChanged {
+    fn from(volume: &models::VolumeChanged) -> Self {
+        Self {
+            muted: volume.is_muted(),
+            volume: volume.volume_level(),
+        }
+    }
+}
+
+/// Typed [`crate::SeekableRange`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SeekableRange {
+    #[serde(rename = "seekableStartTime", deserialize_with = "de::string_as_f64")]
+    pub seekable_start_time: f64,
+    #[serde(rename = "seekableEndTime", deserialize_with = "de::string_as_f64")]
+    pub seekable_end_time: f64,
+}
+
+impl From<&models::SeekableRange> for SeekableRange {
+    fn from(range: &models::SeekableRange) -> Self {
+        Self {
+            seekable_start_time: range.start(),
+            seekable_end_time: range.end(),
+        }
+    }
+}
+
+/// Typed [`crate::AdState`] (`onAdStateChange`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AdState {
+    #[serde(rename = "adState", deserialize_with = "de::string_as_i32")]
+    pub ad_state: i32,
+    #[serde(rename = "contentVideoId", default)]
+    pub content_video_id: Option<String>,
+    #[serde(rename = "currentTime", deserialize_with = "de::string_as_f64")]
+    pub current_time: f64,
+    #[serde(rename = "isSkipEnabled", deserialize_with = "de::string_as_bool")]
+    pub is_skip_enabled: bool,
+}
+
+impl From<&models::AdState> for AdState {
+    fn from(ad: &models::AdState) -> Self {
+        Self {
+            ad_state: youtube_parse::parse_int(&ad.ad_state),
+            content_video_id: ad.content_video_id.clone(),
+            current_time: youtube_parse::parse_float(&ad.current_time),
+            is_skip_enabled: ad.is_skippable(),
+        }
+    }
+}
+
+/// Typed [`crate::HasPreviousNextChanged`] (`onHasPreviousNextChanged`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct HasPreviousNextChanged {
+    #[serde(rename = "hasNext", deserialize_with = "de::string_as_bool")]
+    pub has_next: bool,
+    #[serde(rename = "hasPrevious", deserialize_with = "de::string_as_bool")]
+    pub has_previous: bool,
+}
+
+impl From<&models::HasPreviousNextChanged> for HasPreviousNextChanged {
+    fn from(change: &models::HasPreviousNextChanged) -> Self {
+        Self {
+            has_next: change.has_next(),
+            has_previous: change.has_previous(),
+        }
+    }
+}
+
+/// Typed [`crate::VideoQualityChanged`] (`onVideoQualityChanged`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VideoQualityChanged {
+    #[serde(
+        rename = "availableQualityLevels",
+        deserialize_with = "de::quality_levels"
+    )]
+    pub available_quality_levels: Vec<i32>,
+    /// `None` for a level that isn't numeric, e.g. `auto`.
+    #[serde(rename = "qualityLevel", deserialize_with = "de::string_as_opt_i32")]
+    pub quality_level: Option<i32>,
+    #[serde(rename = "videoId")]
+    pub video_id: String,
+}
+
+impl From<&models::VideoQualityChanged> for VideoQualityChanged {
+    fn from(quality: &models::VideoQualityChanged) -> Self {
+        Self {
+            available_quality_levels: youtube_parse::parse_int_list(
+                &quality.available_quality_levels,
+            ),
+            quality_level: quality.quality_level.trim().parse().ok(),
+            video_id: quality.video_id.clone(),
+        }
+    }
+}
+
+/// Typed [`crate::PlaylistModeChanged`] (`onPlaylistModeChanged`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct PlaylistModeChanged {
+    #[serde(
+        rename = "loopEnabled",
+        default,
+        deserialize_with = "de::string_as_bool"
+    )]
+    pub loop_enabled: bool,
+    #[serde(
+        rename = "shuffleEnabled",
+        default,
+        deserialize_with = "de::string_as_bool"
+    )]
+    pub shuffle_enabled: bool,
+}
+
+impl From<&models::PlaylistModeChanged> for PlaylistModeChanged {
+    fn from(mode: &models::PlaylistModeChanged) -> Self {
+        Self {
+            loop_enabled: mode.is_loop_enabled(),
+            shuffle_enabled: mode.is_shuffle_enabled(),
+        }
+    }
+}
@@ -0,0 +1,3135 @@
+mod common;
+
+use common::{default_response, frame, wait_for_connected, wait_until, MockResponse, MockServer};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing_test::traced_test;
+use youtube_lounge_rs::{
+    CommandOutcome, ConnectionState, DisconnectReason, DpadKey, ErrorKind, EventKind,
+    JsonFileTokenStore, LagPolicy, LoungeClient, LoungeConfig, LoungeError, LoungeEvent,
+    LoungeManager, Metrics, PlaybackCommand, PlaybackStatus, PlaylistModeChanged, PollCycleEvent,
+    PollCycleStats, PollEndReason, SessionSnapshot, StoredScreen, TokenStore, TrackSettings,
+    VolumeChanged,
+};
+
+#[tokio::test]
+async fn test_auto_skip_on_error_sends_single_next() {
+    // The same failure reported twice must only trigger one skip
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onError",{"videoId":"broken1","errorCode":"150"}]],[4,["onError",{"videoId":"broken1","errorCode":"150"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig {
+        auto_skip_on_error: true,
+        ..LoungeConfig::default()
+    });
+    let mut events = client.event_receiver();
+
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let error = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Ok(LoungeEvent::PlaybackError(error)) = events.recv().await {
+                return error;
+            }
+        }
+    })
+    .await
+    .expect("no PlaybackError event received");
+    assert_eq!(error.video_id.as_deref(), Some("broken1"));
+    assert_eq!(error.error_code, "150");
+
+    assert!(wait_until(|| !server.commands().is_empty()).await);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(server.commands(), vec!["next"]);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_poll_cycle_boundaries_are_observable() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string(),
+        r#"[[4,["onAutoplayModeChanged",{"autoplayMode":"ENABLED"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let cycles = Arc::new(Mutex::new(Vec::new()));
+    let recorded = cycles.clone();
+    client
+        .set_poll_cycle_callback(move |event| recorded.lock().unwrap().push(event.clone()))
+        .await;
+
+    client.connect().await.unwrap();
+    // First cycle delivers the events and ends, the second one idles
+    assert!(wait_until(|| cycles.lock().unwrap().len() >= 3).await);
+    client.disconnect().await.unwrap();
+
+    let cycles = cycles.lock().unwrap().clone();
+    assert_eq!(cycles[0], PollCycleEvent::Started { cycle: 1 });
+    match &cycles[1] {
+        PollCycleEvent::Finished(stats) => {
+            assert_eq!(stats.cycle, 1);
+            assert_eq!(stats.messages_decoded, 2);
+            assert!(stats.bytes_received > 0);
+        }
+        other => panic!("Expected Finished, got {:?}", other),
+    }
+    assert_eq!(cycles[2], PollCycleEvent::Started { cycle: 2 });
+
+    assert!(logs_contain("Poll cycle finished"));
+    assert!(logs_contain("messages=2"));
+}
+
+#[tokio::test]
+async fn test_play_playlist_from_video_sends_list_and_video() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    client
+        .play_playlist_from_video("PLtest".to_string(), "dQw4w9WgXcQ".to_string())
+        .await
+        .unwrap();
+    client.disconnect().await.unwrap();
+
+    let request = server
+        .requests()
+        .into_iter()
+        .find(|r| r.command().as_deref() == Some("setPlaylist"))
+        .expect("setPlaylist was not sent");
+    assert_eq!(request.form_param("req0_listId").as_deref(), Some("PLtest"));
+    assert_eq!(
+        request.form_param("req0_videoId").as_deref(),
+        Some("dQw4w9WgXcQ")
+    );
+    assert_eq!(request.form_param("req0_currentIndex"), None);
+}
+
+#[tokio::test]
+async fn test_state_change_callback_reports_transitions() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    let transitions = Arc::new(Mutex::new(Vec::new()));
+    let recorded = transitions.clone();
+    client.on_state_change(move |old, new| {
+        recorded
+            .lock()
+            .unwrap()
+            .push((format!("{:?}", old), format!("{:?}", new)));
+    });
+
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    client.disconnect().await.unwrap();
+    assert!(
+        wait_until(|| transitions
+            .lock()
+            .unwrap()
+            .last()
+            .is_some_and(|(_, new)| new == "Disconnected"))
+        .await
+    );
+
+    let transitions = transitions.lock().unwrap().clone();
+    assert_eq!(transitions[0].0, "Disconnected");
+    assert!(transitions.iter().any(|(_, new)| new == "Connected"));
+    for pair in transitions.windows(2) {
+        assert_eq!(pair[0].1, pair[1].0, "transitions must chain: {:?}", pair);
+    }
+    for (old, new) in &transitions {
+        assert_ne!(old, new);
+    }
+}
+
+#[tokio::test]
+async fn test_error_response_headers_are_captured() {
+    let server = MockServer::start(|req| {
+        if req.command().is_some() {
+            MockResponse::status(403, "forbidden")
+                .with_header("Retry-After", "30")
+                .with_header("X-Goog-Request-Id", "abc123")
+                .with_header("X-Unrelated", "ignored")
+        } else if req.is_poll() {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    assert!(client.last_error_response().is_none());
+
+    let result = client.play().await;
+    assert!(matches!(result, Err(LoungeError::InvalidResponse(_))));
+
+    let diagnostics = client
+        .last_error_response()
+        .expect("diagnostics should be captured");
+    assert_eq!(diagnostics.status, 403);
+    assert_eq!(diagnostics.header("retry-after"), Some("30"));
+    assert_eq!(diagnostics.header("x-goog-request-id"), Some("abc123"));
+    assert_eq!(diagnostics.header("x-unrelated"), None);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_play_and_watch_streams_sessions_until_stopped() {
+    let events = [
+        r#"[[3,["nowPlaying",{"videoId":"watched","cpn":"cpnA","state":"1","currentTime":"1.0","duration":"200.0","loadedTime":"5.0"}]]]"#,
+        r#"[[4,["onStateChange",{"cpn":"cpnA","state":"1","currentTime":"2.0","duration":"200.0","loadedTime":"6.0"}]]]"#,
+        r#"[[5,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#,
+        r#"[[6,["nowPlaying",{"state":"-1"}]]]"#,
+    ]
+    .iter()
+    .map(|e| common::frame(e))
+    .collect::<String>();
+    let played = AtomicBool::new(false);
+    let served = AtomicBool::new(false);
+    let server = MockServer::start(move |req| {
+        if req.command().as_deref() == Some("setPlaylist") {
+            played.store(true, Ordering::SeqCst);
+        }
+        if !req.is_poll() {
+            default_response(req)
+        } else if !played.load(Ordering::SeqCst) {
+            MockResponse::ok("").delayed(Duration::from_millis(20))
+        } else if !served.swap(true, Ordering::SeqCst) {
+            MockResponse::ok(events.clone())
+        } else {
+            MockResponse::hang()
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let stream = client.play_and_watch("watched".to_string()).await.unwrap();
+    let sessions: Vec<_> = tokio::time::timeout(Duration::from_secs(5), stream.collect())
+        .await
+        .expect("stream did not end");
+    client.disconnect().await.unwrap();
+
+    assert_eq!(sessions.len(), 3);
+    assert!(sessions.iter().all(|s| s.video_id == "watched"));
+    assert!(sessions.iter().all(|s| s.cpn.as_deref() == Some("cpnA")));
+    assert_eq!(sessions[1].current_time, 2.0);
+    assert_eq!(sessions[2].status(), PlaybackStatus::Stopped);
+}
+
+#[tokio::test]
+async fn test_session_ids_extracted_from_varied_bind_frames() {
+    let bodies = [
+        // Current layout
+        common::bind_body("sid-current", "gsid-current"),
+        // Whitespace and nesting the marker search can't handle
+        common::frame(r#"[[0, [ "c" , "sid-nested", "", 8 ]], [1, [[ "S", "gsid-nested" ]]]]"#),
+        // Not length-prefixed at all: only the marker fallback finds the IDs
+        r#"garbage [[0,["c","sid-raw","",8]],[1,["S","gsid-raw"]]]"#.to_string(),
+    ];
+    let expected = [
+        ("sid-current", "gsid-current"),
+        ("sid-nested", "gsid-nested"),
+        ("sid-raw", "gsid-raw"),
+    ];
+
+    for (body, (sid, gsessionid)) in bodies.into_iter().zip(expected) {
+        let server = MockServer::start(move |req| {
+            if req.is_poll() {
+                MockResponse::hang()
+            } else if req.method == "POST" && req.query_param("SID").is_none() {
+                MockResponse::ok(body.clone())
+            } else {
+                default_response(req)
+            }
+        })
+        .await;
+        let client = server.client(LoungeConfig::default());
+        client.connect().await.unwrap();
+        assert!(wait_until(|| server.requests().iter().any(|r| r.is_poll())).await);
+        client.disconnect().await.unwrap();
+
+        let poll = server.requests().into_iter().find(|r| r.is_poll()).unwrap();
+        assert_eq!(poll.query_param("SID"), Some(sid));
+        assert_eq!(poll.query_param("gsessionid"), Some(gsessionid));
+    }
+}
+
+#[tokio::test]
+async fn test_disconnect_reason_sent_in_terminate() {
+    let cases = [
+        (None, "MDX_SESSION_DISCONNECT_REASON_DISCONNECTED_BY_USER"),
+        (
+            Some(DisconnectReason::ConnectedToAnotherScreen),
+            "MDX_SESSION_DISCONNECT_REASON_CONNECTED_TO_ANOTHER_SCREEN",
+        ),
+        (
+            Some(DisconnectReason::Custom("MDX_CUSTOM".to_string())),
+            "MDX_CUSTOM",
+        ),
+    ];
+    for (reason, expected) in cases {
+        let server = MockServer::lounge(vec![]).await;
+        let client = server.client(LoungeConfig::default());
+        client.connect().await.unwrap();
+        wait_for_connected(&client).await;
+        match reason {
+            Some(reason) => client.disconnect_with_reason(reason).await.unwrap(),
+            None => client.disconnect().await.unwrap(),
+        }
+
+        let terminate = server
+            .requests()
+            .into_iter()
+            .find(|r| r.form_param("TYPE").as_deref() == Some("terminate"))
+            .expect("terminate request not sent");
+        assert_eq!(
+            terminate.form_param("clientDisconnectReason").as_deref(),
+            Some(expected)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_is_playing_ad_tracks_ad_and_status_events() {
+    let ad_events = vec![
+        r#"[[3,["onStateChange",{"state":"1081","currentTime":"0"}]],[4,["onAdStateChange",{"adState":"1","currentTime":"0","isSkipEnabled":"false"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(ad_events).await;
+    let client = server.client(LoungeConfig::default());
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+    while !matches!(rx.recv().await.unwrap(), LoungeEvent::AdStateChange(_)) {}
+    assert!(client.is_playing_ad());
+    assert_eq!(
+        client.player_state().status,
+        Some(PlaybackStatus::Advertisement)
+    );
+    client.disconnect().await.unwrap();
+
+    let resumed_events = vec![
+        r#"[[3,["onAdStateChange",{"adState":"1","currentTime":"0","isSkipEnabled":"false"}]],[4,["onStateChange",{"state":"1","currentTime":"5"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(resumed_events).await;
+    let client = server.client(LoungeConfig::default());
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+    while !matches!(rx.recv().await.unwrap(), LoungeEvent::StateChange(_)) {}
+    assert!(!client.is_playing_ad());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_refresh_distinguishes_expired_token_from_unpaired_screen() {
+    // Binds are rejected until the client presents the refreshed token
+    async fn server_with_batch(batch: &'static str) -> MockServer {
+        MockServer::start(move |req| {
+            if req.path.ends_with("/pairing/get_lounge_token_batch") {
+                return MockResponse::ok(batch);
+            }
+            let is_initial_bind = req.method == "POST"
+                && req.path.ends_with("/bc/bind")
+                && req.query_param("SID").is_none();
+            if is_initial_bind && req.form_param("loungeIdToken").as_deref() != Some("fresh-token")
+            {
+                return MockResponse::status(401, "Unauthorized");
+            }
+            if req.is_poll() {
+                return MockResponse::hang();
+            }
+            default_response(req)
+        })
+        .await
+    }
+
+    let server = server_with_batch(
+        r#"{"screens":[{"screenId":"mock-screen","loungeToken":"fresh-token","expiration":0}]}"#,
+    )
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect_with_refresh().await.unwrap();
+    wait_for_connected(&client).await;
+    client.disconnect().await.unwrap();
+
+    let server = server_with_batch(r#"{"screens":[]}"#).await;
+    let client = server.client(LoungeConfig::default());
+    let err = client.connect_with_refresh().await.unwrap_err();
+    assert!(
+        matches!(err, LoungeError::ScreenUnpaired),
+        "unexpected error: {:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_duplicate_playback_sessions_are_merged() {
+    let events = vec![
+        r#"[[3,["nowPlaying",{"videoId":"dQw4w9WgXcQ","currentTime":"10","duration":"100","state":"1","cpn":"cpn-1","loadedTime":"20"}]],[4,["onStateChange",{"currentTime":"10","duration":"100","state":"1","cpn":"cpn-1","loadedTime":"20"}]],[5,["onVolumeChanged",{"volume":"50","muted":"false"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig::default());
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+
+    let mut sessions = 0;
+    loop {
+        match rx.recv().await.unwrap() {
+            LoungeEvent::PlaybackSession(_) => sessions += 1,
+            LoungeEvent::VolumeChanged(_) => break,
+            _ => {}
+        }
+    }
+    assert_eq!(sessions, 1);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_partial_sessions_emitted_when_enabled() {
+    let events = vec![
+        r#"[[3,["onStateChange",{"currentTime":"10","duration":"100","state":"1","cpn":"cpn-1","loadedTime":"20"}]],[4,["onVolumeChanged",{"volume":"50","muted":"false"}]]]"#.to_string(),
+    ];
+    let sessions_with = |emit_partial_sessions: bool| {
+        let events = events.clone();
+        async move {
+            let server = MockServer::lounge(events).await;
+            let client = server.client(LoungeConfig {
+                emit_partial_sessions,
+                ..LoungeConfig::default()
+            });
+            let mut rx = client.event_receiver();
+            client.connect().await.unwrap();
+
+            let mut sessions = Vec::new();
+            loop {
+                match rx.recv().await.unwrap() {
+                    LoungeEvent::PlaybackSession(session) => sessions.push(session),
+                    LoungeEvent::VolumeChanged(_) => break,
+                    _ => {}
+                }
+            }
+            client.disconnect().await.unwrap();
+            sessions
+        }
+    };
+
+    assert!(sessions_with(false).await.is_empty());
+    let sessions = sessions_with(true).await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].cpn.as_deref(), Some("cpn-1"));
+    assert_eq!(sessions[0].current_time, 10.0);
+}
+
+#[tokio::test]
+async fn test_lounge_status_devices_shared_between_subscribers() {
+    let devices =
+        r#"[{\"app\":\"lb-v4\",\"name\":\"TV\",\"id\":\"tv-1\",\"type\":\"LOUNGE_SCREEN\"}]"#;
+    let events = vec![format!(
+        r#"[[3,["loungeStatus",{{"devices":"{}","queueId":"RQ1"}}]]]"#,
+        devices
+    )];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig::default());
+    let mut first = client.event_receiver();
+    let mut second = client.event_receiver();
+    client.connect().await.unwrap();
+
+    let mut received = Vec::new();
+    for rx in [&mut first, &mut second] {
+        loop {
+            if let LoungeEvent::LoungeStatus(devices, _) = rx.recv().await.unwrap() {
+                received.push(devices);
+                break;
+            }
+        }
+    }
+    assert_eq!(received[0].len(), 1);
+    assert!(Arc::ptr_eq(&received[0], &received[1]));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_initial_state_requested_after_bind() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig {
+        request_initial_state: true,
+        ..LoungeConfig::default()
+    });
+    client.connect().await.unwrap();
+
+    let expected = ["getNowPlaying", "getVolume", "getAutoplayMode"];
+    assert!(
+        wait_until(|| server.commands() == expected).await,
+        "unexpected commands: {:?}",
+        server.commands()
+    );
+    client.disconnect().await.unwrap();
+
+    // Off by default
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(server.commands().is_empty());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_aid_reset_keeps_events_flowing() {
+    let volume = |aid: u32, volume: u32| {
+        format!(
+            r#"[{},["onVolumeChanged",{{"volume":"{}","muted":"false"}}]]"#,
+            aid, volume
+        )
+    };
+    let polls = [
+        format!("[{}]", volume(500, 10)),
+        // Counter restarts at 1; the repeated AID 2 is a replay and is dropped
+        format!(
+            "[{},{},{},{}]",
+            volume(1, 20),
+            volume(2, 30),
+            volume(2, 99),
+            volume(3, 40)
+        ),
+    ];
+    let polls_served = Arc::new(Mutex::new(0usize));
+    let server = MockServer::start(move |req| {
+        if !req.is_poll() {
+            return default_response(req);
+        }
+        let mut served = polls_served.lock().unwrap();
+        match polls.get(*served) {
+            Some(events) => {
+                *served += 1;
+                MockResponse::ok(common::frame(events))
+            }
+            None => MockResponse::hang(),
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+
+    let mut volumes = Vec::new();
+    while volumes.len() < 4 {
+        if let LoungeEvent::VolumeChanged(change) = rx.recv().await.unwrap() {
+            volumes.push(change.volume);
+        }
+    }
+    assert_eq!(volumes, ["10", "20", "30", "40"]);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_command_batches_use_contiguous_offsets() {
+    // Reject any command request whose offset doesn't continue where the last one ended
+    let next_ofs = Arc::new(Mutex::new(0u32));
+    let server = MockServer::start(move |req| {
+        if req.command().is_none() {
+            return if req.is_poll() {
+                MockResponse::hang()
+            } else {
+                default_response(req)
+            };
+        }
+        let ofs: u32 = req.form_param("ofs").unwrap().parse().unwrap();
+        let count: u32 = req.form_param("count").unwrap().parse().unwrap();
+        let mut expected = next_ofs.lock().unwrap();
+        if ofs != *expected {
+            return MockResponse::status(400, "Unexpected offset");
+        }
+        *expected += count;
+        MockResponse::ok("")
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    client
+        .send_commands(vec![PlaybackCommand::Play, PlaybackCommand::Pause])
+        .await
+        .unwrap();
+    client
+        .send_commands(vec![
+            PlaybackCommand::add_video("dQw4w9WgXcQ".to_string()),
+            PlaybackCommand::SetVolume { volume: 30 },
+            PlaybackCommand::Next,
+        ])
+        .await
+        .unwrap();
+    client.send_command(PlaybackCommand::Play).await.unwrap();
+
+    let requests: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.command().is_some())
+        .collect();
+    let offsets: Vec<_> = requests
+        .iter()
+        .map(|r| r.form_param("ofs").unwrap())
+        .collect();
+    assert_eq!(offsets, ["0", "2", "5"]);
+    let second = &requests[1];
+    assert_eq!(second.form_param("count").as_deref(), Some("3"));
+    assert_eq!(second.form_param("req0__sc").as_deref(), Some("addVideo"));
+    assert_eq!(
+        second.form_param("req0_videoId").as_deref(),
+        Some("dQw4w9WgXcQ")
+    );
+    assert_eq!(second.form_param("req1__sc").as_deref(), Some("setVolume"));
+    assert_eq!(second.form_param("req1_volume").as_deref(), Some("30"));
+    assert_eq!(second.form_param("req2__sc").as_deref(), Some("next"));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_await_pending_commands() {
+    let server = MockServer::start(|req| {
+        if req.command().is_some() {
+            MockResponse::ok("").delayed(Duration::from_millis(200))
+        } else if req.is_poll() {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = Arc::new(server.client(LoungeConfig::default()));
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let tasks: Vec<_> = [
+        PlaybackCommand::Play,
+        PlaybackCommand::Pause,
+        PlaybackCommand::Next,
+    ]
+    .into_iter()
+    .map(|command| {
+        let client = client.clone();
+        tokio::spawn(async move { client.send_command(command).await })
+    })
+    .collect();
+    // All three requests are in flight, their responses still delayed
+    assert!(wait_until(|| server.commands().len() == 3).await);
+    assert!(tasks.iter().all(|task| !task.is_finished()));
+    assert_eq!(client.in_flight_commands(), 3);
+
+    client.await_pending_commands().await.unwrap();
+    assert_eq!(client.in_flight_commands(), 0);
+    for task in tasks {
+        // Each task only has to return its result by now
+        let result = tokio::time::timeout(Duration::from_millis(50), task).await;
+        result.expect("command still pending").unwrap().unwrap();
+    }
+    // Nothing pending resolves immediately
+    client.await_pending_commands().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_state_change_decomposed_into_volume_events() {
+    let state = |aid: u32, volume: u32| {
+        format!(
+            r#"[{},["onStateChange",{{"state":"1","currentTime":"{}","volume":"{}","muted":"false","seekableStartTime":"0","seekableEndTime":"100"}}]]"#,
+            aid, aid, volume
+        )
+    };
+    let events = vec![format!(
+        "[{},{},{},{}]",
+        state(3, 30),
+        state(4, 30),
+        state(5, 40),
+        r#"[6,["onAutoplayModeChanged",{"autoplayMode":"ENABLED"}]]"#
+    )];
+
+    for decompose in [true, false] {
+        let server = MockServer::lounge(events.clone()).await;
+        let client = server.client(LoungeConfig {
+            decompose_state_change: decompose,
+            ..LoungeConfig::default()
+        });
+        let mut rx = client.event_receiver();
+        client.connect().await.unwrap();
+
+        let mut volumes = Vec::new();
+        let mut ranges = 0;
+        loop {
+            match rx.recv().await.unwrap() {
+                LoungeEvent::VolumeChanged(change) => volumes.push(change.volume),
+                LoungeEvent::SeekableRangeChanged(_) => ranges += 1,
+                LoungeEvent::AutoplayModeChanged(_) => break,
+                _ => {}
+            }
+        }
+        if decompose {
+            // Only actual changes are emitted
+            assert_eq!(volumes, ["30", "40"]);
+            assert_eq!(ranges, 1);
+        } else {
+            assert!(volumes.is_empty());
+            assert_eq!(ranges, 0);
+        }
+        client.disconnect().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_debug_session_after_connect() {
+    let server = MockServer::lounge(vec![
+        r#"[[7,["onAutoplayModeChanged",{"autoplayMode":"ENABLED"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let before = client.debug_session().await;
+    assert_eq!(before.sid, None);
+    assert!(!before.connected);
+
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+    while !matches!(
+        rx.recv().await.unwrap(),
+        LoungeEvent::AutoplayModeChanged(_)
+    ) {}
+    wait_for_connected(&client).await;
+    client.send_command(PlaybackCommand::Play).await.unwrap();
+
+    let session = client.debug_session().await;
+    assert_eq!(session.sid.as_deref(), Some(common::SID));
+    assert_eq!(session.gsessionid.as_deref(), Some(common::GSESSIONID));
+    assert_eq!(session.aid, 7);
+    assert_eq!(session.command_offset, 1);
+    assert!(session.rid > 1);
+    assert!(session.connected);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_play_mix_sends_radio_params() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    client.play_mix("dQw4w9WgXcQ".to_string()).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let request = server
+        .requests()
+        .into_iter()
+        .find(|r| r.command().as_deref() == Some("setPlaylist"))
+        .expect("setPlaylist was not sent");
+    assert_eq!(
+        request.form_param("req0_videoId").as_deref(),
+        Some("dQw4w9WgXcQ")
+    );
+    assert_eq!(
+        request.form_param("req0_listId").as_deref(),
+        Some("RDdQw4w9WgXcQ")
+    );
+    assert_eq!(request.form_param("req0_params").as_deref(), Some("OAE="));
+    assert_eq!(
+        request.form_param("req0_currentIndex").as_deref(),
+        Some("0")
+    );
+}
+
+#[tokio::test]
+async fn test_move_in_queue_sends_video_and_index() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    client
+        .move_in_queue("dQw4w9WgXcQ".to_string(), 2)
+        .await
+        .unwrap();
+    client.disconnect().await.unwrap();
+
+    let request = server
+        .requests()
+        .into_iter()
+        .find(|r| r.command().as_deref() == Some("moveVideo"))
+        .expect("moveVideo was not sent");
+    assert_eq!(
+        request.form_param("req0_videoId").as_deref(),
+        Some("dQw4w9WgXcQ")
+    );
+    assert_eq!(request.form_param("req0_index").as_deref(), Some("2"));
+}
+
+#[tokio::test]
+async fn test_redirects_are_followed_for_bind_and_poll() {
+    let events = r#"[[3,["onAutoplayModeChanged",{"autoplayMode":"ENABLED"}]]]"#;
+    let first_poll_served = AtomicBool::new(false);
+    let server = MockServer::start(move |req| {
+        // Everything is answered by a "gateway" path the client is redirected to
+        let Some(path) = req.path.strip_prefix("/gateway") else {
+            let query = serde_urlencoded::to_string(&req.query).unwrap();
+            let status = if req.method == "POST" { 307 } else { 302 };
+            return MockResponse::status(status, "")
+                .with_header("Location", &format!("/gateway{}?{}", req.path, query));
+        };
+        let req = common::MockRequest {
+            path: path.to_string(),
+            ..req.clone()
+        };
+        if req.is_poll() {
+            if first_poll_served.swap(true, Ordering::SeqCst) {
+                MockResponse::hang()
+            } else {
+                MockResponse::ok(common::frame(events))
+            }
+        } else {
+            default_response(&req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+    while !matches!(
+        rx.recv().await.unwrap(),
+        LoungeEvent::AutoplayModeChanged(_)
+    ) {}
+    wait_for_connected(&client).await;
+    client.disconnect().await.unwrap();
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .any(|r| r.is_poll() && r.path.starts_with("/gateway")));
+    assert!(requests
+        .iter()
+        .any(|r| r.method == "POST" && r.path == "/gateway/api/lounge/bc/bind"));
+}
+
+#[tokio::test]
+async fn test_paused_automation_does_not_auto_skip() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onError",{"videoId":"broken1","errorCode":"150"}]],[4,["onAutoplayModeChanged",{"autoplayMode":"ENABLED"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig {
+        auto_skip_on_error: true,
+        ..LoungeConfig::default()
+    });
+    client.set_automation_enabled(false);
+    assert!(!client.automation_enabled());
+    let mut events = client.event_receiver();
+    client.connect().await.unwrap();
+
+    while !matches!(
+        events.recv().await.unwrap(),
+        LoungeEvent::AutoplayModeChanged(_)
+    ) {}
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(server.commands().is_empty());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_skip_forward_sends_ordered_next_commands() {
+    // Slow acknowledgements would let unserialized requests overtake each other
+    let server = MockServer::start(|req| {
+        if req.command().is_some() {
+            MockResponse::ok("").delayed(Duration::from_millis(30))
+        } else if req.is_poll() {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    client.skip_forward(3).await.unwrap();
+    client.skip_backward(1).await.unwrap();
+
+    let commands: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.command().is_some())
+        .map(|r| (r.command().unwrap(), r.form_param("ofs").unwrap()))
+        .collect();
+    assert_eq!(
+        commands,
+        [
+            ("next".to_string(), "0".to_string()),
+            ("next".to_string(), "1".to_string()),
+            ("next".to_string(), "2".to_string()),
+            ("previous".to_string(), "3".to_string()),
+        ]
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subtitles_track_list_parsed() {
+    let tracks = r#"[{\"languageCode\":\"en\",\"languageName\":\"English\",\"vssId\":\".en\"},{\"languageCode\":\"de\",\"languageName\":\"German (auto-generated)\",\"vssId\":\"a.de\",\"kind\":\"asr\"}]"#;
+    let server = MockServer::lounge(vec![format!(
+        r#"[[3,["onSubtitlesTrackList",{{"videoId":"dQw4w9WgXcQ","tracks":"{}"}}]]]"#,
+        tracks
+    )])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+
+    let (video_id, tracks) = loop {
+        if let LoungeEvent::SubtitlesTrackList { video_id, tracks } = rx.recv().await.unwrap() {
+            break (video_id, tracks);
+        }
+    };
+    assert_eq!(video_id, "dQw4w9WgXcQ");
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0].language_code, "en");
+    assert_eq!(tracks[0].vss_id, ".en");
+    assert_eq!(tracks[0].kind, None);
+    assert_eq!(tracks[1].language_name, "German (auto-generated)");
+    assert_eq!(tracks[1].kind.as_deref(), Some("asr"));
+
+    wait_for_connected(&client).await;
+    client.request_subtitles_track_list().await.unwrap();
+    assert_eq!(server.commands(), ["getSubtitlesTrackList"]);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_screen_is_idle_follows_now_playing() {
+    let cases = [
+        (
+            r#"[[3,["nowPlaying",{"videoId":"dQw4w9WgXcQ","currentTime":"1","duration":"100","state":"1"}]]]"#,
+            false,
+        ),
+        // An empty nowPlaying means nothing is loaded
+        (r#"[[3,["nowPlaying",{}]]]"#, true),
+    ];
+    for (events, expected) in cases {
+        let server = MockServer::lounge(vec![events.to_string()]).await;
+        let client = server.client(LoungeConfig::default());
+        assert_eq!(client.screen_is_idle(), None);
+
+        let mut rx = client.event_receiver();
+        client.connect().await.unwrap();
+        while !matches!(rx.recv().await.unwrap(), LoungeEvent::NowPlaying(_)) {}
+        assert_eq!(client.screen_is_idle(), Some(expected));
+        client.disconnect().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_current_playlist_url_from_now_playing() {
+    let cases = [
+        (
+            r#"[[3,["nowPlaying",{"videoId":"dQw4w9WgXcQ","state":"1","listId":"PLtest123"}]]]"#,
+            Some("https://www.youtube.com/playlist?list=PLtest123"),
+        ),
+        (
+            r#"[[3,["nowPlaying",{"videoId":"dQw4w9WgXcQ","state":"1"}]]]"#,
+            None,
+        ),
+    ];
+    for (events, expected) in cases {
+        let server = MockServer::lounge(vec![events.to_string()]).await;
+        let client = server.client(LoungeConfig::default());
+        assert_eq!(client.current_playlist_url(), None);
+
+        let mut rx = client.event_receiver();
+        client.connect().await.unwrap();
+        while !matches!(rx.recv().await.unwrap(), LoungeEvent::NowPlaying(_)) {}
+        assert_eq!(client.current_playlist_url().as_deref(), expected);
+        client.disconnect().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_commands_held_while_reconnecting() {
+    let run = |queue_commands_until_connected: bool| async move {
+        let polls = AtomicUsize::new(0);
+        // The first poll reports a dead session, forcing a re-bind after backoff
+        let server = MockServer::start(move |req| {
+            if req.is_poll() {
+                if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return MockResponse::status(400, "Unknown SID");
+                }
+                return MockResponse::hang();
+            }
+            default_response(req)
+        })
+        .await;
+        let client = server.client(LoungeConfig {
+            queue_commands_until_connected,
+            ..LoungeConfig::default()
+        });
+        client.connect().await.unwrap();
+        assert!(
+            wait_until(|| matches!(
+                client.current_state(),
+                ConnectionState::WaitingToReconnect { .. }
+            ))
+            .await
+        );
+
+        let result = client.send_command(PlaybackCommand::Play).await;
+        client.disconnect().await.unwrap();
+        (result, server.commands())
+    };
+
+    let (result, commands) = run(false).await;
+    assert!(matches!(result, Err(LoungeError::SessionLost)));
+    assert!(commands.is_empty());
+
+    let (result, commands) = run(true).await;
+    result.unwrap();
+    assert_eq!(commands, ["play"]);
+}
+
+#[tokio::test]
+async fn test_available_qualities_cached_from_events() {
+    let events = vec![
+        r#"[[3,["nowPlaying",{"videoId":"dQw4w9WgXcQ","currentTime":"1","duration":"100","state":"1"}]],[4,["onVideoQualityChanged",{"availableQualityLevels":"[0,1080,720,480]","qualityLevel":"720","videoId":"dQw4w9WgXcQ"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig::default());
+    assert!(client.available_qualities().is_empty());
+
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+    while !matches!(
+        rx.recv().await.unwrap(),
+        LoungeEvent::VideoQualityChanged(_)
+    ) {}
+    assert_eq!(client.available_qualities(), [0, 1080, 720, 480]);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_track_settings_assembled_from_events() {
+    let events = vec![
+        r#"[[3,["nowPlaying",{"videoId":"dQw4w9WgXcQ","currentTime":"1","duration":"100","state":"1"}]]]"#.to_string(),
+        r#"[[4,["onSubtitlesTrackChanged",{"videoId":"dQw4w9WgXcQ","languageCode":"en"}]],[5,["onAudioTrackChanged",{"videoId":"dQw4w9WgXcQ","audioTrackId":"en.4"}]],[6,["onVideoQualityChanged",{"availableQualityLevels":"[0,1080,720]","qualityLevel":"1080","videoId":"dQw4w9WgXcQ"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig::default());
+    assert_eq!(client.track_settings(), TrackSettings::default());
+
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+    while !matches!(
+        rx.recv().await.unwrap(),
+        LoungeEvent::VideoQualityChanged(_)
+    ) {}
+    assert_eq!(
+        client.track_settings(),
+        TrackSettings {
+            subtitle: Some("en".to_string()),
+            audio: Some("en.4".to_string()),
+            quality: Some(1080),
+        }
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_sessions_tracked_per_cpn() {
+    let events = vec![
+        r#"[[3,["nowPlaying",{"videoId":"video1","currentTime":"5","duration":"100","loadedTime":"20","state":"2","cpn":"cpn1"}]]]"#.to_string(),
+        r#"[[4,["nowPlaying",{"videoId":"video2","currentTime":"1","duration":"200","loadedTime":"5","state":"1","cpn":"cpn2"}]],[5,["onStateChange",{"currentTime":"2","duration":"200","state":"1","cpn":"cpn2","loadedTime":"10"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig::default());
+    let mut sessions = client.session_receiver();
+    client.connect().await.unwrap();
+
+    let mut received = Vec::new();
+    while received.len() < 3 {
+        received.push(sessions.recv().await.unwrap().cpn.unwrap());
+    }
+    assert_eq!(received, ["cpn1", "cpn2", "cpn2"]);
+
+    let first = client.session_by_cpn("cpn1").unwrap();
+    assert_eq!(first.video_id, "video1");
+    assert_eq!(first.status(), PlaybackStatus::Paused);
+    let second = client.session_by_cpn("cpn2").unwrap();
+    assert_eq!(second.video_id, "video2");
+    assert_eq!(second.current_time, 2.0);
+    assert!(client.session_by_cpn("cpn3").is_none());
+
+    assert_eq!(
+        client.current_session().unwrap().cpn.as_deref(),
+        Some("cpn2")
+    );
+    let playing: Vec<_> = client
+        .playing_sessions()
+        .into_iter()
+        .map(|session| session.video_id)
+        .collect();
+    assert_eq!(playing, ["video2"]);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tracked_sessions_expire_after_ttl() {
+    let events = vec![
+        r#"[[3,["nowPlaying",{"videoId":"video1","currentTime":"5","duration":"100","loadedTime":"20","state":"1","cpn":"cpn1"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig {
+        session_ttl: Some(Duration::from_millis(200)),
+        ..LoungeConfig::default()
+    });
+    let mut sessions = client.session_receiver();
+    client.connect().await.unwrap();
+    sessions.recv().await.unwrap();
+    assert_eq!(client.all_sessions().len(), 1);
+
+    assert!(wait_until(|| client.all_sessions().is_empty()).await);
+    assert!(client.session_by_cpn("cpn1").is_none());
+    assert!(client.current_session().is_none());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_debug_mode_carries_raw_payload() {
+    let events = vec![r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string()];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig::default());
+    client.enable_debug_mode();
+    let mut rx = client.event_receiver();
+    client.connect().await.unwrap();
+
+    let raw = loop {
+        if let LoungeEvent::Raw(raw) = rx.recv().await.unwrap() {
+            if raw.event_type == "onVolumeChanged" {
+                break raw;
+            }
+        }
+    };
+    assert_eq!(raw.payload["volume"], "40");
+    // The parsed event follows its raw form
+    assert!(matches!(
+        rx.recv().await.unwrap(),
+        LoungeEvent::VolumeChanged(_)
+    ));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_session_for_device_via_queue() {
+    let devices = r#"[{\"app\":\"android\",\"name\":\"Phone\",\"id\":\"remote-1\",\"type\":\"REMOTE_CONTROL\"}]"#;
+    let events = vec![
+        format!(
+            r#"[[3,["loungeStatus",{{"devices":"{}","queueId":"RQabc"}}]]]"#,
+            devices
+        ),
+        r#"[[4,["nowPlaying",{"videoId":"video1","currentTime":"5","duration":"100","loadedTime":"20","state":"1","cpn":"cpn1","listId":"RQabc"}]]]"#.to_string(),
+    ];
+    let server = MockServer::lounge(events).await;
+    let client = server.client(LoungeConfig::default());
+    let mut sessions = client.session_receiver();
+    client.connect().await.unwrap();
+    sessions.recv().await.unwrap();
+
+    let session = client.session_for_device("remote-1").unwrap();
+    assert_eq!(session.video_id, "video1");
+    assert!(client.session_for_device("remote-2").is_none());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rotated_gsessionid_used_by_later_polls() {
+    let server = MockServer::lounge(vec![r#"[[3,["S","rotated-gsessionid"]]]"#.to_string()]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+
+    let polls = || -> Vec<_> {
+        server
+            .requests()
+            .into_iter()
+            .filter(|r| r.is_poll())
+            .collect()
+    };
+    assert!(wait_until(|| polls().len() >= 2).await);
+    let polls = polls();
+    assert_eq!(polls[0].query_param("gsessionid"), Some(common::GSESSIONID));
+    assert_eq!(
+        polls[1].query_param("gsessionid"),
+        Some("rotated-gsessionid")
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_last_event_at_tracks_noops() {
+    let server = MockServer::lounge(vec![r#"[[3,["noop"]]]"#.to_string()]).await;
+    let client = server.client(LoungeConfig::default());
+    assert!(client.last_event_at().is_none());
+
+    let before = Instant::now();
+    client.connect().await.unwrap();
+    assert!(wait_until(|| client.last_event_at().is_some()).await);
+    assert!(client.last_event_at().unwrap() >= before);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_resumed_session_skips_processed_events() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string(),
+        r#"[[4,["onVolumeChanged",{"muted":"false","volume":"55"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let mut events = client.event_receiver();
+    assert!(client.session_snapshot().await.is_none());
+
+    let snapshot = SessionSnapshot {
+        sid: common::SID.to_string(),
+        gsessionid: common::GSESSIONID.to_string(),
+        rid: 10,
+        aid: 3,
+        command_offset: 2,
+    };
+    client
+        .connect_with_snapshot(Some(snapshot.clone()))
+        .await
+        .unwrap();
+    wait_for_connected(&client).await;
+
+    let volume = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Ok(LoungeEvent::VolumeChanged(volume)) = events.recv().await {
+                return volume.volume;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    // The event at the stored AID was already handled before the restart
+    assert_eq!(volume, "55");
+    assert_eq!(client.session_snapshot().await.unwrap().aid, 4);
+
+    // No new bind; the poll picked up from the stored position
+    let requests = server.requests();
+    assert!(requests.iter().all(|req| req.method != "POST"));
+    let poll = requests.iter().find(|req| req.is_poll()).unwrap();
+    assert_eq!(poll.query_param("SID"), Some(common::SID));
+    assert_eq!(poll.query_param("AID"), Some("3"));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_bind_ui_and_theme_configurable() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig {
+        bind_ui: true,
+        bind_theme: "m".to_string(),
+        ..LoungeConfig::default()
+    });
+    client.connect().await.unwrap();
+
+    let bind = server
+        .requests()
+        .into_iter()
+        .find(|req| req.method == "POST" && req.query_param("SID").is_none())
+        .unwrap();
+    assert_eq!(bind.form_param("ui").as_deref(), Some("true"));
+    assert_eq!(bind.form_param("theme").as_deref(), Some("m"));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_panicking_manager_reports_failed() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client
+        .set_poll_cycle_callback(|_| panic!("callback bug"))
+        .await;
+
+    client.connect().await.unwrap();
+    assert!(
+        wait_until(|| matches!(client.current_state(), ConnectionState::Failed(_))).await,
+        "state: {:?}",
+        client.current_state()
+    );
+    match client.current_state() {
+        ConnectionState::Failed(failure) => {
+            assert!(failure.message.contains("callback bug"), "{failure}");
+            assert_eq!(failure.kind, ErrorKind::Internal);
+            assert!(!failure.fatal);
+        }
+        other => panic!("Expected Failed, got {:?}", other),
+    }
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_terminate_timeout_honored() {
+    let server = MockServer::start(|req| {
+        // The screen never answers the terminate, nor the poll
+        if req.body.contains("TYPE=terminate") || req.is_poll() {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig {
+        terminate_timeout: Duration::from_millis(100),
+        ..LoungeConfig::default()
+    });
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let started = Instant::now();
+    client.disconnect().await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(server
+        .requests()
+        .iter()
+        .any(|req| req.body.contains("TYPE=terminate")));
+    assert_eq!(client.current_state(), ConnectionState::Disconnected);
+}
+
+#[tokio::test]
+async fn test_go_idle_halts_traffic_until_resumed() {
+    let server = MockServer::start(|req| {
+        if req.is_poll() {
+            // Every poll ends at once, so a running manager polls continuously
+            MockResponse::ok("")
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    assert!(wait_until(|| server.requests().len() >= 3).await);
+
+    client.go_idle().await.unwrap();
+    assert_eq!(client.current_state(), ConnectionState::Idle);
+    let idle_requests = server.requests().len();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(server.requests().len(), idle_requests);
+    let snapshot = client.session_snapshot().await.expect("session kept");
+    assert_eq!(snapshot.sid, common::SID);
+
+    client.resume_polling().await.unwrap();
+    assert!(wait_until(|| server.requests().len() > idle_requests).await);
+    // Resuming continued the session rather than binding a new one
+    assert!(server.requests()[idle_requests..]
+        .iter()
+        .all(|req| req.is_poll() && req.query_param("SID") == Some(common::SID)));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connect_to_offline_screen_fails_fast() {
+    let server = MockServer::start(|req| {
+        if req.path.ends_with("/bc/bind") {
+            MockResponse::status(404, "Not Found")
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+
+    let started = Instant::now();
+    let result = client.connect_with_refresh().await;
+    assert!(
+        matches!(result, Err(LoungeError::ScreenOffline)),
+        "unexpected result: {:?}",
+        result
+    );
+    assert!(started.elapsed() < Duration::from_secs(1));
+    // A single bind, with no separate availability check or retries
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1, "requests: {:?}", requests);
+}
+
+#[tokio::test]
+async fn test_concurrent_401s_share_one_token_refresh() {
+    let refreshes = Arc::new(Mutex::new(0usize));
+    let counter = refreshes.clone();
+    let server = MockServer::start(move |req| {
+        if req.path.ends_with("/pairing/get_lounge_token_batch") {
+            *counter.lock().unwrap() += 1;
+            return MockResponse::ok(
+                r#"{"screens":[{"screenId":"mock-screen","loungeToken":"fresh-token"}]}"#,
+            )
+            .delayed(Duration::from_millis(100));
+        }
+        if req.command().is_some() && req.query_param("loungeIdToken") != Some("fresh-token") {
+            return MockResponse::status(401, "Unauthorized");
+        }
+        if req.is_poll() {
+            return MockResponse::hang();
+        }
+        default_response(req)
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let (first, second) = tokio::join!(
+        client.send_command_with_refresh(PlaybackCommand::Play),
+        client.send_command_with_refresh(PlaybackCommand::Pause),
+    );
+    first.unwrap();
+    second.unwrap();
+    assert_eq!(*refreshes.lock().unwrap(), 1);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_token_still_rejected_after_refresh_backs_off() {
+    // The refreshed token is rejected as well, so the manager must not spin
+    // on polls that fail with 401 until another refresh is allowed
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let counter = refreshes.clone();
+    let server = MockServer::start(move |req| {
+        if req.path.ends_with("/pairing/get_lounge_token_batch") {
+            counter.fetch_add(1, Ordering::SeqCst);
+            return MockResponse::ok(
+                r#"{"screens":[{"screenId":"mock-screen","loungeToken":"fresh-token"}]}"#,
+            );
+        }
+        if req.is_poll() {
+            return MockResponse::status(401, "Unauthorized");
+        }
+        default_response(req)
+    })
+    .await;
+    let client = server.client(LoungeConfig {
+        min_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(200),
+        ..LoungeConfig::default()
+    });
+    client.connect().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    client.disconnect().await.unwrap();
+
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    let polls = server.requests().iter().filter(|req| req.is_poll()).count();
+    assert!(polls <= 10, "{} polls in one second", polls);
+}
+
+#[tokio::test]
+async fn test_token_refresh_honors_retry_after() {
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let counter = refreshes.clone();
+    let server = MockServer::start(move |req| {
+        if req.path.ends_with("/pairing/get_lounge_token_batch") {
+            counter.fetch_add(1, Ordering::SeqCst);
+            return MockResponse::status(403, "Forbidden").with_header("Retry-After", "60");
+        }
+        if req.command().is_some() {
+            return MockResponse::status(401, "Unauthorized");
+        }
+        if req.is_poll() {
+            return MockResponse::hang();
+        }
+        default_response(req)
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let result = client
+        .send_command_with_refresh(PlaybackCommand::Play)
+        .await;
+    match result {
+        Err(LoungeError::RateLimited { retry_after }) => {
+            assert_eq!(retry_after, Some(Duration::from_secs(60)))
+        }
+        other => panic!("Expected RateLimited, got {:?}", other),
+    }
+    // Within the delay, refreshing again fails without asking the server
+    let result = client
+        .send_command_with_refresh(PlaybackCommand::Play)
+        .await;
+    assert!(matches!(
+        result,
+        Err(LoungeError::RateLimited {
+            retry_after: Some(_)
+        })
+    ));
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_message_sizes_recorded() {
+    let short = r#"[[3,["noop"]]]"#.to_string();
+    let long = r#"[[4,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string();
+    let server = MockServer::lounge(vec![short.clone(), long.clone()]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+
+    assert!(wait_until(|| client.message_size_stats().count == 2).await);
+    let stats = client.message_size_stats();
+    assert!(stats.min >= short.len() && stats.min < long.len());
+    assert!(stats.max >= long.len());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_keepalive_sent_while_idle() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig {
+        keepalive_interval: Some(Duration::from_millis(100)),
+        ..LoungeConfig::default()
+    });
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    assert!(
+        wait_until(|| server.commands().len() >= 2).await,
+        "expected repeated keep-alives, got {:?}",
+        server.commands()
+    );
+    assert!(server.commands().iter().all(|c| c == "getNowPlaying"));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_bind_without_session_ids_reports_body_preview() {
+    let long_body = format!("{}{}", common::frame(r#"[[0,["noop"]]]"#), "x".repeat(1000));
+    let body = long_body.clone();
+    let server = MockServer::start(move |req| {
+        if req.path.ends_with("/bc/bind") && req.method == "POST" {
+            MockResponse::ok(body.clone())
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+
+    match client.connect().await {
+        Err(LoungeError::SessionIdExtractionFailed { body_preview }) => {
+            assert!(body_preview.starts_with("15\n[[0,[\"noop\"]]]"));
+            assert!(body_preview.ends_with('…'));
+            assert!(body_preview.chars().count() < long_body.len());
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_network_change_retries_before_backoff_elapses() {
+    let server = MockServer::start(|req| {
+        if req.is_poll() {
+            // Every poll fails, sending the manager into backoff
+            MockResponse::status(500, "boom")
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let binds = || {
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.method == "POST" && r.query_param("SID").is_none())
+            .count()
+    };
+
+    client.connect().await.unwrap();
+    assert!(
+        wait_until(|| matches!(
+            client.current_state(),
+            ConnectionState::WaitingToReconnect { .. }
+        ))
+        .await
+    );
+    let ConnectionState::WaitingToReconnect { backoff, .. } = client.current_state() else {
+        unreachable!()
+    };
+    let binds_before = binds();
+
+    client.notify_network_changed();
+    let retried = tokio::time::timeout(backoff / 2, async {
+        while binds() == binds_before {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await;
+    assert!(
+        retried.is_ok(),
+        "no reconnect attempt within half the {:?} backoff",
+        backoff
+    );
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_command_response_callback_receives_body() {
+    let server = MockServer::start(|req| {
+        if req.is_poll() {
+            return MockResponse::hang();
+        }
+        if req.command().is_some() {
+            return MockResponse::ok(common::frame(r#"[[3,["noop"]]]"#));
+        }
+        default_response(req)
+    })
+    .await;
+    let client = server.client(LoungeConfig {
+        observe_command_responses: true,
+        ..LoungeConfig::default()
+    });
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let log = responses.clone();
+    client
+        .set_command_response_callback(move |command, status, body| {
+            log.lock()
+                .unwrap()
+                .push((command.to_string(), status, body.to_string()));
+        })
+        .await;
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    client.send_command(PlaybackCommand::Play).await.unwrap();
+    assert_eq!(
+        *responses.lock().unwrap(),
+        vec![("play".to_string(), 200, common::frame(r#"[[3,["noop"]]]"#))]
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_inactivity_timeout_fires_at_configured_duration() {
+    let server = MockServer::start(|req| {
+        if req.is_poll() {
+            MockResponse::stall_body()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig {
+        inactivity_timeout: Duration::from_secs(30),
+        ..LoungeConfig::default()
+    });
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    client
+        .set_poll_cycle_callback(move |event| {
+            let _ = tx.send((tokio::time::Instant::now(), event.clone()));
+        })
+        .await;
+
+    // A paused clock auto-advances whenever the runtime idles, even while
+    // real socket I/O is pending, so keep it busy until the poll is stalled
+    let spinner = tokio::spawn(async {
+        loop {
+            tokio::task::yield_now().await;
+        }
+    });
+    client.connect().await.unwrap();
+    while !server.requests().iter().any(|r| r.is_poll()) {
+        tokio::task::yield_now().await;
+    }
+    let settle_until = std::time::Instant::now() + Duration::from_millis(100);
+    while std::time::Instant::now() < settle_until {
+        tokio::task::yield_now().await;
+    }
+    spinner.abort();
+
+    let (started_at, started) = rx.recv().await.unwrap();
+    assert_eq!(started, PollCycleEvent::Started { cycle: 1 });
+    // With nothing else pending the clock jumps straight to the timeout
+    let (finished_at, finished) = rx.recv().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    assert_eq!(finished_at - started_at, Duration::from_secs(30));
+    match finished {
+        PollCycleEvent::Finished(stats) => {
+            assert_eq!(stats.end_reason, Some(PollEndReason::InactivityTimeout));
+            assert_eq!(stats.elapsed, Duration::from_secs(30));
+        }
+        other => panic!("Expected Finished, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_inactivity_repoll_is_configurable() {
+    let polls_with = |repoll_on_inactivity: bool| async move {
+        // Every poll is answered but stays open without sending anything
+        let server = MockServer::start(|req| {
+            if req.is_poll() {
+                MockResponse::stall_body()
+            } else {
+                default_response(req)
+            }
+        })
+        .await;
+        let client = server.client(LoungeConfig {
+            inactivity_timeout: Duration::from_millis(50),
+            repoll_on_inactivity,
+            ..LoungeConfig::default()
+        });
+        client.connect().await.unwrap();
+        wait_for_connected(&client).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        client.disconnect().await.unwrap();
+        server.requests().iter().filter(|r| r.is_poll()).count()
+    };
+
+    assert!(polls_with(true).await > 2);
+    assert_eq!(polls_with(false).await, 1);
+}
+
+#[tokio::test]
+async fn test_waiting_state_reports_retry_instant() {
+    let server = MockServer::start(|req| {
+        if req.is_poll() {
+            MockResponse::status(500, "boom")
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+
+    assert!(
+        wait_until(|| matches!(
+            client.current_state(),
+            ConnectionState::WaitingToReconnect { .. }
+        ))
+        .await
+    );
+    let observed_at = Instant::now();
+    let ConnectionState::WaitingToReconnect { backoff, retry_at } = client.current_state() else {
+        unreachable!()
+    };
+    assert!(retry_at <= observed_at + backoff);
+    assert!(
+        retry_at + Duration::from_millis(100) >= observed_at + backoff,
+        "retry_at is {:?} before now + backoff",
+        (observed_at + backoff) - retry_at
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connected_duration_grows_and_resets() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    assert_eq!(client.connected_duration(), None);
+
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    let since = client.connected_since().expect("connected");
+    let first = client.connected_duration().unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(client.connected_duration().unwrap() >= first + Duration::from_millis(50));
+    assert_eq!(client.connected_since(), Some(since));
+
+    client.disconnect().await.unwrap();
+    assert_eq!(client.connected_since(), None);
+    assert_eq!(client.connected_duration(), None);
+}
+
+#[tokio::test]
+async fn test_poll_end_reason_recorded_for_inactivity() {
+    let server = MockServer::start(|req| {
+        if req.is_poll() {
+            MockResponse::stall_body()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig {
+        inactivity_timeout: Duration::from_millis(50),
+        ..LoungeConfig::default()
+    });
+    assert_eq!(client.last_poll_end_reason(), None);
+    client.connect().await.unwrap();
+
+    assert!(
+        wait_until(|| client.last_poll_end_reason() == Some(PollEndReason::InactivityTimeout))
+            .await,
+        "last reason: {:?}",
+        client.last_poll_end_reason()
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stream_buffer_capacity_configurable() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig {
+        stream_buffer_capacity: 64 * 1024,
+        ..LoungeConfig::default()
+    });
+    let finished = Arc::new(Mutex::new(Vec::new()));
+    let recorded = finished.clone();
+    client
+        .set_poll_cycle_callback(move |event| {
+            if let PollCycleEvent::Finished(stats) = event {
+                recorded.lock().unwrap().push(stats.clone());
+            }
+        })
+        .await;
+
+    client.connect().await.unwrap();
+    assert!(wait_until(|| !finished.lock().unwrap().is_empty()).await);
+    client.disconnect().await.unwrap();
+
+    let stats = finished.lock().unwrap()[0].clone();
+    assert_eq!(stats.messages_decoded, 1);
+    assert!(
+        stats.buffer_capacity >= 64 * 1024,
+        "capacity: {}",
+        stats.buffer_capacity
+    );
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_events_without_subscribers_do_not_warn() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string(),
+        r#"[[4,["onAutoplayModeChanged",{"autoplayMode":"ENABLED"}]]]"#.to_string(),
+    ])
+    .await;
+    // Nobody calls event_receiver(), so every event is dropped
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    assert!(wait_until(|| client.message_size_stats().count >= 2).await);
+    client.disconnect().await.unwrap();
+
+    logs_assert(
+        |lines| match lines.iter().find(|line| line.contains(" WARN ")) {
+            Some(line) => Err(format!("unexpected warning: {}", line)),
+            None => Ok(()),
+        },
+    );
+}
+
+#[tokio::test]
+async fn test_discovery_finds_dial_screen() {
+    use youtube_lounge_rs::discovery::{discover_screens_at, DIAL_SEARCH_TARGET};
+
+    let host = |req: &common::MockRequest| {
+        let (_, host) = req
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+            .unwrap();
+        host.clone()
+    };
+    let server = MockServer::start(move |req| match req.path.as_str() {
+        "/dd.xml" => MockResponse::ok(
+            "<root><device><friendlyName>Living Room &amp; Co</friendlyName></device></root>",
+        )
+        .with_header("Application-URL", &format!("http://{}/apps/", host(req))),
+        "/apps/YouTube" => MockResponse::ok(
+            "<service><name>YouTube</name><state>running</state>\
+             <additionalData><screenId>dial-screen-1</screenId></additionalData></service>",
+        ),
+        _ => MockResponse::status(404, ""),
+    })
+    .await;
+    let location = format!("{}/dd.xml", server.base.trim_end_matches("/api/lounge"));
+
+    // A DIAL device answering the search, plus one that isn't a YouTube screen
+    let device = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let target = device.local_addr().unwrap();
+    let announced = location.clone();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1024];
+        let (len, from) = device.recv_from(&mut buf).await.unwrap();
+        let search = String::from_utf8_lossy(&buf[..len]).to_string();
+        assert!(search.starts_with("M-SEARCH"), "{search}");
+        assert!(search.contains(DIAL_SEARCH_TARGET), "{search}");
+        for answer in [
+            format!("HTTP/1.1 200 OK\r\nLOCATION: {announced}\r\nST: {DIAL_SEARCH_TARGET}\r\n\r\n"),
+            format!("HTTP/1.1 200 OK\r\nLocation: {announced}\r\n\r\n"),
+            "HTTP/1.1 200 OK\r\nLOCATION: http://127.0.0.1:1/missing.xml\r\n\r\n".to_string(),
+        ] {
+            device.send_to(answer.as_bytes(), from).await.unwrap();
+        }
+    });
+
+    let screens = discover_screens_at(target, Duration::from_millis(300))
+        .await
+        .unwrap();
+    assert_eq!(screens.len(), 1, "{screens:?}");
+    assert_eq!(screens[0].screen_id, "dial-screen-1");
+    assert_eq!(screens[0].name.as_deref(), Some("Living Room & Co"));
+    assert_eq!(screens[0].location, location);
+    assert!(screens[0].app_url.ends_with("/apps/YouTube"));
+}
+
+#[tokio::test]
+async fn test_queue_tracked_and_edited() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["playlistModified",{"listId":"RQq1","videoId":"v1","currentIndex":"0","action":"added"}]]]"#.to_string(),
+        r#"[[4,["playlistModified",{"listId":"RQq1","videoId":"v2","currentIndex":"0","action":"added"}]]]"#.to_string(),
+        r#"[[5,["playlistModified",{"listId":"RQq1","videoId":"v3","currentIndex":"0","action":"added"}]]]"#.to_string(),
+        r#"[[6,["playlistModified",{"listId":"RQq1","videoId":"v2","currentIndex":"0","action":"removed"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    assert!(wait_until(|| client.get_queue().len() == 2).await);
+
+    let queue = client.get_queue();
+    assert_eq!(queue.list_id.as_deref(), Some("RQq1"));
+    assert_eq!(queue.video_ids, vec!["v1", "v3"]);
+    assert_eq!(queue.current_index, Some(0));
+
+    client.remove_video(1).await.unwrap();
+    client.move_video(1, 0).await.unwrap();
+    client.clear_queue().await.unwrap();
+    assert!(matches!(
+        client.remove_video(2).await,
+        Err(LoungeError::QueueIndexOutOfRange { index: 2, len: 2 })
+    ));
+    assert!(matches!(
+        client.move_video(0, 5).await,
+        Err(LoungeError::QueueIndexOutOfRange { index: 5, len: 2 })
+    ));
+    client.disconnect().await.unwrap();
+
+    assert_eq!(
+        server.commands(),
+        vec!["removeVideo", "moveVideo", "clearPlaylist"]
+    );
+    let requests = server.requests();
+    let command = |name: &str| {
+        requests
+            .iter()
+            .find(|r| r.command().as_deref() == Some(name))
+            .unwrap()
+    };
+    assert_eq!(
+        command("removeVideo").form_param("req0_videoId").as_deref(),
+        Some("v3")
+    );
+    let moved = command("moveVideo");
+    assert_eq!(moved.form_param("req0_videoId").as_deref(), Some("v3"));
+    assert_eq!(moved.form_param("req0_index").as_deref(), Some("0"));
+}
+
+#[tokio::test]
+async fn test_queue_replaced_by_full_listing() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["playlistModified",{"listId":"RQq1","videoId":"v1","action":"added"}]]]"#.to_string(),
+        r#"[[4,["playlistModified",{"listId":"RQq1","videoId":"v2","action":"added"}]]]"#.to_string(),
+        // A different list starts a new queue, then a full listing reorders it
+        r#"[[5,["playlistModified",{"listId":"RQq2","videoId":"v9","currentIndex":"0","action":"added"}]]]"#.to_string(),
+        r#"[[6,["playlistModified",{"listId":"RQq2","videoId":"v9","currentIndex":"1","action":"reordered","videoIds":"v8,v9"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    assert!(wait_until(|| client.get_queue().current_index == Some(1)).await);
+    client.disconnect().await.unwrap();
+
+    let queue = client.get_queue();
+    assert_eq!(queue.list_id.as_deref(), Some("RQq2"));
+    assert_eq!(queue.video_ids, vec!["v8", "v9"]);
+}
+
+#[tokio::test]
+async fn test_track_and_navigation_commands_sent() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    client
+        .set_subtitles_track("vid".to_string(), "en".to_string())
+        .await
+        .unwrap();
+    client
+        .set_audio_track("vid".to_string(), "en.4".to_string())
+        .await
+        .unwrap();
+    client
+        .set_video_quality("vid".to_string(), 1080)
+        .await
+        .unwrap();
+    client.dpad(DpadKey::Enter).await.unwrap();
+    client.voice_command("start".to_string()).await.unwrap();
+    client.stop_video().await.unwrap();
+    client.set_playlist_index(3).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    assert_eq!(
+        server.commands(),
+        vec![
+            "setSubtitlesTrack",
+            "setAudioTrack",
+            "setVideoQuality",
+            "dpadCommand",
+            "voiceCommand",
+            "stopVideo",
+            "setPlaylistIndex"
+        ]
+    );
+    let requests = server.requests();
+    let param = |command: &str, key: &str| {
+        requests
+            .iter()
+            .find(|r| r.command().as_deref() == Some(command))
+            .and_then(|r| r.form_param(key))
+    };
+    assert_eq!(
+        param("setSubtitlesTrack", "req0_videoId").as_deref(),
+        Some("vid")
+    );
+    assert_eq!(
+        param("setSubtitlesTrack", "req0_languageCode").as_deref(),
+        Some("en")
+    );
+    assert_eq!(
+        param("setAudioTrack", "req0_audioTrackId").as_deref(),
+        Some("en.4")
+    );
+    assert_eq!(
+        param("setVideoQuality", "req0_qualityLevel").as_deref(),
+        Some("1080")
+    );
+    assert_eq!(param("dpadCommand", "req0_key").as_deref(), Some("ENTER"));
+    assert_eq!(
+        param("voiceCommand", "req0_status").as_deref(),
+        Some("start")
+    );
+    assert_eq!(
+        param("setPlaylistIndex", "req0_index").as_deref(),
+        Some("3")
+    );
+}
+
+#[tokio::test]
+async fn test_builder_applies_http_and_connection_settings() {
+    let server = MockServer::start(|req| {
+        // Failing polls make the client back off
+        if req.is_poll() {
+            MockResponse::status(503, "")
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = LoungeClient::builder("mock-screen", "mock-token", "Test Remote")
+        .config(server.config())
+        .device_id("fixed-device")
+        .user_agent("lounge-test/1.0")
+        .min_backoff(Duration::from_secs(7))
+        .max_backoff(Duration::from_secs(9))
+        .long_poll_timeout(Duration::from_secs(42))
+        .event_channel_capacity(4)
+        .build()
+        .unwrap();
+    assert_eq!(client.device_id(), "fixed-device");
+    assert_eq!(client.config().long_poll_timeout, Duration::from_secs(42));
+    assert_eq!(client.config().event_channel_capacity, 4);
+
+    client.connect().await.unwrap();
+    assert!(
+        wait_until(|| matches!(
+            client.current_state(),
+            ConnectionState::WaitingToReconnect { .. }
+        ))
+        .await
+    );
+    let ConnectionState::WaitingToReconnect { backoff, .. } = client.current_state() else {
+        unreachable!()
+    };
+    client.disconnect().await.unwrap();
+
+    // 7s with up to 30% jitter either way
+    assert!(
+        backoff >= Duration::from_millis(4900) && backoff <= Duration::from_millis(9100),
+        "{backoff:?}"
+    );
+    let bind = &server.requests()[0];
+    let user_agent = bind
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))
+        .map(|(_, value)| value.as_str());
+    assert_eq!(user_agent, Some("lounge-test/1.0"));
+    assert_eq!(bind.form_param("id").as_deref(), Some("fixed-device"));
+}
+
+#[tokio::test]
+async fn test_proxy_carries_pairing_and_session_traffic() {
+    let proxy = MockServer::start(|req| {
+        if req.path.ends_with("/pairing/get_screen") {
+            MockResponse::ok(
+                r#"{"screen":{"screenId":"proxied-screen","loungeToken":"t1","name":"TV"}}"#,
+            )
+        } else if req.path.ends_with("/pairing/get_lounge_token_batch") {
+            MockResponse::ok(r#"{"screens":[{"screenId":"proxied-screen","loungeToken":"t2"}]}"#)
+        } else if req.is_poll() {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let proxy_url = proxy.base.trim_end_matches("/api/lounge");
+    // Only reachable through the proxy
+    let config = LoungeConfig {
+        api_base: "http://lounge.invalid/api/lounge".to_string(),
+        proxy: Some(reqwest::Proxy::all(proxy_url).unwrap()),
+        ..LoungeConfig::default()
+    };
+
+    let screen = LoungeClient::pair_with_screen_with_config("1234 5678 9012", &config)
+        .await
+        .unwrap();
+    assert_eq!(screen.lounge_token, "t1");
+    let screen = LoungeClient::refresh_lounge_token_with_config(&screen.screen_id, &config)
+        .await
+        .unwrap();
+    assert_eq!(screen.lounge_token, "t2");
+    let screen = LoungeClient::pair_with_screen_id_with_config(&screen.screen_id, &config)
+        .await
+        .unwrap();
+    assert_eq!(screen.lounge_token, "t2");
+
+    let client = LoungeClient::new(
+        &screen.screen_id,
+        &screen.lounge_token,
+        "Test Remote",
+        None,
+        None,
+    )
+    .with_config(config)
+    .unwrap();
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    client.disconnect().await.unwrap();
+
+    let requests = proxy.requests();
+    assert!(requests.iter().any(|r| r.is_poll()));
+    for request in &requests {
+        assert!(
+            request
+                .path
+                .starts_with("http://lounge.invalid/api/lounge/"),
+            "{}",
+            request.path
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_state_receiver_observes_transitions() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    let mut states = client.state_receiver();
+    assert_eq!(*states.borrow_and_update(), ConnectionState::Disconnected);
+
+    client.connect().await.unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        states.wait_for(|state| *state == ConnectionState::Connected),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    client.disconnect().await.unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        states.wait_for(|state| *state == ConnectionState::Disconnected),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_session_resumed_after_rebind() {
+    let polls = AtomicUsize::new(0);
+    // Playback is reported, then the session dies and has to be bound again
+    let server = MockServer::start(move |req| {
+        if !req.is_poll() {
+            return default_response(req);
+        }
+        match polls.fetch_add(1, Ordering::SeqCst) {
+            0 => MockResponse::ok(frame(
+                r#"[[3,["nowPlaying",{"videoId":"resumeMe","listId":"PLq","currentTime":"10","duration":"100","state":"1","cpn":"cpn-1"}]],[4,["onStateChange",{"currentTime":"42.5","duration":"100","state":"1","cpn":"cpn-1","loadedTime":"50"}]]]"#,
+            )),
+            1 => MockResponse::status(400, "Unknown SID"),
+            _ => MockResponse::hang(),
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig {
+        resume_on_reconnect: true,
+        resume_playback_on_reconnect: true,
+        min_backoff: Duration::from_millis(10),
+        ..LoungeConfig::default()
+    });
+    let mut events = client.event_receiver();
+    client.connect().await.unwrap();
+
+    let resumed = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let LoungeEvent::SessionResumed { video_id, position } = events.recv().await.unwrap()
+            {
+                return (video_id, position);
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(resumed, ("resumeMe".to_string(), 42.5));
+
+    assert!(wait_until(|| server.commands().contains(&"setPlaylist".to_string())).await);
+    client.disconnect().await.unwrap();
+    let requests = server.requests();
+    let request = requests
+        .iter()
+        .find(|r| r.command().as_deref() == Some("setPlaylist"))
+        .unwrap();
+    assert_eq!(
+        request.form_param("req0_videoId").as_deref(),
+        Some("resumeMe")
+    );
+    assert_eq!(request.form_param("req0_listId").as_deref(), Some("PLq"));
+    assert_eq!(
+        request.form_param("req0_currentTime").as_deref(),
+        Some("42.5")
+    );
+}
+
+#[tokio::test]
+async fn test_token_store_persists_refreshed_token() {
+    async fn server_with_batch(batch: &'static str) -> MockServer {
+        MockServer::start(move |req| {
+            if req.path.ends_with("/pairing/get_lounge_token_batch") {
+                return MockResponse::ok(batch);
+            }
+            let is_initial_bind = req.method == "POST"
+                && req.path.ends_with("/bc/bind")
+                && req.query_param("SID").is_none();
+            if is_initial_bind && req.form_param("loungeIdToken").as_deref() != Some("fresh-token")
+            {
+                return MockResponse::status(401, "Unauthorized");
+            }
+            if req.is_poll() {
+                return MockResponse::hang();
+            }
+            default_response(req)
+        })
+        .await
+    }
+
+    let path = std::env::temp_dir().join(format!("lounge-tokens-{}.json", std::process::id()));
+    let store = Arc::new(JsonFileTokenStore::new(&path));
+    store
+        .save(&StoredScreen {
+            screen_id: "mock-screen".to_string(),
+            lounge_token: "stale-token".to_string(),
+            name: Some("Living Room".to_string()),
+            device_id: Some("stored-device".to_string()),
+            device_name: None,
+        })
+        .unwrap();
+
+    let server = server_with_batch(
+        r#"{"screens":[{"screenId":"mock-screen","loungeToken":"fresh-token","expiration":0}]}"#,
+    )
+    .await;
+    let client = LoungeClient::from_token_store(store.clone(), "mock-screen", "Test Remote")
+        .unwrap()
+        .unwrap()
+        .with_config(server.config())
+        .unwrap();
+    assert_eq!(client.device_id(), "stored-device");
+    client.connect_with_refresh().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let stored = store.load("mock-screen").unwrap().unwrap();
+    assert_eq!(stored.lounge_token, "fresh-token");
+    assert_eq!(stored.device_id.as_deref(), Some("stored-device"));
+    assert_eq!(stored.name.as_deref(), Some("Living Room"));
+
+    // Credentials of a screen that was unpaired are useless, so they're dropped
+    let server = server_with_batch(r#"{"screens":[]}"#).await;
+    let client = LoungeClient::builder("mock-screen", "stale-token", "Test Remote")
+        .config(server.config())
+        .token_store(store.clone())
+        .build()
+        .unwrap();
+    let err = client.connect_with_refresh().await.unwrap_err();
+    assert!(matches!(err, LoungeError::ScreenUnpaired), "{:?}", err);
+    assert_eq!(store.load("mock-screen").unwrap(), None);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_manager_multiplexes_screens_and_restarts_failed_ones() {
+    let tv_a = MockServer::lounge(vec![
+        r#"[[3,["nowPlaying",{"videoId":"onA","currentTime":"1","duration":"100","state":"1"}]]]"#
+            .to_string(),
+    ])
+    .await;
+    let binds = AtomicUsize::new(0);
+    // The first bind fails, so only a restart by the manager connects this screen
+    let tv_b = MockServer::start(move |req| {
+        let is_initial_bind = req.method == "POST"
+            && req.path.ends_with("/bc/bind")
+            && req.query_param("SID").is_none()
+            && !req.body.contains("TYPE=terminate");
+        if is_initial_bind && binds.fetch_add(1, Ordering::SeqCst) == 0 {
+            return MockResponse::status(500, "Internal Server Error");
+        }
+        if req.is_poll() {
+            return MockResponse::ok(frame(
+                r#"[[3,["nowPlaying",{"videoId":"onB","currentTime":"1","duration":"100","state":"1"}]]]"#,
+            ))
+            .delayed(Duration::from_millis(200));
+        }
+        default_response(req)
+    })
+    .await;
+
+    let manager = LoungeManager::new().with_restart_delay(Duration::from_millis(50));
+    let mut events = manager.event_receiver();
+    manager
+        .add(
+            LoungeClient::new("tv-a", "token", "Test Remote", None, None)
+                .with_config(tv_a.config())
+                .unwrap(),
+        )
+        .await;
+    manager
+        .add(
+            LoungeClient::new("tv-b", "token", "Test Remote", None, None)
+                .with_config(tv_b.config())
+                .unwrap(),
+        )
+        .await;
+    let mut ids = manager.screen_ids();
+    ids.sort();
+    assert_eq!(ids, ["tv-a", "tv-b"]);
+
+    let results = manager.connect_all().await;
+    assert!(results["tv-a"].is_ok());
+    assert!(results["tv-b"].is_err());
+
+    // Both screens report through the manager once tv-b has been restarted
+    let mut playing = HashMap::new();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while playing.len() < 2 {
+            let tagged = events.recv().await.unwrap();
+            if let LoungeEvent::NowPlaying(np) = tagged.event {
+                playing.insert(tagged.screen_id, np.video_id);
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(playing["tv-a"], "onA");
+    assert_eq!(playing["tv-b"], "onB");
+    assert!(manager
+        .states()
+        .values()
+        .all(|state| *state == ConnectionState::Connected));
+
+    let results = manager.pause_all().await;
+    assert!(results.values().all(Result::is_ok), "{:?}", results);
+    assert_eq!(tv_a.commands(), ["pause"]);
+    assert_eq!(tv_b.commands(), ["pause"]);
+
+    let removed = manager.remove("tv-a").await.unwrap();
+    assert_eq!(removed.current_state(), ConnectionState::Disconnected);
+    assert_eq!(manager.screen_ids(), ["tv-b"]);
+    manager.disconnect_all().await;
+}
+
+#[tokio::test]
+async fn test_event_streams_filter_and_apply_lag_policy() {
+    // Video IDs of the NowPlaying events a stream yields, read after all
+    // eight have been received
+    async fn read_after_burst(capacity: usize, policy: LagPolicy) -> Vec<String> {
+        let events = (0..8)
+            .map(|i| {
+                format!(
+                    r#"[[{},["nowPlaying",{{"videoId":"v{}","currentTime":"1","duration":"100","state":"1"}}]]]"#,
+                    i + 3,
+                    i
+                )
+            })
+            .collect();
+        let server = MockServer::lounge(events).await;
+        let client = server.client(LoungeConfig {
+            event_channel_capacity: capacity,
+            event_lag_policy: policy,
+            ..LoungeConfig::default()
+        });
+        let mut stream = Box::pin(client.events());
+        client.connect().await.unwrap();
+        assert!(
+            wait_until(|| client
+                .player_state()
+                .now_playing
+                .is_some_and(|np| np.video_id == "v7"))
+            .await
+        );
+
+        let mut seen = Vec::new();
+        while let Ok(Some(event)) =
+            tokio::time::timeout(Duration::from_millis(200), stream.next()).await
+        {
+            if let LoungeEvent::NowPlaying(np) = event {
+                seen.push(np.video_id);
+            }
+        }
+        client.disconnect().await.unwrap();
+        seen
+    }
+
+    let all: Vec<_> = (0..8).map(|i| format!("v{}", i)).collect();
+    assert_eq!(read_after_burst(64, LagPolicy::Skip).await, all);
+
+    // A lagging reader either resumes at the oldest kept event or stops
+    let skipped = read_after_burst(4, LagPolicy::Skip).await;
+    assert!(!skipped.is_empty() && skipped.len() < 8, "{:?}", skipped);
+    assert_eq!(skipped.last().map(String::as_str), Some("v7"));
+    assert!(read_after_burst(4, LagPolicy::End).await.is_empty());
+
+    let server = MockServer::lounge(vec![
+        r#"[[3,["nowPlaying",{"videoId":"typed","currentTime":"1","duration":"100","state":"1"}]],[4,["onVolumeChanged",{"volume":"30","muted":"false"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let volumes = client.events_of::<VolumeChanged>();
+    client.connect().await.unwrap();
+    let volume = tokio::time::timeout(Duration::from_secs(5), Box::pin(volumes).next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(volume.volume_level(), 30);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_command_ack_waits_for_matching_event() {
+    let paused = AtomicBool::new(false);
+    // The TV only reports the paused state once it has received the command
+    let server = MockServer::start(move |req| {
+        if req.command().as_deref() == Some("pause") {
+            paused.store(true, Ordering::SeqCst);
+        }
+        if req.is_poll() {
+            if paused.load(Ordering::SeqCst) {
+                return MockResponse::ok(frame(
+                    r#"[[3,["onStateChange",{"currentTime":"7","duration":"100","state":"2","cpn":"c"}]]]"#,
+                ));
+            }
+            return MockResponse::ok(frame(r#"[[2,["noop"]]]"#)).delayed(Duration::from_millis(50));
+        }
+        default_response(req)
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let outcome = client
+        .send_command_ack(PlaybackCommand::Pause, Duration::from_secs(5))
+        .await
+        .unwrap();
+    let CommandOutcome::Acknowledged(event) = outcome else {
+        panic!("unexpected outcome: {:?}", outcome);
+    };
+    let LoungeEvent::StateChange(state) = *event else {
+        panic!("unexpected event: {:?}", event);
+    };
+    assert_eq!(state.status(), PlaybackStatus::Paused);
+
+    let outcome = client
+        .send_command_ack(
+            PlaybackCommand::SetVolume { volume: 40 },
+            Duration::from_millis(300),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(outcome, CommandOutcome::TimedOut), "{:?}", outcome);
+
+    let outcome = client
+        .send_command_ack(
+            PlaybackCommand::Dpad { key: DpadKey::Up },
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert!(
+        matches!(outcome, CommandOutcome::Unconfirmable),
+        "{:?}",
+        outcome
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_commands_rate_limited_and_coalesced() {
+    let server = MockServer::lounge(vec![]).await;
+    let config = LoungeConfig {
+        min_command_interval: Some(Duration::from_millis(150)),
+        command_coalesce_window: Some(Duration::from_millis(200)),
+        ..server.config()
+    };
+    let client = server.client(config);
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    // A knob turned quickly: only the last volume is sent
+    let turns = (0..20).map(|volume| client.send_command(PlaybackCommand::SetVolume { volume }));
+    for result in futures::future::join_all(turns).await {
+        result.unwrap();
+    }
+    let volumes: Vec<_> = server
+        .requests()
+        .iter()
+        .filter(|req| req.command().as_deref() == Some("setVolume"))
+        .filter_map(|req| req.form_param("req0_volume"))
+        .collect();
+    assert_eq!(volumes, vec!["19"]);
+
+    // Other commands are all sent, but spaced out
+    let started = tokio::time::Instant::now();
+    for _ in 0..3 {
+        client.send_command(PlaybackCommand::Play).await.unwrap();
+    }
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert_eq!(server.commands(), vec!["setVolume", "play", "play", "play"]);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_error_context_attached_when_enabled() {
+    let server = MockServer::start(|req| {
+        if req.command().as_deref() == Some("play") {
+            MockResponse::status(400, "Unknown SID")
+        } else if req.is_poll() {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig {
+        error_context: true,
+        ..LoungeConfig::default()
+    });
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let err = client.play().await.unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        LoungeError::SessionInvalidatedByServer(400)
+    ));
+    assert_eq!(err.kind(), ErrorKind::Session);
+    let context = err.context().expect("context attached");
+    assert_eq!(context.screen_id.as_deref(), Some("mock-screen"));
+    assert_eq!(context.endpoint.as_deref(), Some("bc/bind"));
+    assert_eq!(context.status, Some(400));
+    assert_eq!(context.body_excerpt.as_deref(), Some("Unknown SID"));
+
+    // Without the option errors come as before
+    let server = MockServer::start(|req| match req.command().as_deref() {
+        Some("play") => MockResponse::status(400, "Unknown SID"),
+        _ if req.is_poll() => MockResponse::hang(),
+        _ => default_response(req),
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    let err = client.play().await.unwrap_err();
+    assert!(matches!(err, LoungeError::SessionInvalidatedByServer(400)));
+    assert!(err.context().is_none());
+}
+
+#[tokio::test]
+async fn test_playlist_mode_event_and_command() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onPlaylistModeChanged",{"loopEnabled":"false","shuffleEnabled":"true"}]]]"#
+            .to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let mut modes = Box::pin(client.events_of::<PlaylistModeChanged>());
+    client.connect().await.unwrap();
+
+    let mode = tokio::time::timeout(Duration::from_secs(5), modes.next())
+        .await
+        .expect("no PlaylistModeChanged event received")
+        .unwrap();
+    assert!(!mode.is_loop_enabled());
+    assert!(mode.is_shuffle_enabled());
+
+    client.set_playlist_mode(true, false).await.unwrap();
+    let request = server
+        .requests()
+        .into_iter()
+        .find(|req| req.command().as_deref() == Some("setPlaylistMode"))
+        .expect("setPlaylistMode sent");
+    assert_eq!(
+        request.form_param("req0_loopEnabled").as_deref(),
+        Some("true")
+    );
+    assert_eq!(
+        request.form_param("req0_shuffleEnabled").as_deref(),
+        Some("false")
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_volume_steps_from_tracked_level() {
+    let asked = AtomicBool::new(false);
+    let answered = AtomicBool::new(false);
+    // The TV only reports its volume when asked
+    let server = MockServer::start(move |req| {
+        if req.command().as_deref() == Some("getVolume") {
+            asked.store(true, Ordering::SeqCst);
+        }
+        if req.is_poll() {
+            if !asked.load(Ordering::SeqCst) {
+                return MockResponse::ok(frame(r#"[[2,["noop"]]]"#))
+                    .delayed(Duration::from_millis(50));
+            }
+            if answered.swap(true, Ordering::SeqCst) {
+                return MockResponse::hang();
+            }
+            return MockResponse::ok(frame(
+                r#"[[3,["onVolumeChanged",{"muted":"false","volume":"95"}]]]"#,
+            ));
+        }
+        default_response(req)
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    assert_eq!(client.current_volume(), None);
+
+    assert_eq!(client.volume_up(10).await.unwrap(), 100);
+    // Later steps build on the level just set, without asking again
+    assert_eq!(client.volume_down(30).await.unwrap(), 70);
+    assert_eq!(client.volume_down(100).await.unwrap(), 0);
+    let volume = client.current_volume().unwrap();
+    assert_eq!(volume.level, 0);
+    assert!(!volume.muted);
+
+    let volumes: Vec<_> = server
+        .requests()
+        .iter()
+        .filter_map(|req| req.form_param("req0_volume"))
+        .collect();
+    assert_eq!(volumes, vec!["100", "70", "0"]);
+    assert_eq!(
+        server.commands(),
+        vec!["getVolume", "setVolume", "setVolume", "setVolume"]
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_shutdown_times_out_and_reaper_terminates_on_drop() {
+    let is_terminate =
+        |r: &common::MockRequest| r.form_param("TYPE").as_deref() == Some("terminate");
+
+    // A screen that never answers the terminate request
+    let server = MockServer::start(move |req| {
+        if req.is_poll() || is_terminate(req) {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    let states = client.state_receiver();
+    let started = std::time::Instant::now();
+    let result = client.shutdown(Duration::from_millis(200)).await;
+    assert!(matches!(result, Err(LoungeError::ShutdownTimedOut(_))));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(*states.borrow(), ConnectionState::Disconnected);
+    // The state channel closes once the aborted manager task is gone
+    assert!(wait_until(|| states.has_changed().is_err()).await);
+
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    client.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert!(server.requests().iter().any(is_terminate));
+
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig {
+        reaper: Some(tokio::runtime::Handle::current()),
+        ..LoungeConfig::default()
+    });
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    drop(client);
+    assert!(wait_until(|| server.requests().iter().any(is_terminate)).await);
+}
+
+#[tokio::test]
+async fn test_drop_without_reaper_ends_manager_without_terminating() {
+    let server = MockServer::lounge(vec![]).await;
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    let states = client.state_receiver();
+    drop(client);
+    // Closed once nothing holds the state sender, i.e. the manager task is gone
+    assert!(wait_until(|| states.has_changed().is_err()).await);
+    // Without a reaper the session is left to expire on the screen
+    assert!(!server
+        .requests()
+        .iter()
+        .any(|r| r.form_param("TYPE").as_deref() == Some("terminate")));
+}
+
+#[tokio::test]
+async fn test_pair_with_tv_code_pads_and_redeems_code() {
+    let server = MockServer::start(|req| {
+        if req.path.ends_with("/pairing/get_screen") {
+            match req.form_param("pairing_code").as_deref() {
+                Some("000012345678") => MockResponse::ok(
+                    r#"{"screen":{"screenId":"tv-screen","loungeToken":"tv-token","name":"Living Room"}}"#,
+                ),
+                _ => MockResponse::status(404, "Unknown pairing code"),
+            }
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let config = server.config();
+
+    let screen = LoungeClient::pair_with_tv_code_with_config("1234-5678", &config)
+        .await
+        .unwrap();
+    assert_eq!(screen.screen_id, "tv-screen");
+    assert_eq!(screen.lounge_token, "tv-token");
+
+    let err = LoungeClient::pair_with_tv_code_with_config("1234-567a", &config)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LoungeError::InvalidPairingCode(_)));
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    // Rejected before any request
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_filtered_subscriptions_have_independent_queues() {
+    let mut entries: Vec<String> = (0..10)
+        .map(|i| {
+            format!(
+                r#"[{},["onStateChange",{{"state":"1","currentTime":"{}"}}]]"#,
+                i + 3,
+                i
+            )
+        })
+        .collect();
+    entries.push(r#"[13,["nowPlaying",{"videoId":"np-video","state":"1"}]]"#.to_string());
+    let server = MockServer::lounge(vec![format!("[{}]", entries.join(","))]).await;
+    let client = server.client(LoungeConfig {
+        filtered_event_capacity: 3,
+        ..LoungeConfig::default()
+    });
+    // Never read until the end
+    let mut slow = client.subscribe_filtered(EventKind::StateChange);
+    let mut playback =
+        client.subscribe_filtered(EventKind::NowPlaying | EventKind::SessionEstablished);
+    client.connect().await.unwrap();
+
+    let received = tokio::time::timeout(Duration::from_secs(5), async {
+        vec![
+            playback.recv().await.unwrap(),
+            playback.recv().await.unwrap(),
+        ]
+    })
+    .await
+    .expect("filtered events not delivered");
+    assert!(matches!(received[0], LoungeEvent::SessionEstablished));
+    assert!(matches!(&received[1], LoungeEvent::NowPlaying(np) if np.video_id == "np-video"));
+    assert_eq!(playback.dropped(), 0);
+
+    assert!(wait_until(|| slow.dropped() == 7).await);
+    for _ in 0..3 {
+        assert!(matches!(slow.try_recv(), Some(LoungeEvent::StateChange(_))));
+    }
+    assert!(slow.try_recv().is_none());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_connect_with_expired_snapshot_falls_back_to_bind() {
+    let server = MockServer::start(|req| {
+        if req.query_param("SID") == Some("expired-sid") {
+            MockResponse::status(410, "Gone")
+        } else if req.is_poll() {
+            MockResponse::hang()
+        } else {
+            default_response(req)
+        }
+    })
+    .await;
+    let client = server.client(LoungeConfig::default());
+    let snapshot = SessionSnapshot {
+        sid: "expired-sid".to_string(),
+        gsessionid: "expired-gsession".to_string(),
+        rid: 10,
+        aid: 3,
+        command_offset: 2,
+    };
+    // Persisted and loaded again, as across a restart
+    let snapshot: SessionSnapshot =
+        serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+    client.connect_with_snapshot(Some(snapshot)).await.unwrap();
+    assert!(
+        wait_until(|| server
+            .requests()
+            .iter()
+            .any(|req| req.is_poll() && req.query_param("SID") == Some(common::SID)))
+        .await
+    );
+    let requests = server.requests();
+    assert!(requests[0].is_poll());
+    assert_eq!(requests[0].query_param("SID"), Some("expired-sid"));
+    assert!(requests
+        .iter()
+        .any(|req| req.method == "POST" && req.query_param("SID").is_none()));
+    assert_eq!(
+        client.session_snapshot().await.unwrap().sid,
+        common::SID.to_string()
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[derive(Default)]
+struct CountingMetrics {
+    poll_cycles: AtomicUsize,
+    messages: AtomicUsize,
+    decode_errors: AtomicUsize,
+    binds: Mutex<Vec<bool>>,
+    reconnects: AtomicUsize,
+    commands: Mutex<Vec<(Vec<&'static str>, bool)>>,
+}
+
+impl Metrics for CountingMetrics {
+    fn poll_cycle(&self, _stats: &PollCycleStats) {
+        self.poll_cycles.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn message_decoded(&self, _bytes: usize) {
+        self.messages.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn decode_error(&self) {
+        self.decode_errors.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn bind_attempt(&self, _elapsed: Duration, established: bool) {
+        self.binds.lock().unwrap().push(established);
+    }
+
+    fn reconnect_scheduled(&self, _backoff: Duration) {
+        self.reconnects.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn command_sent(&self, commands: &[PlaybackCommand], _elapsed: Duration, succeeded: bool) {
+        let names = commands.iter().map(PlaybackCommand::name).collect();
+        self.commands.lock().unwrap().push((names, succeeded));
+    }
+}
+
+#[tokio::test]
+async fn test_metrics_report_polls_decoding_binds_and_commands() {
+    let polls = AtomicUsize::new(0);
+    // Two messages, then a frame the codec can't read, which forces a re-bind
+    let server = MockServer::start(move |req| {
+        if req.is_poll() {
+            if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return MockResponse::ok(format!(
+                    "{}{}garbage\n",
+                    frame(r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#),
+                    frame(r#"[[4,["onAutoplayModeChanged",{"autoplayMode":"ENABLED"}]]]"#),
+                ));
+            }
+            return MockResponse::hang();
+        }
+        default_response(req)
+    })
+    .await;
+    let metrics = Arc::new(CountingMetrics::default());
+    let client = LoungeClient::builder("mock-screen", "mock-token", "Test Remote")
+        .config(LoungeConfig {
+            min_backoff: Duration::from_millis(10),
+            ..server.config()
+        })
+        .metrics(metrics.clone())
+        .build()
+        .unwrap();
+
+    client.connect().await.unwrap();
+    assert!(wait_until(|| !metrics.binds.lock().unwrap().is_empty()).await);
+    wait_for_connected(&client).await;
+    client.send_command(PlaybackCommand::Play).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    assert_eq!(metrics.messages.load(Ordering::SeqCst), 2);
+    assert_eq!(metrics.decode_errors.load(Ordering::SeqCst), 1);
+    assert!(metrics.poll_cycles.load(Ordering::SeqCst) >= 1);
+    assert_eq!(metrics.reconnects.load(Ordering::SeqCst), 1);
+    assert_eq!(*metrics.binds.lock().unwrap(), [true]);
+    assert_eq!(*metrics.commands.lock().unwrap(), [(vec!["play"], true)]);
+}
+
+#[tokio::test]
+async fn test_manager_does_not_restart_unpaired_screen() {
+    let binds = Arc::new(AtomicUsize::new(0));
+    let counted = binds.clone();
+    // Every bind is rejected and the refresh finds the screen unpaired
+    let server = MockServer::start(move |req| {
+        if req.path.ends_with("/pairing/get_lounge_token_batch") {
+            return MockResponse::ok(r#"{"screens":[]}"#);
+        }
+        if req.method == "POST" && req.path.ends_with("/bc/bind") {
+            counted.fetch_add(1, Ordering::SeqCst);
+            return MockResponse::status(401, "Unauthorized");
+        }
+        default_response(req)
+    })
+    .await;
+
+    let manager = LoungeManager::new().with_restart_delay(Duration::from_millis(20));
+    manager
+        .add(
+            LoungeClient::new("tv", "token", "Test Remote", None, None)
+                .with_config(server.config())
+                .unwrap(),
+        )
+        .await;
+    let results = manager.connect_all().await;
+    assert!(matches!(results["tv"], Err(LoungeError::ScreenUnpaired)));
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(binds.load(Ordering::SeqCst), 1);
+    match &manager.states()["tv"] {
+        ConnectionState::Failed(failure) => {
+            assert!(failure.fatal, "{:?}", failure);
+            assert_eq!(failure.kind, ErrorKind::Auth);
+        }
+        other => panic!("Expected Failed, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_snapshot_after_go_idle_resumes_without_new_bind() {
+    let server = MockServer::lounge(vec![]).await;
+    let is_new_bind = |r: &common::MockRequest| {
+        r.method == "POST"
+            && r.path.ends_with("/bc/bind")
+            && r.query_param("SID").is_none()
+            && !r.body.contains("TYPE=terminate")
+    };
+
+    let client = server.client(LoungeConfig::default());
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    client.go_idle().await.unwrap();
+    let snapshot = client.session_snapshot().await;
+    assert!(snapshot.is_some());
+    drop(client);
+
+    // As after a restart
+    let client = server.client(LoungeConfig::default());
+    client.connect_with_snapshot(snapshot).await.unwrap();
+    wait_for_connected(&client).await;
+    let requests = server.requests();
+    assert_eq!(requests.iter().filter(|r| is_new_bind(r)).count(), 1);
+    assert!(!requests
+        .iter()
+        .any(|r| r.form_param("TYPE").as_deref() == Some("terminate")));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_keeps_working_with_poisoned_locks() {
+    let server = MockServer::lounge(vec![
+        r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string(),
+        r#"[[4,["nowPlaying",{"videoId":"abc","currentTime":"5","duration":"100","state":"1","cpn":"cpn-1"}]]]"#.to_string(),
+    ])
+    .await;
+    let client = server.client(LoungeConfig::default());
+    // As if a panic elsewhere had happened while they were held
+    client.poison_locks();
+    let mut events = client.event_receiver();
+
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !matches!(events.recv().await, Ok(LoungeEvent::NowPlaying(_))) {}
+    })
+    .await
+    .expect("no NowPlaying event received");
+
+    assert_eq!(client.current_volume().map(|v| v.level), Some(40));
+    assert_eq!(
+        client.player_state().now_playing.map(|n| n.video_id),
+        Some("abc".to_string())
+    );
+    assert!(client.message_size_stats().count >= 2);
+    assert!(client.last_event_at().is_some());
+    assert!(client.connected_duration().is_some());
+    client.send_command(PlaybackCommand::Play).await.unwrap();
+    assert_eq!(server.commands(), vec!["play"]);
+    client.disconnect().await.unwrap();
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking_client_connect_and_command() {
+    use youtube_lounge_rs::blocking::BlockingLoungeClient;
+
+    let server_runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = server_runtime.block_on(MockServer::lounge(vec![
+        r#"[[3,["onVolumeChanged",{"muted":"false","volume":"40"}]]]"#.to_string(),
+    ]));
+    let mut client = BlockingLoungeClient::new(server.client(LoungeConfig::default())).unwrap();
+
+    client.connect().unwrap();
+    loop {
+        match client.recv_event() {
+            Some(LoungeEvent::VolumeChanged(volume)) => {
+                assert_eq!(volume.volume, "40");
+                break;
+            }
+            Some(_) => continue,
+            None => panic!("event stream closed"),
+        }
+    }
+    client.send_command(PlaybackCommand::Play).unwrap();
+    assert_eq!(server.commands(), vec!["play"]);
+    client.disconnect().unwrap();
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_lounge_server_full_cycle() {
+    use youtube_lounge_rs::mock::MockLoungeServer;
+
+    let server = MockLoungeServer::start().await.unwrap();
+    let screen = LoungeClient::pair_with_screen_with_config(
+        MockLoungeServer::PAIRING_CODE,
+        &server.config(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(screen.screen_id, MockLoungeServer::SCREEN_ID);
+    let client = LoungeClient::builder(&screen.screen_id, &screen.lounge_token, "Test Remote")
+        .config(server.config())
+        .build()
+        .unwrap();
+    client.connect().await.unwrap();
+    wait_for_connected(&client).await;
+
+    // Commands are echoed with the event a TV would send
+    let outcome = client
+        .send_command_ack(
+            PlaybackCommand::set_playlist("dQw4w9WgXcQ".to_string()),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert!(
+        matches!(outcome, CommandOutcome::Acknowledged(_)),
+        "{:?}",
+        outcome
+    );
+    let outcome = client
+        .send_command_ack(
+            PlaybackCommand::SetVolume { volume: 25 },
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert!(
+        matches!(outcome, CommandOutcome::Acknowledged(_)),
+        "{:?}",
+        outcome
+    );
+    let commands = server.commands();
+    assert_eq!(commands[0].name, "setPlaylist");
+    assert_eq!(commands[0].params["videoId"], "dQw4w9WgXcQ");
+    assert_eq!(commands[1].params["volume"], "25");
+
+    // Pushed events arrive like any other
+    let mut events = client.event_receiver();
+    server.push_event(
+        "onAutoplayModeChanged",
+        serde_json::json!({"autoplayMode": "DISABLED"}),
+    );
+    let event = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Ok(LoungeEvent::AutoplayModeChanged(mode)) = events.recv().await {
+                return mode;
+            }
+        }
+    })
+    .await
+    .expect("no AutoplayModeChanged event received");
+    assert_eq!(event.autoplay_mode, "DISABLED");
+
+    // An expired token is refreshed from the token endpoint
+    server.expire_token();
+    client
+        .send_command_with_refresh(PlaybackCommand::Pause)
+        .await
+        .unwrap();
+    assert_eq!(server.commands().last().unwrap().name, "pause");
+
+    client.disconnect().await.unwrap();
+}
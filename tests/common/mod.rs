@@ -0,0 +1,327 @@
+// Minimal in-process Lounge API server for exercising the client end-to-end
+// without a TV. Speaks just enough HTTP/1.1 for reqwest (one request per connection).
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use youtube_lounge_rs::{LoungeClient, LoungeConfig};
+
+pub const SID: &str = "mock-sid";
+pub const GSESSIONID: &str = "mock-gsessionid";
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn query_param(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn form(&self) -> Vec<(String, String)> {
+        serde_urlencoded::from_str(&self.body).unwrap_or_default()
+    }
+
+    pub fn form_param(&self, key: &str) -> Option<String> {
+        self.form()
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// The command name (`req0__sc`) if this is a command request.
+    pub fn command(&self) -> Option<String> {
+        self.form_param("req0__sc")
+    }
+
+    pub fn is_poll(&self) -> bool {
+        self.method == "GET" && self.path.ends_with("/bc/bind")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// Keep the connection open without answering (an idle long poll).
+    pub hang: bool,
+    /// Send the headers, then keep the body open without data (a quiet long poll).
+    pub stall_body: bool,
+    /// Wait this long before answering.
+    pub delay: Option<Duration>,
+}
+
+impl MockResponse {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self::status(200, body)
+    }
+
+    pub fn status(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            hang: false,
+            stall_body: false,
+            delay: None,
+        }
+    }
+
+    pub fn hang() -> Self {
+        Self {
+            hang: true,
+            ..Self::ok("")
+        }
+    }
+
+    pub fn stall_body() -> Self {
+        Self {
+            stall_body: true,
+            ..Self::ok("")
+        }
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+pub struct MockServer {
+    pub base: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let log = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, handler.clone(), log.clone()));
+            }
+        });
+
+        Self {
+            base: format!("http://{}/api/lounge", addr),
+            requests,
+        }
+    }
+
+    /// A server that completes the bind handshake, answers the first long poll
+    /// with `events` (each a JSON array of `[aid, [type, payload]]` entries) and
+    /// accepts every command.
+    pub async fn lounge(events: Vec<String>) -> Self {
+        let first_poll_served = AtomicBool::new(false);
+        Self::start(move |req| {
+            if req.is_poll() {
+                if first_poll_served.swap(true, Ordering::SeqCst) {
+                    MockResponse::hang()
+                } else {
+                    MockResponse::ok(events.iter().map(|e| frame(e)).collect::<String>())
+                }
+            } else {
+                default_response(req)
+            }
+        })
+        .await
+    }
+
+    pub fn config(&self) -> LoungeConfig {
+        LoungeConfig {
+            api_base: self.base.clone(),
+            ..LoungeConfig::default()
+        }
+    }
+
+    pub fn client(&self, config: LoungeConfig) -> LoungeClient {
+        LoungeClient::new("mock-screen", "mock-token", "Test Remote", None, None)
+            .with_config(LoungeConfig {
+                api_base: self.base.clone(),
+                ..config
+            })
+            .unwrap()
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Names of all commands received so far, in arrival order.
+    pub fn commands(&self) -> Vec<String> {
+        self.requests()
+            .iter()
+            .filter_map(MockRequest::command)
+            .collect()
+    }
+}
+
+/// Default answers for the non-polling endpoints.
+pub fn default_response(req: &MockRequest) -> MockResponse {
+    if req.path.ends_with("/bc/bind") && req.method == "POST" {
+        if req.query_param("SID").is_none() && !req.body.contains("TYPE=terminate") {
+            return MockResponse::ok(bind_body(SID, GSESSIONID));
+        }
+        return MockResponse::ok("");
+    }
+    if req.path.ends_with("/pairing/get_screen_availability") {
+        return MockResponse::ok(r#"{"screens":[]}"#);
+    }
+    MockResponse::status(404, "not found")
+}
+
+/// Length-prefix a message the way the Lounge long poll does.
+pub fn frame(message: &str) -> String {
+    let content = format!("{}\n", message);
+    format!("{}\n{}", content.len(), content)
+}
+
+pub fn bind_body(sid: &str, gsessionid: &str) -> String {
+    frame(&format!(
+        r#"[[0,["c","{}","",8]],[1,["S","{}"]],[2,["noop"]]]"#,
+        sid, gsessionid
+    ))
+}
+
+/// Wait (up to 5s) until `predicate` holds, polling every 10ms.
+pub async fn wait_until(mut predicate: impl FnMut() -> bool) -> bool {
+    for _ in 0..500 {
+        if predicate() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    predicate()
+}
+
+pub async fn wait_for_connected(client: &LoungeClient) {
+    assert!(
+        wait_until(|| format!("{:?}", client.current_state()) == "Connected").await,
+        "client never reached Connected, state: {:?}",
+        client.current_state()
+    );
+}
+
+async fn serve_connection(
+    mut stream: TcpStream,
+    handler: Arc<Handler>,
+    log: Arc<Mutex<Vec<MockRequest>>>,
+) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    log.lock().unwrap().push(request.clone());
+    let response = handler(&request);
+    if response.hang {
+        hold_open(&mut stream).await;
+        return;
+    }
+    if response.stall_body {
+        let head = "HTTP/1.1 200 Mock\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n";
+        if stream.write_all(head.as_bytes()).await.is_ok() {
+            hold_open(&mut stream).await;
+        }
+        return;
+    }
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut out = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    out.push_str(&response.body);
+    let _ = stream.write_all(out.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Hold the connection open until the client gives up on it.
+async fn hold_open(stream: &mut TcpStream) {
+    let mut buf = [0u8; 64];
+    while let Ok(n) = stream.read(&mut buf).await {
+        if n == 0 {
+            break;
+        }
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<MockRequest> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let content_length = headers
+        .iter()
+        .find(|(k, _)| k == "content-length")
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    while data.len() < header_end + content_length {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+    let body = String::from_utf8_lossy(&data[header_end..]).to_string();
+
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    Some(MockRequest {
+        method,
+        path: path.to_string(),
+        query: serde_urlencoded::from_str(query).unwrap_or_default(),
+        headers,
+        body,
+    })
+}
@@ -1,9 +1,44 @@
 use serde_json::json;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
 use youtube_lounge_rs::{
-    youtube_parse, AdState, Device, DeviceInfo, LoungeClient, LoungeError, LoungeEvent, NowPlaying,
-    PlaybackCommand, PlaybackState, Screen,
+    replay, youtube_parse, AdState, Chapter, ClockSkewEstimator, ConnectionState, CurrentState,
+    Device, DeviceInfo, DpadKey, ErrorContext, ErrorKind, EventHistory, EventKind, EventKindSet,
+    Failure, JsonFileTokenStore, LoungeClient, LoungeError, LoungeEvent, MessageSizeStats,
+    NowPlaying, PlaybackCommand, PlaybackSession, PlaybackState, PlaybackStatus, PlaylistAction,
+    PlaylistModeChanged, PlaylistModified, Screen, StateDiff, StoredScreen, ThroughputMeter,
+    TokenStore, VideoData, VolumeChanged,
 };
 
+// Counts the bytes allocated on each thread, so tests can measure their own
+// allocations while others run in parallel
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATED.try_with(|bytes| bytes.set(bytes.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn bytes_allocated_by(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.with(Cell::get);
+    f();
+    ALLOCATED.with(Cell::get) - before
+}
+
 // Test model serialization and deserialization
 #[test]
 fn test_models() {
@@ -92,6 +127,7 @@ fn test_events() {
         loaded_time: "60.0".to_string(),
         seekable_start_time: "0.0".to_string(),
         seekable_end_time: "180.0".to_string(),
+        chapter_data: None,
     };
     let event = LoungeEvent::NowPlaying(now_playing);
 
@@ -139,6 +175,12 @@ fn test_youtube_parse_module() {
     // Test parse_list
     let list = youtube_parse::parse_list("item1,item2,item3");
     assert_eq!(list, vec!["item1", "item2", "item3"]);
+
+    // Test parse_int_list
+    assert_eq!(
+        youtube_parse::parse_int_list("[0,1080, 720,auto]"),
+        vec![0, 1080, 720]
+    );
 }
 
 // Test client constructors
@@ -239,6 +281,140 @@ fn test_lounge_error() {
     assert!(error_message.contains("Test error"));
 }
 
+// Test every error is classified as retryable, fatal, or neither
+#[tokio::test]
+async fn test_lounge_error_classification() {
+    let request_failed = reqwest::Client::new().get("not a url").build().unwrap_err();
+    let join_error = tokio::spawn(std::future::pending::<()>());
+    join_error.abort();
+    let join_error = join_error.await.unwrap_err();
+
+    let retryable = [
+        LoungeError::RequestFailed(request_failed),
+        LoungeError::SessionLost,
+        LoungeError::TokenExpired,
+        LoungeError::InvalidResponse("bad".to_string()),
+        LoungeError::IoError(std::io::Error::other("reset")),
+        LoungeError::SessionInvalidatedByServer(410),
+        LoungeError::ScreenOffline,
+        LoungeError::SessionIdExtractionFailed {
+            body_preview: String::new(),
+        },
+        LoungeError::TokenRefreshFailed(Box::new(LoungeError::SessionLost)),
+        LoungeError::RateLimited {
+            retry_after: Some(Duration::from_secs(60)),
+        },
+        LoungeError::VolumeUnknown,
+    ];
+    for err in &retryable {
+        assert!(err.is_retryable(), "{:?} should be retryable", err);
+        assert!(!err.is_fatal(), "{:?} should not be fatal", err);
+    }
+
+    let fatal = [
+        LoungeError::ScreenUnpaired,
+        LoungeError::DuplicateScreen("screen".to_string()),
+        LoungeError::TaskJoinError(join_error),
+        LoungeError::TokenRefreshFailed(Box::new(LoungeError::ScreenUnpaired)),
+    ];
+    for err in &fatal {
+        assert!(err.is_fatal(), "{:?} should be fatal", err);
+        assert!(!err.is_retryable(), "{:?} should not be retryable", err);
+    }
+
+    let neither = [
+        LoungeError::ParseFailed(serde_json::from_str::<u32>("x").unwrap_err()),
+        LoungeError::UrlEncodingFailed(serde_urlencoded::to_string(vec![1]).unwrap_err()),
+        LoungeError::NumericParseFailed("x".parse::<f64>().unwrap_err()),
+        LoungeError::ConnectionClosed,
+        LoungeError::ShutdownTimedOut(Duration::from_secs(1)),
+        LoungeError::InvalidPairingCode("x".to_string()),
+        LoungeError::QueueIndexOutOfRange { index: 3, len: 2 },
+    ];
+    for err in &neither {
+        assert!(!err.is_retryable() && !err.is_fatal(), "{:?}", err);
+    }
+}
+
+// Test error kinds, and that attached context doesn't change the classification
+#[test]
+fn test_lounge_error_kinds_and_context() {
+    let kinds = [
+        (LoungeError::TokenExpired, ErrorKind::Auth),
+        (LoungeError::ScreenUnpaired, ErrorKind::Auth),
+        (
+            LoungeError::SessionInvalidatedByServer(400),
+            ErrorKind::Session,
+        ),
+        (LoungeError::ScreenOffline, ErrorKind::Unavailable),
+        (
+            LoungeError::RateLimited { retry_after: None },
+            ErrorKind::RateLimited,
+        ),
+        (
+            LoungeError::InvalidResponse("bad".to_string()),
+            ErrorKind::Protocol,
+        ),
+        (LoungeError::ConnectionClosed, ErrorKind::Closed),
+        (
+            LoungeError::ShutdownTimedOut(Duration::from_secs(1)),
+            ErrorKind::Closed,
+        ),
+        (
+            LoungeError::QueueIndexOutOfRange { index: 3, len: 2 },
+            ErrorKind::InvalidInput,
+        ),
+        (
+            LoungeError::TokenRefreshFailed(Box::new(LoungeError::ScreenOffline)),
+            ErrorKind::Unavailable,
+        ),
+    ];
+    for (err, kind) in &kinds {
+        assert_eq!(err.kind(), *kind, "{:?}", err);
+    }
+    assert!(LoungeError::TokenExpired.is_auth());
+    assert!(!LoungeError::TokenExpired.is_terminal());
+    assert!(LoungeError::ScreenUnpaired.is_terminal());
+    assert!(LoungeError::ConnectionClosed.is_terminal());
+    assert!(!LoungeError::SessionLost.is_terminal());
+
+    let context = ErrorContext {
+        screen_id: Some("screen".to_string()),
+        endpoint: Some("bc/bind".to_string()),
+        status: Some(401),
+        body_excerpt: Some("Unauthorized".to_string()),
+    };
+    let err = LoungeError::TokenExpired.with_context(context.clone());
+    assert_eq!(err.context(), Some(&context));
+    assert!(matches!(err.without_context(), LoungeError::TokenExpired));
+    assert!(err.is_auth() && err.is_retryable() && !err.is_fatal());
+    assert_eq!(
+        err.to_string(),
+        r#"Token expired (HTTP 401) (screen screen, bc/bind, HTTP 401, body "Unauthorized")"#
+    );
+    assert!(std::error::Error::source(&err).is_some());
+
+    // New context replaces the old rather than nesting
+    let err = err.with_context(ErrorContext::default());
+    assert_eq!(err.context(), Some(&ErrorContext::default()));
+    assert!(matches!(err.without_context(), LoungeError::TokenExpired));
+    assert_eq!(LoungeError::SessionLost.context(), None);
+}
+
+// Test message size stats track min, max and average
+#[test]
+fn test_message_size_stats() {
+    let mut stats = MessageSizeStats::default();
+    assert_eq!(stats.average(), None);
+    for size in [120, 40, 2000] {
+        stats.record(size);
+    }
+    assert_eq!(stats.count, 3);
+    assert_eq!(stats.min, 40);
+    assert_eq!(stats.max, 2000);
+    assert_eq!(stats.average(), Some(720.0));
+}
+
 // Test thumbnail URL generation
 #[test]
 fn test_thumbnail_url() {
@@ -285,3 +461,753 @@ async fn test_event_receiver() {
         Err(_) => panic!("Failed to receive event"),
     }
 }
+
+// Test VideoData placeholder vs populated metadata
+#[test]
+fn test_video_data_populated() {
+    let placeholder = VideoData::for_video("dQw4w9WgXcQ");
+    assert_eq!(placeholder.video_id, "dQw4w9WgXcQ");
+    assert!(!placeholder.is_populated());
+    assert_eq!(placeholder.title(), None);
+    assert_eq!(placeholder.author(), None);
+
+    let populated = VideoData {
+        title: "Never Gonna Give You Up".to_string(),
+        author: "Rick Astley".to_string(),
+        ..placeholder
+    };
+    assert!(populated.is_populated());
+    assert_eq!(populated.title(), Some("Never Gonna Give You Up"));
+    assert_eq!(populated.author(), Some("Rick Astley"));
+
+    // Sessions always carry the video ID in their metadata, even without a title
+    let now_playing: NowPlaying = serde_json::from_value(json!({
+        "videoId": "dQw4w9WgXcQ",
+        "currentTime": "1.0",
+        "duration": "212.0",
+        "state": "1"
+    }))
+    .unwrap();
+    let state: PlaybackState = serde_json::from_value(json!({
+        "currentTime": "1.0",
+        "duration": "212.0",
+        "loadedTime": "10.0",
+        "state": "1"
+    }))
+    .unwrap();
+    let session = PlaybackSession::new(&now_playing, &state).unwrap();
+    let data = session.video_data.expect("session should carry video data");
+    assert_eq!(data.video_id, "dQw4w9WgXcQ");
+    assert!(!data.is_populated());
+}
+
+// Test the local pairing code format check
+#[test]
+fn test_pairing_code_format() {
+    for valid in [
+        "123456789012",
+        "123 456 789 012",
+        "1234-5678-9012",
+        " 123456789012 ",
+    ] {
+        assert!(
+            LoungeClient::is_valid_pairing_code_format(valid),
+            "{:?} should be valid",
+            valid
+        );
+    }
+    for invalid in [
+        "",
+        "12345678901",
+        "1234567890123",
+        "12345678901a",
+        "123_456_789_012",
+    ] {
+        assert!(
+            !LoungeClient::is_valid_pairing_code_format(invalid),
+            "{:?} should be invalid",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_tv_code_normalization() {
+    for (code, expected) in [
+        ("123 456 789 012", "123456789012"),
+        ("1234-5678-9012", "123456789012"),
+        ("12 345", "000000012345"),
+        (" 7 ", "000000000007"),
+    ] {
+        assert_eq!(LoungeClient::normalize_tv_code(code).unwrap(), expected);
+    }
+    for invalid in ["", " - ", "1234567890123", "12345a", "123_456"] {
+        assert!(
+            matches!(
+                LoungeClient::normalize_tv_code(invalid),
+                Err(LoungeError::InvalidPairingCode(ref code)) if code == invalid
+            ),
+            "{:?} should be rejected",
+            invalid
+        );
+    }
+}
+
+// Test connection fingerprints are stable and distinguish clients
+#[tokio::test]
+async fn test_connection_fingerprint() {
+    let client = LoungeClient::new("screen_a", "token", "Device", Some("device_1"), None);
+    let fingerprint = client.connection_fingerprint();
+    assert_eq!(fingerprint.len(), 12);
+    assert_eq!(fingerprint, client.connection_fingerprint());
+
+    // Same device and screen yield the same fingerprint, even across client instances
+    let same = LoungeClient::new("screen_a", "other_token", "Device", Some("device_1"), None);
+    assert_eq!(fingerprint, same.connection_fingerprint());
+
+    let other_screen = LoungeClient::new("screen_b", "token", "Device", Some("device_1"), None);
+    let other_device = LoungeClient::new("screen_a", "token", "Device", Some("device_2"), None);
+    assert_ne!(fingerprint, other_screen.connection_fingerprint());
+    assert_ne!(fingerprint, other_device.connection_fingerprint());
+}
+
+// Test optional durations distinguish "not reported" from a genuine zero
+#[test]
+fn test_now_playing_duration_opt() {
+    let now_playing = |duration: &str| -> NowPlaying {
+        serde_json::from_value(json!({
+            "videoId": "dQw4w9WgXcQ",
+            "duration": duration,
+            "seekableStartTime": "0",
+            "state": "1"
+        }))
+        .unwrap()
+    };
+
+    assert_eq!(now_playing("").duration_opt(), None);
+    assert_eq!(now_playing("0").duration_opt(), Some(0.0));
+    assert_eq!(now_playing("180.0").duration_opt(), Some(180.0));
+
+    let live = now_playing("0");
+    assert_eq!(live.seekable_start_time_opt(), Some(0.0));
+    assert_eq!(live.seekable_end_time_opt(), None);
+}
+
+// Test chapter markers are parsed from nowPlaying when present
+#[test]
+fn test_now_playing_chapters() {
+    let with_chapters: NowPlaying = serde_json::from_value(json!({
+        "videoId": "dQw4w9WgXcQ",
+        "state": "1",
+        "chapters": r#"[{"title":"Chorus","startTime":"43.5"},{"title":"Intro","startTime":0}]"#
+    }))
+    .unwrap();
+    assert_eq!(
+        with_chapters.chapters(),
+        vec![
+            Chapter {
+                start: 0.0,
+                title: "Intro".to_string()
+            },
+            Chapter {
+                start: 43.5,
+                title: "Chorus".to_string()
+            },
+        ]
+    );
+
+    let without_chapters: NowPlaying = serde_json::from_value(json!({
+        "videoId": "dQw4w9WgXcQ",
+        "state": "1"
+    }))
+    .unwrap();
+    assert!(without_chapters.chapters().is_empty());
+}
+
+// Test the rolling event-stream throughput over known byte volumes
+#[test]
+fn test_throughput_meter() {
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+    let mut meter = ThroughputMeter::new_at(Duration::from_secs(10), start);
+    assert_eq!(meter.rate_at(start), None);
+    assert_eq!(meter.rate_at(at(1)), Some(0.0));
+
+    meter.record_at(at(1), 1000);
+    meter.record_at(at(2), 1000);
+    // Averaged over the time since measuring started while shorter than the window
+    assert_eq!(meter.rate_at(at(2)), Some(1000.0));
+    assert_eq!(meter.rate_at(at(4)), Some(500.0));
+
+    meter.record_at(at(11), 500);
+    // The sample at 1s has left the 10s window
+    assert_eq!(meter.rate_at(at(11)), Some(150.0));
+
+    // Stalled: nothing received for a full window
+    assert_eq!(meter.rate_at(at(30)), Some(0.0));
+}
+
+// Test the event history evicts by age and caps its size
+#[test]
+fn test_event_history_eviction() {
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+    let mut history = EventHistory::new(Duration::from_secs(10), 3);
+
+    history.push_at(at(0), LoungeEvent::SessionEstablished);
+    history.push_at(at(5), LoungeEvent::ScreenDisconnected);
+    assert_eq!(history.recent_at(at(5)).len(), 2);
+
+    // The first event falls out of the window
+    let recent = history.recent_at(at(12));
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].0, at(5));
+    assert!(matches!(recent[0].1, LoungeEvent::ScreenDisconnected));
+
+    // Pushing evicts expired events and enforces the cap
+    for secs in 20..24 {
+        history.push_at(at(secs), LoungeEvent::SessionEstablished);
+    }
+    let recent = history.recent_at(at(23));
+    assert_eq!(
+        recent.iter().map(|(t, _)| *t).collect::<Vec<_>>(),
+        [at(21), at(22), at(23)]
+    );
+}
+
+// Test clock skew estimation from timed playing states
+#[test]
+fn test_clock_skew_estimate() {
+    let state = |current_time: &str, status: &str| -> PlaybackState {
+        serde_json::from_value(json!({
+            "currentTime": current_time,
+            "state": status,
+            "cpn": "cpn-1"
+        }))
+        .unwrap()
+    };
+    let start = Instant::now();
+    let mut estimator = ClockSkewEstimator::default();
+    estimator.observe_at(start, &state("10.0", "1"));
+    assert_eq!(estimator.estimate(), None);
+
+    // 10s passed locally while the TV advanced 9.75s
+    estimator.observe_at(start + Duration::from_secs(10), &state("19.75", "1"));
+    let skew = estimator.estimate().unwrap();
+    assert!((skew.as_secs_f64() - 0.25).abs() < 1e-6, "skew: {:?}", skew);
+
+    // A seek restarts the measurement without disturbing the last estimate
+    estimator.observe_at(start + Duration::from_secs(11), &state("120.0", "1"));
+    assert_eq!(estimator.estimate(), Some(skew));
+
+    // Pausing breaks the run; the next playing state only sets a new baseline
+    estimator.observe_at(start + Duration::from_secs(12), &state("121.0", "2"));
+    estimator.observe_at(start + Duration::from_secs(30), &state("121.0", "1"));
+    estimator.observe_at(start + Duration::from_secs(32), &state("123.0", "1"));
+    assert_eq!(estimator.estimate(), Some(Duration::ZERO));
+}
+
+// Test ConnectionState round-trips through JSON for IPC
+#[test]
+fn test_connection_state_json() {
+    let states = [
+        ConnectionState::Disconnected,
+        ConnectionState::Connecting,
+        ConnectionState::Connected,
+        ConnectionState::WaitingToReconnect {
+            backoff: Duration::from_millis(1500),
+            retry_at: Instant::now(),
+        },
+        ConnectionState::Failed(Failure::new(
+            "Token refresh failed",
+            &LoungeError::ScreenUnpaired,
+        )),
+        ConnectionState::Stopping,
+        ConnectionState::Idle,
+    ];
+    for state in states {
+        let json = serde_json::to_string(&state).unwrap();
+        let parsed: ConnectionState = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, state, "round trip through {}", json);
+    }
+
+    let waiting = ConnectionState::WaitingToReconnect {
+        backoff: Duration::from_millis(1500),
+        retry_at: Instant::now(),
+    };
+    assert_eq!(
+        serde_json::to_value(&waiting).unwrap(),
+        json!({"WaitingToReconnect": {"backoff": 1500}})
+    );
+    assert_eq!(
+        serde_json::to_value(ConnectionState::Connected).unwrap(),
+        json!("Connected")
+    );
+    assert_eq!(
+        serde_json::to_value(ConnectionState::Failed(Failure::new(
+            "Screen unpaired",
+            &LoungeError::ScreenUnpaired
+        )))
+        .unwrap(),
+        json!({"Failed": {"message": "Screen unpaired", "kind": "Auth", "fatal": true}})
+    );
+}
+
+// Test a recorded long-poll body decodes into the events a client would emit
+#[test]
+fn test_parse_recording() {
+    let frame = |message: &str| format!("{}\n{}\n", message.len() + 1, message);
+    let recording = [
+        frame(r#"[[1,["nowPlaying",{"videoId":"dQw4w9WgXcQ","currentTime":"0","state":"3","cpn":"abc","duration":"212.0"}]]]"#),
+        frame(r#"[[2,["onStateChange",{"currentTime":"1.5","state":"1","cpn":"abc","duration":"212.0","loadedTime":"10"}]],[3,["onVolumeChanged",{"volume":"40","muted":"false"}]]]"#),
+        frame(r#"[[4,["noop"]]]"#),
+    ]
+    .concat();
+
+    let events = replay::parse_recording(recording.as_bytes());
+    let kinds: Vec<String> = events
+        .iter()
+        .map(|e| format!("{:?}", e).split('(').next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "NowPlaying",
+            "StateChange",
+            "PlaybackSession",
+            "VolumeChanged"
+        ]
+    );
+    match &events[2] {
+        LoungeEvent::PlaybackSession(session) => {
+            assert_eq!(session.video_id, "dQw4w9WgXcQ");
+            assert_eq!(session.current_time, 1.5);
+        }
+        other => panic!("Expected PlaybackSession, got {:?}", other),
+    }
+
+    // A truncated recording still yields the events before the cut
+    let truncated = &recording.as_bytes()[..recording.len() - 5];
+    assert_eq!(replay::parse_recording(truncated).len(), 4);
+}
+
+// Test stopping after playback reports the video's real duration, even when
+// only the state change carried it
+#[test]
+fn test_stop_after_play_session() {
+    let frame = |message: &str| format!("{}\n{}\n", message.len() + 1, message);
+    let recording = [
+        frame(r#"[[1,["nowPlaying",{"videoId":"dQw4w9WgXcQ","state":"1","cpn":"abc"}]]]"#),
+        frame(r#"[[2,["onStateChange",{"currentTime":"100.0","state":"1","cpn":"abc","duration":"212.0","loadedTime":"120"}]]]"#),
+        frame(r#"[[3,["nowPlaying",{"state":"-1"}]]]"#),
+    ]
+    .concat();
+
+    let sessions: Vec<PlaybackSession> = replay::parse_recording(recording.as_bytes())
+        .into_iter()
+        .filter_map(|event| match event {
+            LoungeEvent::PlaybackSession(session) => Some(session),
+            _ => None,
+        })
+        .collect();
+    let last = sessions.last().expect("a session for the stop");
+    assert_eq!(last.video_id, "dQw4w9WgXcQ");
+    assert_eq!(last.status(), PlaybackStatus::Stopped);
+    assert_eq!(last.duration, 212.0);
+    assert_eq!(last.cpn.as_deref(), Some("abc"));
+}
+
+// Test unknown events keep their type and payload, formatted only on demand
+#[test]
+fn test_unknown_event() {
+    let frame = |message: &str| format!("{}\n{}\n", message.len() + 1, message);
+    let recording = [
+        frame(r#"[[1,["onSomethingNew",{"flag":"true"}]]]"#),
+        frame(r#"[[2,["onSomethingNew",{"flag":"false"}]]]"#),
+    ]
+    .concat();
+
+    let events = replay::parse_recording(recording.as_bytes());
+    assert_eq!(events.len(), 2);
+    match &events[0] {
+        LoungeEvent::Unknown(unknown) => {
+            assert_eq!(unknown.event_type, "onSomethingNew");
+            assert_eq!(unknown.payload["flag"], "true");
+            assert_eq!(
+                unknown.to_string(),
+                r#"onSomethingNew - payload: {"flag":"true"}"#
+            );
+        }
+        other => panic!("Expected Unknown, got {:?}", other),
+    }
+}
+
+// Test repeated unknown events share their payload instead of copying it
+#[test]
+fn test_unknown_events_are_decoded_without_copying_payloads() {
+    let entries: Vec<String> = (0..1000).map(|i| format!("entry-{}", i)).collect();
+    let payload = json!({ "entries": entries });
+    let count = 20;
+    let recording = |event_type: &str| -> String {
+        (1..=count)
+            .map(|aid| {
+                let message = json!([[aid, [event_type, payload]]]).to_string();
+                format!("{}\n{}\n", message.len() + 1, message)
+            })
+            .collect()
+    };
+
+    // An "S" event whose payload isn't a session ID is decoded, then ignored
+    let (ignored_recording, unknown_recording) = (recording("S"), recording("onSomethingNew"));
+    let mut ignored = Vec::new();
+    let ignored_bytes = bytes_allocated_by(|| {
+        ignored = replay::parse_recording(ignored_recording.as_bytes());
+    });
+    let mut unknown = Vec::new();
+    let unknown_bytes = bytes_allocated_by(|| {
+        unknown = replay::parse_recording(unknown_recording.as_bytes());
+    });
+    assert!(ignored.is_empty());
+    assert_eq!(unknown.len(), count);
+    assert!(unknown
+        .iter()
+        .all(|event| matches!(event, LoungeEvent::Unknown(raw) if *raw.payload == payload)));
+
+    // Emitting the parsed payload must cost far less than copying it
+    let copy_bytes = bytes_allocated_by(|| drop(std::hint::black_box(payload.clone())));
+    let emit_bytes = unknown_bytes.saturating_sub(ignored_bytes) / count;
+    assert!(
+        emit_bytes < copy_bytes / 2,
+        "{} bytes per unknown event, a copy of its payload is {}",
+        emit_bytes,
+        copy_bytes
+    );
+}
+
+// Test partial sessions built from a single event
+#[test]
+fn test_partial_playback_sessions() {
+    let now_playing: NowPlaying = serde_json::from_value(json!({
+        "videoId": "dQw4w9WgXcQ",
+        "currentTime": "12.5",
+        "state": "1",
+        "cpn": "abc",
+        "listId": "PLtest"
+    }))
+    .unwrap();
+    let session = PlaybackSession::from_now_playing(&now_playing);
+    assert_eq!(session.video_id, "dQw4w9WgXcQ");
+    assert_eq!(session.current_time, 12.5);
+    assert_eq!(session.duration, 0.0);
+    assert_eq!(session.cpn.as_deref(), Some("abc"));
+    assert_eq!(session.list_id.as_deref(), Some("PLtest"));
+    assert!(session.video_data.is_some());
+
+    let state: PlaybackState = serde_json::from_value(json!({
+        "currentTime": "30.0",
+        "duration": "212.0",
+        "loadedTime": "60.0",
+        "state": "2",
+        "cpn": "abc"
+    }))
+    .unwrap();
+    let session = PlaybackSession::from_state(&state);
+    assert!(session.video_id.is_empty());
+    assert!(session.video_data.is_none());
+    assert_eq!(session.current_time, 30.0);
+    assert_eq!(session.duration, 212.0);
+    assert_eq!(session.loaded_time, 60.0);
+    assert_eq!(session.status(), PlaybackStatus::Paused);
+}
+
+// Test diffing player state snapshots reports only what changed
+#[test]
+fn test_current_state_diff() {
+    let before = CurrentState {
+        now_playing: Some(
+            serde_json::from_value(json!({
+                "videoId": "dQw4w9WgXcQ",
+                "currentTime": "10",
+                "duration": "212",
+                "state": "1"
+            }))
+            .unwrap(),
+        ),
+        volume: Some(VolumeChanged {
+            muted: "false".to_string(),
+            volume: "40".to_string(),
+        }),
+        ..CurrentState::default()
+    };
+    assert!(before.diff(&before.clone()).is_empty());
+
+    let mut after = before.clone();
+    after.volume = Some(VolumeChanged {
+        muted: "false".to_string(),
+        volume: "55".to_string(),
+    });
+    assert_eq!(
+        before.diff(&after),
+        StateDiff {
+            volume: true,
+            ..StateDiff::default()
+        }
+    );
+
+    after.now_playing = None;
+    let diff = before.diff(&after);
+    assert!(diff.video && diff.position && diff.volume);
+    assert!(!diff.status && !diff.playlist && !diff.ad);
+}
+
+// Test the playlistModified action is captured when present
+#[test]
+fn test_playlist_modified_action() {
+    let modified: PlaylistModified = serde_json::from_value(json!({
+        "currentIndex": "2",
+        "listId": "RQabc",
+        "videoId": "dQw4w9WgXcQ",
+        "action": "ADD"
+    }))
+    .unwrap();
+    assert_eq!(modified.action, Some(PlaylistAction::Added));
+
+    let moved: PlaylistModified =
+        serde_json::from_value(json!({"listId": "RQabc", "type": "reordered"})).unwrap();
+    assert_eq!(moved.action, Some(PlaylistAction::Reordered));
+
+    let bare: PlaylistModified = serde_json::from_value(json!({"listId": "RQabc"})).unwrap();
+    assert_eq!(bare.action, None);
+}
+
+// Test typed models parse string and plain JSON values alike, and match the
+// string models they convert from
+#[test]
+fn test_typed_models() {
+    use youtube_lounge_rs::v2;
+
+    let state: v2::PlaybackState = serde_json::from_value(json!({
+        "state": "1",
+        "currentTime": "42.5",
+        "duration": 180,
+        "loadedTime": "",
+        "cpn": "cpn1"
+    }))
+    .unwrap();
+    assert_eq!(state.state, PlaybackStatus::Playing);
+    assert_eq!(state.current_time, 42.5);
+    assert_eq!(state.duration, 180.0);
+    assert_eq!(state.loaded_time, 0.0);
+
+    let legacy: PlaybackState = serde_json::from_value(json!({
+        "state": "1",
+        "currentTime": "42.5",
+        "duration": "180",
+        "cpn": "cpn1"
+    }))
+    .unwrap();
+    assert_eq!(v2::PlaybackState::from(&legacy), state);
+
+    let volume: v2::VolumeChanged =
+        serde_json::from_value(json!({"muted": "true", "volume": "35"})).unwrap();
+    assert_eq!(
+        volume,
+        v2::VolumeChanged {
+            muted: true,
+            volume: 35
+        }
+    );
+    let legacy: VolumeChanged =
+        serde_json::from_value(json!({"muted": "true", "volume": "35"})).unwrap();
+    assert_eq!(v2::VolumeChanged::from(&legacy), volume);
+    let volume: v2::VolumeChanged =
+        serde_json::from_value(json!({"muted": false, "volume": 80})).unwrap();
+    assert_eq!(
+        volume,
+        v2::VolumeChanged {
+            muted: false,
+            volume: 80
+        }
+    );
+
+    let np: v2::NowPlaying = serde_json::from_value(json!({
+        "videoId": "vid",
+        "currentTime": "3",
+        "state": "2",
+        "listId": "RQ1"
+    }))
+    .unwrap();
+    assert_eq!(np.state, PlaybackStatus::Paused);
+    assert_eq!(np.current_time, 3.0);
+    assert_eq!(np.duration, None);
+    assert_eq!(np.list_id.as_deref(), Some("RQ1"));
+
+    let ad: v2::AdState = serde_json::from_value(json!({
+        "adState": "1",
+        "currentTime": "4.5",
+        "isSkipEnabled": "false"
+    }))
+    .unwrap();
+    assert_eq!(ad.ad_state, 1);
+    assert!(!ad.is_skip_enabled);
+
+    let legacy: PlaylistModeChanged =
+        serde_json::from_value(json!({"loopEnabled": "true", "shuffleEnabled": "false"})).unwrap();
+    assert!(legacy.is_loop_enabled() && !legacy.is_shuffle_enabled());
+    let mode = v2::PlaylistModeChanged::from(&legacy);
+    assert_eq!(
+        mode,
+        v2::PlaylistModeChanged {
+            loop_enabled: true,
+            shuffle_enabled: false
+        }
+    );
+    let parsed: v2::PlaylistModeChanged =
+        serde_json::from_value(json!({"loopEnabled": true})).unwrap();
+    assert_eq!(parsed, mode);
+
+    let quality: v2::VideoQualityChanged = serde_json::from_value(json!({
+        "availableQualityLevels": "[0,1080,720]",
+        "qualityLevel": "auto",
+        "videoId": "vid"
+    }))
+    .unwrap();
+    assert_eq!(quality.available_quality_levels, vec![0, 1080, 720]);
+    assert_eq!(quality.quality_level, None);
+}
+
+#[test]
+fn test_json_file_token_store() {
+    let path = std::env::temp_dir().join(format!("lounge-store-{}.json", std::process::id()));
+    // Files written by earlier versions of the examples carry extra fields
+    std::fs::write(
+        &path,
+        r#"{"screens":{"tv1":{"name":"Living Room","screen_id":"tv1","lounge_token":"t1","device_id":"dev","device_name":null,"enabled":true}}}"#,
+    )
+    .unwrap();
+    let store = JsonFileTokenStore::new(&path);
+
+    let tv1 = store.load("tv1").unwrap().unwrap();
+    assert_eq!(tv1.lounge_token, "t1");
+    assert_eq!(tv1.device_id.as_deref(), Some("dev"));
+    assert_eq!(store.load("tv2").unwrap(), None);
+
+    let screen = Screen {
+        name: None,
+        screen_id: "tv2".to_string(),
+        lounge_token: "t2".to_string(),
+    };
+    store.save(&StoredScreen::new(&screen, "dev")).unwrap();
+    let ids: Vec<_> = store
+        .screens()
+        .unwrap()
+        .into_iter()
+        .map(|s| s.screen_id)
+        .collect();
+    assert_eq!(ids, ["tv1", "tv2"]);
+
+    store.delete("tv1").unwrap();
+    store.delete("tv1").unwrap();
+    assert_eq!(store.load("tv1").unwrap(), None);
+    assert_eq!(store.load("tv2").unwrap().unwrap().lounge_token, "t2");
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(store.screens().unwrap().is_empty());
+}
+
+#[test]
+fn test_command_acknowledgement_matching() {
+    let state = |status: &str| {
+        LoungeEvent::StateChange(
+            serde_json::from_value(json!({"currentTime": "5", "state": status, "duration": "100"}))
+                .unwrap(),
+        )
+    };
+    let volume = |level: &str, muted: &str| {
+        LoungeEvent::VolumeChanged(
+            serde_json::from_value(json!({"volume": level, "muted": muted})).unwrap(),
+        )
+    };
+    let now_playing = LoungeEvent::NowPlaying(
+        serde_json::from_value(json!({"videoId": "abc", "state": "3"})).unwrap(),
+    );
+
+    assert_eq!(
+        PlaybackCommand::Pause.is_acknowledged_by(&state("2")),
+        Some(true)
+    );
+    assert_eq!(
+        PlaybackCommand::Pause.is_acknowledged_by(&state("1")),
+        Some(false)
+    );
+    assert_eq!(
+        PlaybackCommand::Play.is_acknowledged_by(&state("1")),
+        Some(true)
+    );
+    let set_volume = PlaybackCommand::SetVolume { volume: 30 };
+    assert_eq!(
+        set_volume.is_acknowledged_by(&volume("30", "false")),
+        Some(true)
+    );
+    assert_eq!(
+        set_volume.is_acknowledged_by(&volume("31", "false")),
+        Some(false)
+    );
+    assert_eq!(
+        PlaybackCommand::Mute.is_acknowledged_by(&volume("30", "true")),
+        Some(true)
+    );
+    assert_eq!(
+        PlaybackCommand::Unmute.is_acknowledged_by(&volume("30", "true")),
+        Some(false)
+    );
+    assert_eq!(
+        PlaybackCommand::set_playlist("abc".to_string()).is_acknowledged_by(&now_playing),
+        Some(true)
+    );
+    assert_eq!(
+        PlaybackCommand::set_playlist("xyz".to_string()).is_acknowledged_by(&now_playing),
+        Some(false)
+    );
+    assert_eq!(
+        PlaybackCommand::Next.is_acknowledged_by(&state("1")),
+        Some(false)
+    );
+
+    let dpad = PlaybackCommand::Dpad {
+        key: DpadKey::Enter,
+    };
+    assert!(!dpad.is_confirmable());
+    assert_eq!(dpad.is_acknowledged_by(&state("1")), None);
+}
+
+#[test]
+fn test_event_kind_sets() {
+    let set = EventKind::NowPlaying | EventKind::PlaybackSession;
+    assert!(set.contains(EventKind::NowPlaying));
+    assert!(set.contains(EventKind::PlaybackSession));
+    assert!(!set.contains(EventKind::StateChange));
+    assert_eq!(set | EventKind::Raw, set.with(EventKind::Raw));
+    assert_eq!(
+        [EventKind::NowPlaying, EventKind::PlaybackSession]
+            .into_iter()
+            .collect::<EventKindSet>(),
+        set
+    );
+    assert!(EventKindSet::empty().is_empty());
+    assert!(EventKindSet::all().contains(EventKind::Raw));
+    assert!(EventKindSet::from(EventKind::Unknown).contains(EventKind::Unknown));
+
+    assert_eq!(
+        LoungeEvent::ScreenDisconnected.kind(),
+        EventKind::ScreenDisconnected
+    );
+    assert_eq!(
+        LoungeEvent::SessionResumed {
+            video_id: "v".to_string(),
+            position: 1.0,
+        }
+        .kind(),
+        EventKind::SessionResumed
+    );
+}